@@ -0,0 +1,289 @@
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Directory within the notes directory that attachments are stored in.
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Identifies an attachment by the SHA-256 hash of its contents, so that a file attached to
+/// several notes, or several times, is stored once.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AttachmentId([u8; 32]);
+
+impl AttachmentId {
+    /// Returns the ID of an attachment with these contents.
+    pub fn for_contents(contents: &[u8]) -> Self {
+        Self(Sha256::digest(contents).into())
+    }
+
+    /// Returns the attachment a note refers to by its path relative to the notes directory,
+    /// such as `attachments/<hash>.png`.
+    pub fn from_source(source: &str) -> Option<Self> {
+        let file_name = source.strip_prefix(ATTACHMENTS_DIR)?.strip_prefix('/')?;
+        Self::from_file_name(file_name)
+    }
+
+    /// Returns the attachment stored in a file, named by its hash and extension.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let hash = file_name
+            .split_once('.')
+            .map_or(file_name, |(hash, _)| hash);
+        hash.parse().ok()
+    }
+
+    /// Returns the path a note refers to the attachment by, relative to the notes directory.
+    pub fn source(&self, extension: &str) -> String {
+        format!("{ATTACHMENTS_DIR}/{}", self.file_name(extension))
+    }
+
+    fn file_name(&self, extension: &str) -> String {
+        if extension.is_empty() {
+            self.to_string()
+        } else {
+            format!("{self}.{extension}")
+        }
+    }
+}
+
+impl fmt::Display for AttachmentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AttachmentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AttachmentId({self})")
+    }
+}
+
+/// The hash of an attachment wasn't 64 hexadecimal digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAttachmentIdError;
+
+impl fmt::Display for ParseAttachmentIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("attachment IDs are 64 hexadecimal digits")
+    }
+}
+
+impl std::error::Error for ParseAttachmentIdError {}
+
+impl FromStr for AttachmentId {
+    type Err = ParseAttachmentIdError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.len() != 64 || !text.is_ascii() {
+            return Err(ParseAttachmentIdError);
+        }
+
+        let mut hash = [0; 32];
+        for (byte, digits) in hash.iter_mut().zip(text.as_bytes().chunks(2)) {
+            let digits = std::str::from_utf8(digits).map_err(|_| ParseAttachmentIdError)?;
+            *byte = u8::from_str_radix(digits, 16).map_err(|_| ParseAttachmentIdError)?;
+        }
+        Ok(Self(hash))
+    }
+}
+
+/// Files attached to notes, stored in the notes directory under their hash and counted by the
+/// notes that refer to them.
+///
+/// Only the notes that were loaded are counted, so garbage collection is given the attachments
+/// the other notes refer to, and keeps them.
+pub struct AttachmentStore {
+    /// Directory the attachment files are stored in.
+    directory: PathBuf,
+    /// Number of references from notes to each attachment the store has seen.
+    reference_counts: HashMap<AttachmentId, usize>,
+}
+
+impl AttachmentStore {
+    /// Creates a store for the attachments of the notes in a directory.
+    pub fn new(notes_dir: &Path) -> Self {
+        Self {
+            directory: notes_dir.join(ATTACHMENTS_DIR),
+            reference_counts: HashMap::new(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Stores a file's contents, unless an identical file is already stored, returning the path
+    /// notes refer to it by. It's removed by the next garbage collection if no note refers to it
+    /// by then.
+    pub fn insert(&mut self, contents: &[u8], extension: &str) -> io::Result<String> {
+        let id = AttachmentId::for_contents(contents);
+        let file_name = id.file_name(extension);
+        let path = self.directory.join(&file_name);
+        if !path.exists() {
+            fs::create_dir_all(&self.directory)?;
+
+            // Written under another name first, so that a partly written file is never taken
+            // for the attachment
+            let partial_path = self.directory.join(format!("{file_name}.partial"));
+            fs::write(&partial_path, contents)?;
+            fs::rename(&partial_path, &path)?;
+        }
+
+        self.reference_counts.entry(id).or_insert(0);
+        Ok(id.source(extension))
+    }
+
+    pub fn reference_count(&self, id: AttachmentId) -> usize {
+        self.reference_counts.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Counts a note referring to an attachment.
+    pub fn add_reference(&mut self, id: AttachmentId) {
+        *self.reference_counts.entry(id).or_insert(0) += 1;
+    }
+
+    /// Stops counting a note referring to an attachment.
+    pub fn remove_reference(&mut self, id: AttachmentId) {
+        if let Some(count) = self.reference_counts.get_mut(&id) {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// Removes the files of attachments no note refers to anymore, returning their IDs.
+    ///
+    /// An attachment is only removed if the store counted no references to it and it isn't in
+    /// `uncounted_references`, the attachments referred to by notes that weren't counted, such
+    /// as note files that weren't opened.
+    pub fn collect_garbage(
+        &mut self,
+        uncounted_references: &BTreeSet<AttachmentId>,
+    ) -> io::Result<Vec<AttachmentId>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error),
+        };
+
+        let mut removed = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(AttachmentId::from_file_name)
+            else {
+                continue;
+            };
+            if self.reference_count(id) == 0 && !uncounted_references.contains(&id) {
+                fs::remove_file(entry.path())?;
+                removed.push(id);
+            }
+        }
+
+        self.reference_counts.retain(|_, count| *count > 0);
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an empty directory for a test's notes.
+    fn notes_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("attachments-{name}-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    #[test]
+    fn test_attachment_ids() {
+        let id = AttachmentId::for_contents(b"contents");
+        let source = id.source("png");
+        assert_eq!(source, format!("attachments/{id}.png"));
+        assert_eq!(AttachmentId::from_source(&source), Some(id));
+        assert_eq!(id.to_string().parse::<AttachmentId>(), Ok(id));
+        assert_eq!(AttachmentId::from_source("attachments/diagram.png"), None);
+        assert_eq!(
+            AttachmentId::from_source(&format!("elsewhere/{id}.png")),
+            None
+        );
+        assert_eq!(id.source(""), format!("attachments/{id}"));
+        assert_eq!("abc".parse::<AttachmentId>(), Err(ParseAttachmentIdError));
+    }
+
+    #[test]
+    fn test_insert() {
+        let dir = notes_dir("insert");
+        let mut store = AttachmentStore::new(&dir);
+
+        // Identical contents are stored once
+        let source = store.insert(b"image", "png").unwrap();
+        assert_eq!(store.insert(b"image", "png").unwrap(), source);
+        assert_eq!(fs::read(dir.join(&source)).unwrap(), b"image");
+        assert_eq!(fs::read_dir(store.directory()).unwrap().count(), 1);
+
+        let id = AttachmentId::from_source(&source).unwrap();
+        assert_eq!(id, AttachmentId::for_contents(b"image"));
+        assert_eq!(store.reference_count(id), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reference_counts() {
+        let mut store = AttachmentStore::new(&notes_dir("reference-counts"));
+        let id = AttachmentId::for_contents(b"image");
+
+        store.add_reference(id);
+        store.add_reference(id);
+        assert_eq!(store.reference_count(id), 2);
+        store.remove_reference(id);
+        assert_eq!(store.reference_count(id), 1);
+        store.remove_reference(id);
+        store.remove_reference(id);
+        assert_eq!(store.reference_count(id), 0);
+    }
+
+    #[test]
+    fn test_collect_garbage() {
+        let dir = notes_dir("collect-garbage");
+        let mut store = AttachmentStore::new(&dir);
+        assert_eq!(store.collect_garbage(&BTreeSet::new()).unwrap(), []);
+
+        let referenced = AttachmentId::from_source(&store.insert(b"kept", "png").unwrap());
+        let unreferenced = AttachmentId::from_source(&store.insert(b"removed", "png").unwrap());
+        let uncounted = AttachmentId::from_source(&store.insert(b"elsewhere", "png").unwrap());
+        let (referenced, unreferenced, uncounted) = (
+            referenced.unwrap(),
+            unreferenced.unwrap(),
+            uncounted.unwrap(),
+        );
+        store.add_reference(referenced);
+
+        // Attachments of notes that weren't counted are kept, like those counted
+        let removed = store.collect_garbage(&BTreeSet::from([uncounted])).unwrap();
+        assert_eq!(removed, [unreferenced]);
+        assert!(dir.join(referenced.source("png")).exists());
+        assert!(dir.join(uncounted.source("png")).exists());
+        assert!(!dir.join(unreferenced.source("png")).exists());
+
+        // Files not named by a hash are left alone
+        fs::write(store.directory().join("notes.txt"), "").unwrap();
+        store.remove_reference(referenced);
+        let removed = store.collect_garbage(&BTreeSet::new()).unwrap();
+        assert_eq!(
+            removed.into_iter().collect::<BTreeSet<_>>(),
+            [referenced, uncounted].into()
+        );
+        assert!(store.directory().join("notes.txt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
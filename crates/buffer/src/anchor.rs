@@ -0,0 +1,83 @@
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+    rc::{Rc, Weak},
+};
+
+/// Which side of an insertion at its position an anchor stays on.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Bias {
+    /// Stay before text inserted at the anchor.
+    #[default]
+    Left,
+    /// Move after text inserted at the anchor.
+    Right,
+}
+
+/// A position in a buffer that tracks edits made after it was created.
+///
+/// The buffer moves the anchor along with each edit for as long as the anchor or a clone of it
+/// is kept. Resolve it with [`Buffer::offset_for_anchor`](crate::Buffer::offset_for_anchor) to
+/// get its current offset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Anchor {
+    /// Current offset, shared with the buffer that moves it.
+    pub(crate) offset: Rc<Cell<usize>>,
+    pub bias: Bias,
+}
+
+/// The anchors a buffer created, which it moves with each edit.
+///
+/// Only weak references are kept, so anchors that were dropped are forgotten on the next edit.
+#[derive(Debug, Default)]
+pub(crate) struct Anchors(RefCell<Vec<(Weak<Cell<usize>>, Bias)>>);
+
+impl Clone for Anchors {
+    /// A copy of a buffer starts without anchors, as those of the original don't follow its
+    /// edits.
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl Anchors {
+    pub fn insert(&self, offset: usize, bias: Bias) -> Anchor {
+        let offset = Rc::new(Cell::new(offset));
+        self.0.borrow_mut().push((Rc::downgrade(&offset), bias));
+        Anchor { offset, bias }
+    }
+
+    /// Moves every anchor still in use across an edit.
+    pub fn apply(&mut self, edit: &Edit) {
+        self.0.get_mut().retain(|(offset, bias)| {
+            let Some(offset) = offset.upgrade() else {
+                return false;
+            };
+            offset.set(edit.transform_offset(offset.get(), *bias));
+            true
+        });
+    }
+}
+
+/// A single insertion or deletion, which anchors are moved across.
+#[derive(Clone, Debug)]
+pub(crate) struct Edit {
+    pub old_range: Range<usize>,
+    pub new_len: usize,
+}
+
+impl Edit {
+    /// Returns where an offset before the edit ends up after it.
+    pub fn transform_offset(&self, offset: usize, bias: Bias) -> usize {
+        if offset < self.old_range.start {
+            offset
+        } else if offset > self.old_range.end {
+            offset - self.old_range.len() + self.new_len
+        } else {
+            match bias {
+                Bias::Left => self.old_range.start,
+                Bias::Right => self.old_range.start + self.new_len,
+            }
+        }
+    }
+}
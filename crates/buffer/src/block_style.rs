@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+
+/// Styling that applies to a whole line, rather than a range of text.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlockStyle {
+    /// A heading, from level 1 for the most prominent to 3 for the least.
+    Heading(u8),
+    /// A quotation set apart from the surrounding text.
+    Quote,
+}
+
+/// Line-start markers and the block styles they stand for.
+const MARKERS: [(&str, BlockStyle); 4] = [
+    ("# ", BlockStyle::Heading(1)),
+    ("## ", BlockStyle::Heading(2)),
+    ("### ", BlockStyle::Heading(3)),
+    ("> ", BlockStyle::Quote),
+];
+
+impl BlockStyle {
+    /// Returns the marker a line with this style starts with in Markdown, such as `## `.
+    pub fn marker(self) -> &'static str {
+        match self {
+            Self::Heading(1) => "# ",
+            Self::Heading(2) => "## ",
+            Self::Heading(_) => "### ",
+            Self::Quote => "> ",
+        }
+    }
+
+    /// Splits the marker off the start of a line written in Markdown, returning the style it
+    /// stands for and the rest of the line.
+    pub fn strip_marker(line: &str) -> Option<(Self, &str)> {
+        MARKERS
+            .iter()
+            .find_map(|(marker, block_style)| Some((*block_style, line.strip_prefix(marker)?)))
+    }
+}
@@ -1,10 +1,37 @@
+mod anchor;
+mod block_style;
+mod code_block;
+mod divider;
 mod format_span;
+mod image;
+mod line_ending;
+mod markdown;
 mod selection;
-
+mod table;
+mod tag;
+mod wiki_link;
+
+pub use anchor::*;
+pub use attachments::AttachmentId;
+pub use block_style::*;
+pub use code_block::*;
+pub use divider::*;
 pub use format_span::*;
+pub use image::*;
+pub use line_ending::*;
+pub use markdown::*;
 pub use selection::*;
-
-use std::ops::Range;
+pub use table::*;
+pub use tag::*;
+pub use wiki_link::*;
+
+use gpui::{Context, EventEmitter};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, VecDeque},
+    ops::Range,
+    sync::Arc,
+};
 
 use text::{TextBuffer, TextPoint};
 
@@ -12,23 +39,95 @@ use text::{TextBuffer, TextPoint};
 pub struct Buffer {
     text: TextBuffer,
     format_spans: Vec<FormatSpan>,
+    version: usize,
+    /// Anchors into the text, moved with every insertion and deletion.
+    anchors: Anchors,
+    line_ending: LineEnding,
+    /// Style of each line, kept in step with the text's line breaks.
+    block_styles: Vec<Option<BlockStyle>>,
+    /// The most recent row edits, for per-row state elsewhere to catch up with.
+    row_edits: VecDeque<RowEdit>,
+    /// Number of row edits made before the oldest one kept.
+    dropped_row_edits: usize,
+    /// Fenced code blocks in the text, found again on first use after an edit.
+    code_blocks: RefCell<Option<Arc<[CodeBlock]>>>,
+}
+
+/// Number of recent row edits a buffer keeps. Anything synced with it less recently than that
+/// has to be rebuilt from the whole text.
+const MAX_ROW_EDITS: usize = 1024;
+
+/// The lines changed by a single insertion or deletion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowEdit {
+    /// The rows the edit touched, in the text as it was right before the edit.
+    pub old_rows: Range<usize>,
+    /// Number of rows the touched rows became.
+    pub new_row_count: usize,
+}
+
+/// Changes to a buffer that other parts of the app can subscribe to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BufferEvent {
+    /// Text was edited. Each range covers the new text of one edit, in the updated buffer.
+    Edited { ranges: Vec<Range<usize>> },
+    /// Formatting changed over a range, without changing the text.
+    FormatChanged { range: Range<usize> },
+    /// The line ending the buffer is saved with changed.
+    LineEndingChanged,
+    /// The buffer's contents were saved.
+    Saved,
+    /// The buffer's contents were replaced, such as after the note changed on disk.
+    Reloaded,
 }
 
+impl EventEmitter<BufferEvent> for Buffer {}
+
 impl Buffer {
     pub fn new() -> Self {
         Self {
             text: TextBuffer::new(),
             format_spans: Vec::new(),
+            version: 0,
+            anchors: Anchors::default(),
+            line_ending: LineEnding::default(),
+            block_styles: vec![None],
+            row_edits: VecDeque::new(),
+            dropped_row_edits: 0,
+            code_blocks: RefCell::default(),
         }
     }
 
+    /// Creates a buffer from a note's text, remembering its line ending and storing its line
+    /// breaks as `\n`.
     pub fn from_text(text: impl Into<String>) -> Self {
+        let text = text.into();
+        let text_buffer = TextBuffer::from(LineEnding::normalize(&text).as_ref());
+        let line_count = text_buffer.max_point().row + 1;
         Self {
-            text: TextBuffer::from(text.into().as_str()),
+            text: text_buffer,
             format_spans: Vec::new(),
+            version: 0,
+            anchors: Anchors::default(),
+            line_ending: LineEnding::detect(&text),
+            block_styles: vec![None; line_count],
+            row_edits: VecDeque::new(),
+            dropped_row_edits: 0,
+            code_blocks: RefCell::default(),
         }
     }
 
+    /// Creates a buffer from a note written in Markdown, reading its headings, quotes and inline
+    /// formatting into line styles and format spans.
+    pub fn from_markdown(markdown: &str) -> Self {
+        let parsed = parse_markdown(&LineEnding::normalize(markdown));
+        let mut buffer = Self::from_text(parsed.text);
+        buffer.line_ending = LineEnding::detect(markdown);
+        buffer.block_styles = parsed.block_styles;
+        buffer.format_spans = parsed.format_spans;
+        buffer
+    }
+
     pub fn byte_to_utf16(&self, byte_offset: usize) -> usize {
         self.text.byte_to_utf16(byte_offset)
     }
@@ -41,6 +140,11 @@ impl Buffer {
         self.text.len()
     }
 
+    /// Returns a counter that changes whenever the buffer is modified.
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
     pub fn is_empty(&self) -> bool {
         self.text.is_empty()
     }
@@ -49,6 +153,40 @@ impl Buffer {
         self.text.to_string()
     }
 
+    /// Returns the text as Markdown with line breaks in the note's line ending, as it should be
+    /// saved.
+    pub fn text_for_save(&self) -> String {
+        let markdown = to_markdown(&self.text(), &self.block_styles, &self.format_spans);
+        self.line_ending.apply(&markdown).into_owned()
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Changes the line ending the note is saved with.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding, cx: &mut Context<Self>) {
+        if self.line_ending != line_ending {
+            self.line_ending = line_ending;
+            self.version += 1;
+            cx.emit(BufferEvent::LineEndingChanged);
+            cx.notify();
+        }
+    }
+
+    /// Returns the number of row edits made so far, to catch up from with
+    /// [`Self::row_edits_since`].
+    pub fn row_edit_count(&self) -> usize {
+        self.dropped_row_edits + self.row_edits.len()
+    }
+
+    /// Returns the row edits made since there were `count` of them, in order, or `None` if
+    /// they're no longer all kept.
+    pub fn row_edits_since(&self, count: usize) -> Option<impl Iterator<Item = &RowEdit>> {
+        let start = count.checked_sub(self.dropped_row_edits)?;
+        (start <= self.row_edits.len()).then(|| self.row_edits.range(start..))
+    }
+
     pub fn line_count(&self) -> usize {
         self.text.max_point().row + 1
     }
@@ -78,23 +216,256 @@ impl Buffer {
         self.text.slice(range).to_string()
     }
 
+    /// Creates an anchor at an offset, which stays on the given side of text inserted there.
+    pub fn anchor_at(&self, offset: usize, bias: Bias) -> Anchor {
+        self.anchors.insert(offset.min(self.len()), bias)
+    }
+
+    /// Creates an anchor that stays before text inserted at its position.
+    pub fn anchor_before(&self, offset: usize) -> Anchor {
+        self.anchor_at(offset, Bias::Left)
+    }
+
+    /// Creates an anchor that moves after text inserted at its position.
+    pub fn anchor_after(&self, offset: usize) -> Anchor {
+        self.anchor_at(offset, Bias::Right)
+    }
+
+    /// Returns the current offset of an anchor, accounting for edits made since it was created.
+    pub fn offset_for_anchor(&self, anchor: &Anchor) -> usize {
+        anchor.offset.get().min(self.len())
+    }
+
     pub fn format_spans(&self) -> &[FormatSpan] {
         &self.format_spans
     }
 
-    pub fn insert(&mut self, offset: usize, text: &str) {
+    /// Returns the style of a whole line, such as a heading.
+    pub fn block_style(&self, row: usize) -> Option<BlockStyle> {
+        self.block_styles.get(row).copied().flatten()
+    }
+
+    /// Returns the image shown on a line, if the line holds nothing but an image.
+    pub fn image_block(&self, row: usize) -> Option<ImageBlock> {
+        ImageBlock::parse(&self.line(row)?)
+    }
+
+    /// Returns the fenced code blocks in the text, in order.
+    pub fn code_blocks(&self) -> Arc<[CodeBlock]> {
+        self.code_blocks
+            .borrow_mut()
+            .get_or_insert_with(|| code_blocks(self.text().split('\n')).into())
+            .clone()
+    }
+
+    /// Returns whether a line is a divider drawn across the text. Lines inside code blocks are
+    /// code rather than dividers, and dashes directly under a line of text underline it as a
+    /// heading.
+    pub fn is_divider(&self, row: usize) -> bool {
+        let Some(line) = self.line(row) else {
+            return false;
+        };
+        let line_above = row.checked_sub(1).and_then(|row| self.line(row));
+
+        is_divider(&line)
+            && !line_above.is_some_and(|line_above| is_heading_underline(&line, &line_above))
+            && !self
+                .code_blocks()
+                .iter()
+                .any(|block| block.contains_row(row))
+    }
+
+    /// Returns the rows of the table a row is part of. A table is a run of rows written as
+    /// `| cell | cell |`, whose second row separates the header from the rest.
+    pub fn table_rows(&self, row: usize) -> Option<Range<usize>> {
+        let is_table_row =
+            |row: usize| self.line(row).and_then(|line| table_cells(&line)).is_some();
+        if !is_table_row(row) {
+            return None;
+        }
+
+        let mut start = row;
+        while start > 0 && is_table_row(start - 1) {
+            start -= 1;
+        }
+        let mut end = row + 1;
+        while end < self.line_count() && is_table_row(end) {
+            end += 1;
+        }
+
+        let separator = self.line(start + 1).unwrap_or_default();
+        is_table_separator(&separator).then_some(start..end)
+    }
+
+    /// Returns the attachments the text refers to, from its images and links.
+    pub fn attachments(&self) -> BTreeSet<AttachmentId> {
+        let images = (0..self.line_count()).filter_map(|row| self.image_block(row));
+        let image_sources = images.filter_map(|image| image.attachment());
+        let link_sources = self
+            .format_spans
+            .iter()
+            .filter_map(|span| AttachmentId::from_source(span.link.as_deref()?));
+        image_sources.chain(link_sources).collect()
+    }
+
+    /// Sets or clears the style of a whole line.
+    pub fn set_block_style(
+        &mut self,
+        row: usize,
+        block_style: Option<BlockStyle>,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(current) = self.block_styles.get_mut(row) else {
+            return;
+        };
+        if *current == block_style {
+            return;
+        }
+
+        *current = block_style;
+        self.version += 1;
+
+        let start = self.line_start(row);
+        cx.emit(BufferEvent::FormatChanged {
+            range: start..start + self.line_len(row),
+        });
+    }
+
+    pub fn insert(&mut self, offset: usize, text: &str, cx: &mut Context<Self>) {
+        self.insert_text(offset, text);
+        cx.emit(BufferEvent::Edited {
+            ranges: vec![offset..offset + text.len()],
+        });
+    }
+
+    pub fn remove(&mut self, range: Range<usize>, cx: &mut Context<Self>) {
+        self.remove_text(range.clone());
+        cx.emit(BufferEvent::Edited {
+            ranges: vec![range.start..range.start],
+        });
+    }
+
+    pub fn replace(&mut self, range: Range<usize>, text: &str, cx: &mut Context<Self>) {
+        self.remove_text(range.clone());
+        self.insert_text(range.start, text);
+        cx.emit(BufferEvent::Edited {
+            ranges: vec![range.start..range.start + text.len()],
+        });
+    }
+
+    /// Replaces several ranges at once, reporting them as a single edit.
+    ///
+    /// The ranges must be sorted and must not overlap.
+    pub fn edit(
+        &mut self,
+        edits: impl IntoIterator<Item = (Range<usize>, String)>,
+        cx: &mut Context<Self>,
+    ) {
+        let edits = edits.into_iter().collect::<Vec<_>>();
+        if edits.is_empty() {
+            return;
+        }
+
+        for (range, text) in edits.iter().rev() {
+            self.remove_text(range.clone());
+            self.insert_text(range.start, text);
+        }
+
+        let mut delta = 0isize;
+        let ranges = edits
+            .iter()
+            .map(|(range, text)| {
+                let start = range.start.saturating_add_signed(delta);
+                delta += text.len() as isize - range.len() as isize;
+                start..start + text.len()
+            })
+            .collect();
+        cx.emit(BufferEvent::Edited { ranges });
+    }
+
+    /// Replaces the whole text and formatting, such as after the note changed on disk.
+    ///
+    /// The note's line ending is detected again, and the format spans refer to the text with its
+    /// line breaks stored as `\n`.
+    pub fn reload(&mut self, text: &str, format_spans: Vec<FormatSpan>, cx: &mut Context<Self>) {
+        self.remove_text(0..self.len());
+        self.insert_text(0, &LineEnding::normalize(text));
+        self.format_spans = format_spans;
+        self.line_ending = LineEnding::detect(text);
+        self.block_styles = vec![None; self.line_count()];
+        cx.emit(BufferEvent::Reloaded);
+    }
+
+    /// Reports that the buffer's contents were saved.
+    pub fn did_save(&mut self, cx: &mut Context<Self>) {
+        cx.emit(BufferEvent::Saved);
+    }
+
+    fn insert_text(&mut self, offset: usize, text: &str) {
         let len = text.len();
+        if len == 0 {
+            return;
+        }
+
+        // New lines are unstyled. Inserting at the start of a line moves its style down with it
+        let point = self.offset_to_point(offset);
+        let new_lines = text.matches('\n').count();
+        if new_lines > 0 {
+            let row = if point.column == 0 {
+                point.row
+            } else {
+                point.row + 1
+            };
+            self.block_styles
+                .splice(row..row, std::iter::repeat_n(None, new_lines));
+        }
+
         self.text.insert(offset, text);
+        self.code_blocks.take();
+        self.push_row_edit(RowEdit {
+            old_rows: point.row..point.row + 1,
+            new_row_count: new_lines + 1,
+        });
+        self.anchors.apply(&Edit {
+            old_range: offset..offset,
+            new_len: len,
+        });
 
         let delta = len as isize;
         for span in &mut self.format_spans {
             span.shift_by_delta(offset, delta);
         }
+
+        self.version += 1;
     }
 
-    pub fn remove(&mut self, range: Range<usize>) {
+    fn remove_text(&mut self, range: Range<usize>) {
         let len = range.len();
+        if len == 0 {
+            return;
+        }
+
+        // Joined lines keep the style of the first one, unless it was removed from its start
+        let start = self.offset_to_point(range.start);
+        let end = self.offset_to_point(range.end);
+        if end.row > start.row {
+            if start.column == 0 {
+                self.block_styles.drain(start.row..end.row);
+            } else {
+                self.block_styles.drain(start.row + 1..end.row + 1);
+            }
+        }
+
         self.text.remove(range.clone());
+        self.code_blocks.take();
+        self.push_row_edit(RowEdit {
+            old_rows: start.row..end.row + 1,
+            new_row_count: 1,
+        });
+        self.anchors.apply(&Edit {
+            old_range: range.clone(),
+            new_len: 0,
+        });
 
         let delta = -(len as isize);
         for span in &mut self.format_spans {
@@ -102,14 +473,224 @@ impl Buffer {
         }
 
         self.format_spans.retain(|span| !span.range.is_empty());
+        self.version += 1;
+    }
+
+    fn push_row_edit(&mut self, row_edit: RowEdit) {
+        if self.row_edits.len() == MAX_ROW_EDITS {
+            self.row_edits.pop_front();
+            self.dropped_row_edits += 1;
+        }
+        self.row_edits.push_back(row_edit);
     }
 
-    pub fn replace(&mut self, range: Range<usize>, text: &str) {
-        self.remove(range.clone());
-        self.insert(range.start, text);
+    /// Returns the format spans overlapping a range, relative to the start of the range.
+    pub fn format_spans_in_range(&self, range: Range<usize>) -> Vec<FormatSpan> {
+        self.format_spans
+            .iter()
+            .filter(|span| span.overlaps(&range))
+            .map(|span| FormatSpan {
+                range: (span.range.start.max(range.start) - range.start)
+                    ..(span.range.end.min(range.end) - range.start),
+                ..span.clone()
+            })
+            .collect()
+    }
+
+    /// Inserts text with the given format spans, relative to the start of the text, instead of
+    /// extending the spans surrounding the insertion point over it.
+    pub fn insert_formatted(
+        &mut self,
+        offset: usize,
+        text: &str,
+        format_spans: &[FormatSpan],
+        cx: &mut Context<Self>,
+    ) {
+        self.insert_formatted_text(offset, text, format_spans);
+        cx.emit(BufferEvent::Edited {
+            ranges: vec![offset..offset + text.len()],
+        });
+    }
+
+    fn insert_formatted_text(&mut self, offset: usize, text: &str, format_spans: &[FormatSpan]) {
+        self.insert_text(offset, text);
+        let inserted = offset..offset + text.len();
+
+        self.format_spans = self
+            .format_spans
+            .drain(..)
+            .flat_map(|span| {
+                if !span.overlaps(&inserted) {
+                    return vec![span];
+                }
+
+                let mut parts = Vec::with_capacity(2);
+
+                if span.range.start < inserted.start {
+                    parts.push(FormatSpan {
+                        range: span.range.start..inserted.start,
+                        ..span.clone()
+                    });
+                }
+                if span.range.end > inserted.end {
+                    parts.push(FormatSpan {
+                        range: inserted.end..span.range.end,
+                        ..span
+                    });
+                }
+
+                parts
+            })
+            .collect();
+
+        self.format_spans
+            .extend(format_spans.iter().map(|span| FormatSpan {
+                range: span.range.start + offset..span.range.end + offset,
+                ..span.clone()
+            }));
+        self.format_spans.sort_by_key(|span| span.range.start);
+    }
+
+    /// Moves text and its formatting to `destination` as a single edit, returning its new range.
+    pub fn move_range(
+        &mut self,
+        range: Range<usize>,
+        destination: usize,
+        cx: &mut Context<Self>,
+    ) -> Range<usize> {
+        let version = self.version;
+        let text = self.slice(range.clone());
+        let format_spans = self.format_spans_in_range(range.clone());
+
+        self.remove_text(range.clone());
+        let destination = if destination > range.end {
+            destination - range.len()
+        } else {
+            destination.min(range.start)
+        };
+        self.insert_formatted_text(destination, &text, &format_spans);
+        self.version = version + 1;
+
+        let new_range = destination..destination + text.len();
+        let removed_at = if destination <= range.start {
+            range.start + text.len()
+        } else {
+            range.start
+        };
+        let mut ranges = vec![removed_at..removed_at, new_range.clone()];
+        ranges.sort_by_key(|range| range.start);
+        cx.emit(BufferEvent::Edited { ranges });
+
+        new_range
+    }
+
+    /// Copies text and its formatting to `destination` as a single edit, returning the range of
+    /// the copy.
+    pub fn copy_range(
+        &mut self,
+        range: Range<usize>,
+        destination: usize,
+        cx: &mut Context<Self>,
+    ) -> Range<usize> {
+        let text = self.slice(range.clone());
+        let format_spans = self.format_spans_in_range(range);
+        self.insert_formatted(destination, &text, &format_spans, cx);
+
+        destination..destination + text.len()
+    }
+
+    /// Moves whole lines, with their formatting and block styles, to start at `destination_row`,
+    /// counted with the lines taken out, as a single edit. Returns how far the lines' text moved.
+    pub fn move_lines(
+        &mut self,
+        rows: Range<usize>,
+        destination_row: usize,
+        cx: &mut Context<Self>,
+    ) -> isize {
+        let version = self.version;
+        let (old_start, new_start, len) = self.with_trailing_newline(|buffer| {
+            let range = buffer.line_start(rows.start)..buffer.line_start(rows.end);
+            let text = buffer.slice(range.clone());
+            let format_spans = buffer.format_spans_in_range(range.clone());
+            let block_styles = buffer.block_styles[rows.clone()].to_vec();
+
+            buffer.remove_text(range.clone());
+            let destination = buffer.line_start(destination_row);
+            buffer.insert_formatted_text(destination, &text, &format_spans);
+            buffer.block_styles[destination_row..destination_row + rows.len()]
+                .copy_from_slice(&block_styles);
+
+            (range.start, destination, text.len())
+        });
+        self.version = version + 1;
+
+        let start = old_start.min(new_start);
+        let end = (old_start.max(new_start) + len).min(self.len());
+        cx.emit(BufferEvent::Edited {
+            ranges: vec![start..end],
+        });
+
+        new_start as isize - old_start as isize
+    }
+
+    /// Inserts a copy of whole lines, with their formatting and block styles, above them,
+    /// returning the range of the copy.
+    pub fn duplicate_lines(&mut self, rows: Range<usize>, cx: &mut Context<Self>) -> Range<usize> {
+        let version = self.version;
+        let copy = self.with_trailing_newline(|buffer| {
+            let range = buffer.line_start(rows.start)..buffer.line_start(rows.end);
+            let text = buffer.slice(range.clone());
+            let format_spans = buffer.format_spans_in_range(range.clone());
+            let block_styles = buffer.block_styles[rows.clone()].to_vec();
+            buffer.insert_formatted_text(range.start, &text, &format_spans);
+            buffer.block_styles[rows.start..rows.end].copy_from_slice(&block_styles);
+
+            range.start..range.start + text.len()
+        });
+        self.version = version + 1;
+
+        cx.emit(BufferEvent::Edited {
+            ranges: vec![copy.clone()],
+        });
+
+        copy
+    }
+
+    /// Removes whole lines, along with the newline that separated them from the rest of the text.
+    pub fn delete_lines(&mut self, rows: Range<usize>, cx: &mut Context<Self>) {
+        let version = self.version;
+        let start = self.with_trailing_newline(|buffer| {
+            let range = buffer.line_start(rows.start)..buffer.line_start(rows.end);
+            buffer.remove_text(range.clone());
+            range.start
+        });
+        self.version = version + 1;
+
+        let start = start.min(self.len());
+        cx.emit(BufferEvent::Edited {
+            ranges: vec![start..start],
+        });
+    }
+
+    fn line_start(&self, row: usize) -> usize {
+        self.point_to_offset(TextPoint::new(row, 0))
+    }
+
+    /// Runs a line operation with a newline appended to the text, so the last line ends with a
+    /// newline like every other line.
+    fn with_trailing_newline<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.insert_text(self.len(), "\n");
+        let result = f(self);
+
+        let len = self.len();
+        if len > 0 && self.slice(len - 1..len) == "\n" {
+            self.remove_text(len - 1..len);
+        }
+
+        result
     }
 
-    pub fn toggle_bold(&mut self, range: Range<usize>) {
+    pub fn toggle_bold(&mut self, range: Range<usize>, cx: &mut Context<Self>) {
         let is_fully_bold = self.is_formatted_with(&range, |span| span.bold);
 
         let should_split = |span: &FormatSpan| {
@@ -134,7 +715,7 @@ impl Buffer {
                 if span.range.start < range.start {
                     parts.push(FormatSpan {
                         range: span.range.start..range.start,
-                        ..span
+                        ..span.clone()
                     });
                 }
                 if span.range.end > range.end {
@@ -150,16 +731,20 @@ impl Buffer {
 
         if !is_fully_bold {
             self.format_spans.push(FormatSpan {
-                range,
+                range: range.clone(),
                 bold: Some(true),
                 italic: None,
                 underline: None,
+                link: None,
             });
             self.format_spans.sort_by_key(|span| span.range.start);
         }
+
+        self.version += 1;
+        cx.emit(BufferEvent::FormatChanged { range });
     }
 
-    pub fn toggle_italic(&mut self, range: Range<usize>) {
+    pub fn toggle_italic(&mut self, range: Range<usize>, cx: &mut Context<Self>) {
         let is_fully_italic = self.is_formatted_with(&range, |span| span.italic);
 
         let should_split = |span: &FormatSpan| {
@@ -184,7 +769,7 @@ impl Buffer {
                 if span.range.start < range.start {
                     parts.push(FormatSpan {
                         range: span.range.start..range.start,
-                        ..span
+                        ..span.clone()
                     });
                 }
                 if span.range.end > range.end {
@@ -200,16 +785,20 @@ impl Buffer {
 
         if !is_fully_italic {
             self.format_spans.push(FormatSpan {
-                range,
+                range: range.clone(),
                 bold: None,
                 italic: Some(true),
                 underline: None,
+                link: None,
             });
             self.format_spans.sort_by_key(|span| span.range.start);
         }
+
+        self.version += 1;
+        cx.emit(BufferEvent::FormatChanged { range });
     }
 
-    pub fn toggle_underline(&mut self, range: Range<usize>) {
+    pub fn toggle_underline(&mut self, range: Range<usize>, cx: &mut Context<Self>) {
         let is_fully_underline = self.is_formatted_with(&range, |span| span.underline);
 
         let should_split = |span: &FormatSpan| {
@@ -234,7 +823,7 @@ impl Buffer {
                 if span.range.start < range.start {
                     parts.push(FormatSpan {
                         range: span.range.start..range.start,
-                        ..span
+                        ..span.clone()
                     });
                 }
                 if span.range.end > range.end {
@@ -250,13 +839,69 @@ impl Buffer {
 
         if !is_fully_underline {
             self.format_spans.push(FormatSpan {
-                range,
+                range: range.clone(),
                 bold: None,
                 italic: None,
                 underline: Some(true),
+                link: None,
+            });
+            self.format_spans.sort_by_key(|span| span.range.start);
+        }
+
+        self.version += 1;
+        cx.emit(BufferEvent::FormatChanged { range });
+    }
+
+    /// Links a range to a URL, or removes the links over it when `url` is `None`.
+    pub fn set_link(&mut self, range: Range<usize>, url: Option<String>, cx: &mut Context<Self>) {
+        self.format_spans = self
+            .format_spans
+            .drain(..)
+            .flat_map(|span| {
+                if span.link.is_none() || !span.overlaps(&range) {
+                    return vec![span];
+                }
+
+                let mut parts = Vec::with_capacity(2);
+
+                if span.range.start < range.start {
+                    parts.push(FormatSpan {
+                        range: span.range.start..range.start,
+                        ..span.clone()
+                    });
+                }
+                if span.range.end > range.end {
+                    parts.push(FormatSpan {
+                        range: range.end..span.range.end,
+                        ..span
+                    });
+                }
+
+                parts
+            })
+            .collect();
+
+        if let Some(url) = url
+            && !range.is_empty()
+        {
+            self.format_spans.push(FormatSpan {
+                link: Some(url),
+                ..FormatSpan::new(range.clone())
             });
             self.format_spans.sort_by_key(|span| span.range.start);
         }
+
+        self.version += 1;
+        cx.emit(BufferEvent::FormatChanged { range });
+    }
+
+    /// Returns the range and URL of the link whose text contains or ends at `offset`.
+    pub fn link_at(&self, offset: usize) -> Option<(Range<usize>, &str)> {
+        self.format_spans.iter().find_map(|span| {
+            let url = span.link.as_deref()?;
+            (span.range.start <= offset && offset <= span.range.end)
+                .then(|| (span.range.clone(), url))
+        })
     }
 
     fn is_formatted_with<F>(&self, range: &Range<usize>, predicate: F) -> bool
@@ -0,0 +1,71 @@
+use std::ops::Range;
+
+/// Fence that opens and closes a code block.
+const FENCE: &str = "```";
+
+/// A block of code fenced by lines of three backticks, the opening one optionally followed by
+/// the language of the code:
+///
+/// ````text
+/// ```rust
+/// fn main() {}
+/// ```
+/// ````
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// All of the block's rows, including its fences.
+    pub rows: Range<usize>,
+    /// The rows of code between the fences. A block that isn't closed runs to the end of the
+    /// text.
+    pub code_rows: Range<usize>,
+    /// The language tag after the opening fence, such as `rust`.
+    pub language: Option<String>,
+}
+
+impl CodeBlock {
+    pub fn contains_row(&self, row: usize) -> bool {
+        self.rows.contains(&row)
+    }
+
+    /// Returns whether a row is one of the block's fences.
+    pub fn is_fence(&self, row: usize) -> bool {
+        self.contains_row(row) && !self.code_rows.contains(&row)
+    }
+}
+
+/// Returns the code blocks in a text's lines, in order.
+pub fn code_blocks<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open_block: Option<(usize, Option<String>)> = None;
+    let mut row_count = 0;
+
+    for (row, line) in lines.into_iter().enumerate() {
+        row_count = row + 1;
+        let Some(info) = line.trim_start().strip_prefix(FENCE) else {
+            continue;
+        };
+
+        match open_block.take() {
+            Some((start, language)) if info.trim().is_empty() => blocks.push(CodeBlock {
+                rows: start..row + 1,
+                code_rows: start + 1..row,
+                language,
+            }),
+            Some(open) => open_block = Some(open),
+            None => {
+                let language = info.split_whitespace().next().map(str::to_string);
+                open_block = Some((row, language));
+            }
+        }
+    }
+
+    if let Some((start, language)) = open_block {
+        blocks.push(CodeBlock {
+            rows: start..row_count,
+            code_rows: start + 1..row_count,
+            language,
+        });
+    }
+
+    blocks
+}
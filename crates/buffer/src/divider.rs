@@ -0,0 +1,33 @@
+/// How a divider is written when inserted.
+pub const DIVIDER: &str = "---";
+
+/// Returns whether a line is a divider, written as three or more of the same `-`, `*` or `_`
+/// alone on the line, optionally with spaces between them, such as `---` or `* * *`.
+pub fn is_divider(line: &str) -> bool {
+    let mut chars = line.chars().filter(|char| !char.is_whitespace());
+    let Some(first) = chars.next() else {
+        return false;
+    };
+
+    let mut count = 1;
+    for char in chars {
+        if char != first {
+            return false;
+        }
+        count += 1;
+    }
+    count >= 3 && matches!(first, '-' | '*' | '_')
+}
+
+/// Returns whether a line of dashes written directly under `line_above` underlines it as a
+/// heading, which is how Markdown reads `---` under a line of a paragraph, rather than being a
+/// divider.
+pub fn is_heading_underline(line: &str, line_above: &str) -> bool {
+    let line = line.trim();
+    let line_above = line_above.trim();
+    !line.is_empty()
+        && line.chars().all(|char| char == '-')
+        && !line_above.is_empty()
+        && !is_divider(line_above)
+        && !line_above.starts_with(['#', '`'])
+}
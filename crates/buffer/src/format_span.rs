@@ -1,12 +1,14 @@
 use serde::{Deserialize, Serialize};
 use std::ops::Range;
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct FormatSpan {
     pub range: Range<usize>,
     pub bold: Option<bool>,
     pub italic: Option<bool>,
     pub underline: Option<bool>,
+    /// The URL the text links to.
+    pub link: Option<String>,
 }
 
 impl FormatSpan {
@@ -16,11 +18,15 @@ impl FormatSpan {
             bold: None,
             italic: None,
             underline: None,
+            link: None,
         }
     }
 
     pub fn has_formatting(&self) -> bool {
-        self.bold == Some(true) || self.italic == Some(true) || self.underline == Some(true)
+        self.bold == Some(true)
+            || self.italic == Some(true)
+            || self.underline == Some(true)
+            || self.link.is_some()
     }
 
     pub fn overlaps(&self, other: &Range<usize>) -> bool {
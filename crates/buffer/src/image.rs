@@ -0,0 +1,111 @@
+use attachments::AttachmentId;
+
+/// An image shown as a block of its own, written alone on a line as `![alt](source)`, or as
+/// `![alt|640x480](source)` with the size it's shown at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageBlock {
+    pub alt: String,
+    /// Where the image is stored, such as the path of an attachment relative to the note.
+    pub source: String,
+    pub size: Option<ImageSize>,
+}
+
+/// The size of an image, in pixels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageBlock {
+    /// Parses a line holding nothing but an image.
+    pub fn parse(line: &str) -> Option<Self> {
+        let rest = line.trim().strip_prefix("![")?;
+        let (label, rest) = rest.split_once("](")?;
+        let source = rest.strip_suffix(')')?;
+        if source.is_empty() || source.contains(char::is_whitespace) || label.contains(']') {
+            return None;
+        }
+
+        let (alt, size) = match label.rsplit_once('|') {
+            Some((alt, size)) => (alt, Some(ImageSize::parse(size)?)),
+            None => (label, None),
+        };
+
+        Some(Self {
+            alt: alt.to_string(),
+            source: source.to_string(),
+            size,
+        })
+    }
+
+    /// Returns the attachment the image is stored as, if it's stored in the notes directory.
+    pub fn attachment(&self) -> Option<AttachmentId> {
+        AttachmentId::from_source(&self.source)
+    }
+
+    /// Formats the image as the line it's written as.
+    pub fn to_markdown(&self) -> String {
+        match self.size {
+            Some(size) => format!(
+                "![{}|{}x{}]({})",
+                self.alt, size.width, size.height, self.source
+            ),
+            None => format!("![{}]({})", self.alt, self.source),
+        }
+    }
+}
+
+impl ImageSize {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// Parses a size written as `640x480`.
+    fn parse(text: &str) -> Option<Self> {
+        let (width, height) = text.split_once('x')?;
+        let size = Self::new(width.parse().ok()?, height.parse().ok()?);
+        (size.width > 0 && size.height > 0).then_some(size)
+    }
+
+    /// Returns the size scaled to a width, keeping its aspect ratio.
+    pub fn scaled_to_width(self, width: u32) -> Self {
+        let width = width.max(1);
+        let height = (self.height as u64 * width as u64 / self.width.max(1) as u64).max(1);
+        Self::new(width, height as u32)
+    }
+
+    /// Reads the size of a PNG, GIF, JPEG or BMP image from its header.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let be_u16 = |ix: usize| Some(u16::from_be_bytes(bytes.get(ix..ix + 2)?.try_into().ok()?));
+        let le_u16 = |ix: usize| Some(u16::from_le_bytes(bytes.get(ix..ix + 2)?.try_into().ok()?));
+        let be_u32 = |ix: usize| Some(u32::from_be_bytes(bytes.get(ix..ix + 4)?.try_into().ok()?));
+        let le_i32 = |ix: usize| Some(i32::from_le_bytes(bytes.get(ix..ix + 4)?.try_into().ok()?));
+
+        let size = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Self::new(be_u32(16)?, be_u32(20)?)
+        } else if bytes.starts_with(b"GIF8") {
+            Self::new(le_u16(6)?.into(), le_u16(8)?.into())
+        } else if bytes.starts_with(b"BM") {
+            Self::new(le_i32(18)?.unsigned_abs(), le_i32(22)?.unsigned_abs())
+        } else if bytes.starts_with(&[0xff, 0xd8]) {
+            // The size is in the first start-of-frame segment, after any other segments
+            let mut ix = 2;
+            loop {
+                let (&0xff, &marker) = (bytes.get(ix)?, bytes.get(ix + 1)?) else {
+                    return None;
+                };
+                let is_start_of_frame =
+                    (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker);
+                if is_start_of_frame {
+                    break Self::new(be_u16(ix + 7)?.into(), be_u16(ix + 5)?.into());
+                }
+                ix += 2 + be_u16(ix + 2)? as usize;
+            }
+        } else {
+            return None;
+        };
+
+        (size.width > 0 && size.height > 0).then_some(size)
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// The line break style of a note, kept so saving writes back the style it was loaded with.
+///
+/// Buffers always store `\n` internally, whatever the note used.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum LineEnding {
+    /// Lines end with `\n`.
+    #[default]
+    Unix,
+    /// Lines end with `\r\n`.
+    Windows,
+}
+
+impl LineEnding {
+    /// Returns the line ending a text uses, going by its first line break.
+    pub fn detect(text: &str) -> Self {
+        match text.find('\n') {
+            Some(ix) if text[..ix].ends_with('\r') => Self::Windows,
+            _ => Self::Unix,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unix => "\n",
+            Self::Windows => "\r\n",
+        }
+    }
+
+    /// Returns a short name for the line ending, such as shown in the status bar.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Unix => "LF",
+            Self::Windows => "CRLF",
+        }
+    }
+
+    /// Replaces every `\r\n` and lone `\r` in a text with `\n`.
+    pub fn normalize(text: &str) -> Cow<'_, str> {
+        if text.contains('\r') {
+            Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// Converts a text with `\n` line breaks to use this line ending.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::Unix => Cow::Borrowed(text),
+            Self::Windows => Cow::Owned(text.replace('\n', "\r\n")),
+        }
+    }
+}
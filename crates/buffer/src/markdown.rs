@@ -0,0 +1,719 @@
+use std::{collections::HashSet, iter, ops::Range};
+
+use crate::{
+    BlockStyle, CodeBlock, FormatSpan, ImageBlock, code_blocks, code_spans, is_divider, wiki_links,
+};
+
+/// Characters a backslash before them keeps from being read as Markdown.
+const ESCAPABLE: [char; 9] = ['\\', '*', '_', '#', '>', '[', ']', '<', '!'];
+
+/// Characters a backslash before them keeps from ending a link's URL, or from starting a wiki
+/// link in it.
+const URL_ESCAPABLE: [char; 6] = ['\\', '(', ')', '<', '>', '['];
+
+/// A note's text with its line styles and inline formatting, read from Markdown.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParsedMarkdown {
+    pub text: String,
+    /// The style of each line of the text.
+    pub block_styles: Vec<Option<BlockStyle>>,
+    pub format_spans: Vec<FormatSpan>,
+}
+
+/// Writes a text as Markdown, with its line styles as markers such as `# ` and its inline
+/// formatting as markers such as `**` and `[text](url)`. Characters of the text that Markdown
+/// would read as markers are escaped, so [`parse_markdown`] reads back the same text and
+/// formatting.
+///
+/// Code blocks, images and dividers are written as they are, without their formatting.
+pub fn to_markdown(
+    text: &str,
+    block_styles: &[Option<BlockStyle>],
+    format_spans: &[FormatSpan],
+) -> String {
+    let lines = text.split('\n').collect::<Vec<_>>();
+    let code_blocks = code_blocks(lines.iter().copied());
+    let mut markdown = String::with_capacity(text.len());
+    let mut line_start = 0;
+
+    for (row, line) in lines.iter().enumerate() {
+        if row > 0 {
+            markdown.push('\n');
+        }
+        if is_verbatim(row, line, &code_blocks) {
+            markdown.push_str(line);
+        } else {
+            let block_style = block_styles.get(row).copied().flatten();
+            markdown.push_str(&write_line(line, line_start, block_style, format_spans));
+        }
+        line_start += line.len() + 1;
+    }
+
+    markdown
+}
+
+/// Reads Markdown into its text, line styles and inline formatting, undoing [`to_markdown`].
+pub fn parse_markdown(markdown: &str) -> ParsedMarkdown {
+    let lines = markdown.split('\n').collect::<Vec<_>>();
+    let code_blocks = code_blocks(lines.iter().copied());
+    let mut parsed = ParsedMarkdown::default();
+
+    for (row, line) in lines.iter().enumerate() {
+        if row > 0 {
+            parsed.text.push('\n');
+        }
+        if is_verbatim(row, line, &code_blocks) {
+            parsed.text.push_str(line);
+            parsed.block_styles.push(None);
+            continue;
+        }
+
+        let (block_style, content) = match BlockStyle::strip_marker(line) {
+            Some((block_style, content)) => (Some(block_style), content),
+            None => (None, *line),
+        };
+        let line_start = parsed.text.len();
+        let mut parser = InlineParser::new(content);
+        parser.parse(0, None);
+
+        parsed.text.push_str(&parser.text);
+        parsed.block_styles.push(block_style);
+        parsed
+            .format_spans
+            .extend(parser.format_spans.into_iter().map(|mut span| {
+                span.range = span.range.start + line_start..span.range.end + line_start;
+                span
+            }));
+    }
+
+    parsed.format_spans.sort_by_key(|span| span.range.start);
+    parsed
+}
+
+/// Returns whether a line is written the same in the text and in Markdown, because it's code or
+/// a block that's drawn from its text.
+fn is_verbatim(row: usize, line: &str, code_blocks: &[CodeBlock]) -> bool {
+    code_blocks.iter().any(|block| block.contains_row(row))
+        || ImageBlock::parse(line).is_some()
+        || is_divider(line)
+}
+
+/// Returns the ranges of a line that are written as they are, in order: its code spans and its
+/// wiki links. In a line of Markdown, a `[` escaped with a backslash doesn't start a wiki link.
+fn literal_ranges(line: &str, is_markdown: bool) -> Vec<Range<usize>> {
+    let code_spans = code_spans(line);
+
+    // Wiki links are looked for with code and escaped characters blanked out
+    let mut blanked = String::with_capacity(line.len());
+    let mut chars = line.char_indices().peekable();
+    while let Some((ix, char)) = chars.next() {
+        if code_spans.iter().any(|span| span.contains(&ix)) {
+            blanked.extend(iter::repeat_n(' ', char.len_utf8()));
+            continue;
+        }
+        blanked.push(char);
+        if is_markdown
+            && char == '\\'
+            && let Some((_, escaped)) = chars.next_if(|(_, next)| ESCAPABLE.contains(next))
+        {
+            blanked.push(if escaped == '[' { ' ' } else { escaped });
+        }
+    }
+
+    let mut ranges = code_spans;
+    ranges.extend(wiki_links(&blanked).into_iter().map(|link| link.range));
+    ranges.sort_by_key(|range| range.start);
+
+    let mut literal_ranges: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        if literal_ranges
+            .last()
+            .is_none_or(|last| last.end <= range.start)
+        {
+            literal_ranges.push(range);
+        }
+    }
+    literal_ranges
+}
+
+/// The inline formatting of part of a line, as far as Markdown can write it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Format {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    link: Option<String>,
+}
+
+/// A kind of inline formatting, written between an opening and a closing marker.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Style {
+    Link,
+    Underline,
+    Bold,
+    Italic,
+}
+
+impl Style {
+    /// The order styles are opened in where several start together, outermost first.
+    const ALL: [Self; 4] = [Self::Link, Self::Underline, Self::Bold, Self::Italic];
+
+    fn is_set(self, format: &Format) -> bool {
+        match self {
+            Self::Link => format.link.is_some(),
+            Self::Underline => format.underline,
+            Self::Bold => format.bold,
+            Self::Italic => format.italic,
+        }
+    }
+
+    /// Returns whether the style stays open from a piece with the `previous` format to one with
+    /// `format`. A link is closed where its URL changes.
+    fn keeps(self, previous: &Format, format: &Format) -> bool {
+        self.is_set(format) && (self != Self::Link || previous.link == format.link)
+    }
+
+    /// Sets the style on a span, other than a link, whose URL is set by the caller.
+    fn set(self, span: &mut FormatSpan) {
+        match self {
+            Self::Link => {}
+            Self::Underline => span.underline = Some(true),
+            Self::Bold => span.bold = Some(true),
+            Self::Italic => span.italic = Some(true),
+        }
+    }
+
+    fn clear(self, format: &mut Format) {
+        match self {
+            Self::Link => format.link = None,
+            Self::Underline => format.underline = false,
+            Self::Bold => format.bold = false,
+            Self::Italic => format.italic = false,
+        }
+    }
+
+    /// Returns whether the style's markers have to be next to text rather than whitespace.
+    fn needs_text_at_ends(self) -> bool {
+        !matches!(self, Self::Link | Self::Underline)
+    }
+}
+
+/// Part of a line that's written as a whole: a single character, a run of backticks, or a
+/// literal range.
+struct Piece {
+    range: Range<usize>,
+    format: Format,
+    is_literal: bool,
+}
+
+/// Writes a line that isn't written as it is, starting at `line_start` in the text.
+fn write_line(
+    line: &str,
+    line_start: usize,
+    block_style: Option<BlockStyle>,
+    format_spans: &[FormatSpan],
+) -> String {
+    let line_range = line_start..line_start + line.len();
+    let format_spans = format_spans
+        .iter()
+        .filter(|span| span.overlaps(&line_range))
+        .collect::<Vec<_>>();
+    let format_at = |offset: usize| {
+        let offset = line_start + offset;
+        let covering = || {
+            format_spans
+                .iter()
+                .filter(|span| span.range.start <= offset && offset < span.range.end)
+        };
+        Format {
+            bold: covering().any(|span| span.bold == Some(true)),
+            italic: covering().any(|span| span.italic == Some(true)),
+            underline: covering().any(|span| span.underline == Some(true)),
+            link: covering().find_map(|span| span.link.clone()),
+        }
+    };
+
+    let literal_ranges = literal_ranges(line, false);
+    let mut pieces = Vec::new();
+    let mut ix = 0;
+    while ix < line.len() {
+        // Backticks that don't make up a code span are kept together, so that markers can't
+        // split them into a span's opening and closing backticks
+        let range = match literal_ranges.iter().find(|range| range.start == ix) {
+            Some(range) => range.clone(),
+            None if line[ix..].starts_with('`') => {
+                ix..line.len() - line[ix..].trim_start_matches('`').len()
+            }
+            None => ix..ix + line[ix..].chars().next().map_or(1, char::len_utf8),
+        };
+        let is_literal = literal_ranges.contains(&range);
+        let mut format = format_at(ix);
+        // A wiki link is left out of links, which would otherwise start with `[[[`
+        if is_literal && line[range.clone()].starts_with('[') {
+            format.link = None;
+        }
+        pieces.push(Piece {
+            range: range.clone(),
+            format,
+            is_literal,
+        });
+        ix = range.end;
+    }
+    trim_styles(line, &mut pieces);
+
+    let mut markdown = String::with_capacity(line.len());
+    let mut open_styles: Vec<(Style, String)> = Vec::new();
+    let no_format = Format::default();
+    let last_link = pieces.iter().rposition(|piece| piece.format.link.is_some());
+    for (ix, piece) in pieces.iter().enumerate() {
+        // Close the styles the piece doesn't have, with the styles opened after them, then open
+        // the ones it's missing.
+        let previous_format = ix
+            .checked_sub(1)
+            .map_or(&no_format, |ix| &pieces[ix].format);
+        if let Some(first_unset) = open_styles
+            .iter()
+            .position(|(style, _)| !style.keeps(previous_format, &piece.format))
+        {
+            for (_, closer) in open_styles.drain(first_unset..).rev() {
+                markdown.push_str(&closer);
+            }
+        }
+        for style in Style::ALL {
+            if style.is_set(&piece.format) && !open_styles.iter().any(|(open, _)| *open == style) {
+                let (opener, closer) = match style {
+                    Style::Link => ("[", link_closer(piece.format.link.as_deref().unwrap_or(""))),
+                    Style::Underline => ("<u>", "</u>".to_string()),
+                    Style::Bold => ("**", "**".to_string()),
+                    Style::Italic => {
+                        let marker = italic_marker(line, &pieces[ix..]);
+                        (marker, marker.to_string())
+                    }
+                };
+                markdown.push_str(opener);
+                open_styles.push((style, closer));
+            }
+        }
+
+        let text = &line[piece.range.clone()];
+        if piece.is_literal {
+            markdown.push_str(text);
+            continue;
+        }
+
+        // Markers may be written next to a piece whose formatting differs from its neighbor's,
+        // which is taken as the `[` that may open a link, or otherwise as a `*`, when deciding
+        // whether to escape it.
+        let marker_before = |format: &Format| if format.link.is_some() { '[' } else { '*' };
+        let previous = match ix.checked_sub(1).map(|ix| &pieces[ix]) {
+            Some(previous) if previous.format != piece.format => Some(marker_before(&piece.format)),
+            Some(previous) => line[previous.range.clone()].chars().next_back(),
+            None if piece.format != no_format => Some(marker_before(&piece.format)),
+            None => None,
+        };
+        let next = match pieces.get(ix + 1) {
+            Some(next) if next.format != piece.format => Some(marker_before(&next.format)),
+            Some(next) => line[next.range.clone()].chars().next(),
+            None if piece.format != no_format => Some('*'),
+            None => None,
+        };
+        let after = &line[piece.range.end..];
+        let is_link = piece.format.link.is_some();
+        for char in text.chars() {
+            let escape = match char {
+                '<' => after.starts_with("u>") || after.starts_with("/u>"),
+                // Keeps brackets from being read as a link, or as the end of one
+                '[' if !is_link
+                    && (last_link.is_some_and(|last| last > ix) || after.contains("](")) =>
+                {
+                    true
+                }
+                ']' => is_link,
+                _ => false,
+            };
+            if escape || needs_escape(char, previous, next) {
+                markdown.push('\\');
+            }
+            markdown.push(char);
+        }
+    }
+    for (_, closer) in open_styles.into_iter().rev() {
+        markdown.push_str(&closer);
+    }
+
+    match block_style {
+        Some(block_style) => markdown.insert_str(0, block_style.marker()),
+        None if BlockStyle::strip_marker(&markdown).is_some() => markdown.insert(0, '\\'),
+        None => {}
+    }
+    markdown
+}
+
+/// Leaves whitespace out of the styles next to their markers, since Markdown doesn't read
+/// `** bold **` as bold. Styles are opened and closed as they're written by [`write_line`].
+fn trim_styles(line: &str, pieces: &mut [Piece]) {
+    let is_whitespace =
+        |piece: &Piece| !piece.is_literal && line[piece.range.clone()].trim().is_empty();
+
+    // Leaving a style out of a piece can close a style that was open around it, so repeat
+    // until every marker is next to text
+    let mut trimmed = true;
+    while trimmed {
+        trimmed = false;
+        let mut open_styles: Vec<Style> = Vec::new();
+        for ix in 0..=pieces.len() {
+            let format = pieces.get(ix).map(|piece| piece.format.clone());
+            let format = format.unwrap_or_default();
+            let previous = ix.checked_sub(1).map(|ix| pieces[ix].format.clone());
+            let previous = previous.unwrap_or_default();
+            if let Some(first_unset) = open_styles
+                .iter()
+                .position(|style| !style.keeps(&previous, &format))
+            {
+                for style in open_styles.drain(first_unset..) {
+                    let previous = &mut pieces[ix - 1];
+                    if style.needs_text_at_ends() && is_whitespace(previous) {
+                        style.clear(&mut previous.format);
+                        trimmed = true;
+                    }
+                }
+            }
+
+            let Some(piece) = pieces.get_mut(ix) else {
+                break;
+            };
+            for style in Style::ALL {
+                if style.is_set(&piece.format) && !open_styles.contains(&style) {
+                    if style.needs_text_at_ends() && is_whitespace(piece) {
+                        style.clear(&mut piece.format);
+                        trimmed = true;
+                    } else {
+                        open_styles.push(style);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the marker for italic text starting at the first of `pieces`. It's `_`, unless the
+/// text is next to a letter or digit, where only `*` is read as a marker.
+fn italic_marker(line: &str, pieces: &[Piece]) -> &'static str {
+    let run_end = pieces
+        .iter()
+        .take_while(|piece| piece.format.italic)
+        .last()
+        .map_or(0, |piece| piece.range.end);
+    let run_start = pieces.first().map_or(0, |piece| piece.range.start);
+    let before = line[..run_start].chars().next_back();
+    let after = line[run_end..].chars().next();
+
+    if before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric) {
+        "*"
+    } else {
+        "_"
+    }
+}
+
+/// Returns the closer of a link to `url`, escaping the characters that would end the URL early.
+/// A URL with whitespace is written in angle brackets.
+fn link_closer(url: &str) -> String {
+    let mut destination = String::with_capacity(url.len());
+    for char in url.chars() {
+        if URL_ESCAPABLE.contains(&char) {
+            destination.push('\\');
+        }
+        destination.push(char);
+    }
+
+    if url.is_empty() || url.contains(char::is_whitespace) {
+        format!("](<{destination}>)")
+    } else {
+        format!("]({destination})")
+    }
+}
+
+/// Reads the closer of a link at the start of `markdown`, undoing [`link_closer`]. Returns the
+/// URL and the length of the closer.
+fn parse_link_closer(markdown: &str) -> Option<(String, usize)> {
+    let rest = markdown.strip_prefix("](")?;
+    let (rest, end) = match rest.strip_prefix('<') {
+        Some(rest) => (rest, ">)"),
+        None => (rest, ")"),
+    };
+
+    let mut url = String::new();
+    let mut chars = rest.char_indices();
+    while let Some((ix, char)) = chars.next() {
+        if rest[ix..].starts_with(end) {
+            return Some((url, markdown.len() - rest.len() + ix + end.len()));
+        }
+        match char {
+            '\\' => {
+                let escaped = rest[ix + 1..].chars().next();
+                match escaped.filter(|char| URL_ESCAPABLE.contains(char)) {
+                    Some(escaped) => {
+                        url.push(escaped);
+                        chars.next();
+                    }
+                    None => url.push(char),
+                }
+            }
+            '(' | ')' | '<' | '>' => return None,
+            char if end == ")" && char.is_whitespace() => return None,
+            char => url.push(char),
+        }
+    }
+    None
+}
+
+/// Returns whether a character of the text has to be escaped so it's not read as a marker,
+/// given the characters around it.
+fn needs_escape(char: char, previous: Option<char>, next: Option<char>) -> bool {
+    let is_space = |char: Option<char>| char.is_none_or(char::is_whitespace);
+    let is_word = |char: Option<char>| char.is_some_and(char::is_alphanumeric);
+    match char {
+        '\\' => next.is_some_and(|next| ESCAPABLE.contains(&next)),
+        // Keeps brackets that aren't a wiki link from being read as one
+        '[' => previous == Some('['),
+        // Keeps text before a link from being read as an image
+        '!' => next == Some('['),
+        '*' => !(is_space(previous) && next.is_some_and(char::is_whitespace)),
+        '_' => !(is_word(previous) && is_word(next) || is_space(previous) && is_space(next)),
+        _ => false,
+    }
+}
+
+/// A marker wrapping formatted text.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Delimiter {
+    Link,
+    Underline,
+    Bold,
+    Star,
+    Underscore,
+}
+
+impl Delimiter {
+    /// The delimiters tried where one could start, longest first.
+    const ALL: [Self; 5] = [
+        Self::Link,
+        Self::Underline,
+        Self::Bold,
+        Self::Star,
+        Self::Underscore,
+    ];
+
+    fn opener(self) -> &'static str {
+        match self {
+            Self::Link => "[",
+            Self::Underline => "<u>",
+            Self::Bold => "**",
+            Self::Star => "*",
+            Self::Underscore => "_",
+        }
+    }
+
+    fn style(self) -> Style {
+        match self {
+            Self::Link => Style::Link,
+            Self::Underline => Style::Underline,
+            Self::Bold => Style::Bold,
+            Self::Star | Self::Underscore => Style::Italic,
+        }
+    }
+}
+
+/// Reads the inline formatting of a line of Markdown.
+struct InlineParser<'a> {
+    markdown: &'a str,
+    literal_ranges: Vec<Range<usize>>,
+    /// The styles of the formatted text being read, outermost first.
+    open_styles: Vec<Style>,
+    /// Openers found to have no closer, by position and the styles open around them, which are
+    /// read as text when reached again in the same styles.
+    unclosed: HashSet<(usize, Delimiter, Vec<Style>)>,
+    text: String,
+    /// The formatted ranges of the text read so far.
+    format_spans: Vec<FormatSpan>,
+    /// Number of `*` and `_` read as text that [`to_markdown`] would have escaped.
+    stray_markers: usize,
+}
+
+/// How much an [`InlineParser`] had read at some point, to go back to.
+#[derive(Copy, Clone)]
+struct Checkpoint {
+    text_len: usize,
+    span_count: usize,
+    stray_markers: usize,
+}
+
+impl<'a> InlineParser<'a> {
+    fn new(markdown: &'a str) -> Self {
+        Self {
+            markdown,
+            literal_ranges: literal_ranges(markdown, true),
+            open_styles: Vec::new(),
+            unclosed: HashSet::new(),
+            text: String::with_capacity(markdown.len()),
+            format_spans: Vec::new(),
+            stray_markers: 0,
+        }
+    }
+
+    /// Reads the Markdown from `ix` to the closer of `delimiter`, or to the end of the line
+    /// without a delimiter. Returns the range of the closer, or `None` if it isn't found.
+    fn parse(&mut self, mut ix: usize, delimiter: Option<Delimiter>) -> Option<Range<usize>> {
+        while ix < self.markdown.len() {
+            if let Some(range) = self.literal_ranges.iter().find(|range| range.start == ix) {
+                self.text.push_str(&self.markdown[range.clone()]);
+                ix = range.end;
+                continue;
+            }
+
+            if let Some(delimiter) = delimiter
+                && let Some(closer) = self.closer_at(ix, delimiter)
+            {
+                // In `*italic*` text, `**` opens bold text if it's closed without leaving markers
+                // unread, and is otherwise the closer followed by a `*`
+                if delimiter == Delimiter::Star {
+                    let checkpoint = self.checkpoint();
+                    if let Some(end) = self.parse_formatted(ix, Delimiter::Bold) {
+                        if self.stray_markers == checkpoint.stray_markers {
+                            ix = end;
+                            continue;
+                        }
+                        self.restore(checkpoint);
+                    }
+                }
+                return Some(closer);
+            }
+
+            let rest = &self.markdown[ix..];
+            if let Some(escaped) = rest
+                .strip_prefix('\\')
+                .and_then(|rest| rest.chars().next())
+                .filter(|char| ESCAPABLE.contains(char))
+            {
+                self.text.push(escaped);
+                ix += 1 + escaped.len_utf8();
+                continue;
+            }
+
+            if let Some(end) = Delimiter::ALL
+                .into_iter()
+                .find_map(|delimiter| self.parse_formatted(ix, delimiter))
+            {
+                ix = end;
+                continue;
+            }
+
+            let char = rest.chars().next().unwrap_or_default();
+            let previous = self.markdown[..ix].chars().next_back();
+            let next = rest[char.len_utf8()..].chars().next();
+            if matches!(char, '*' | '_') && needs_escape(char, previous, next) {
+                self.stray_markers += 1;
+            }
+            self.text.push(char);
+            ix += char.len_utf8();
+        }
+
+        delimiter.is_none().then_some(ix..ix)
+    }
+
+    /// Reads text wrapped in `delimiter` starting at `ix`, returning the offset after its closer.
+    /// Leaves the text unread if the delimiter doesn't open formatted text there, which it can't
+    /// inside text that already has its style.
+    fn parse_formatted(&mut self, ix: usize, delimiter: Delimiter) -> Option<usize> {
+        let style = delimiter.style();
+        if self.open_styles.contains(&style)
+            || !self.opens(ix, delimiter)
+            || self
+                .unclosed
+                .contains(&(ix, delimiter, self.open_styles.clone()))
+        {
+            return None;
+        }
+
+        let checkpoint = self.checkpoint();
+        self.open_styles.push(style);
+        let closer = self.parse(ix + delimiter.opener().len(), Some(delimiter));
+        self.open_styles.pop();
+        match closer {
+            Some(closer) if self.text.len() > checkpoint.text_len => {
+                let mut span = FormatSpan::new(checkpoint.text_len..self.text.len());
+                match delimiter {
+                    Delimiter::Link => {
+                        span.link =
+                            parse_link_closer(&self.markdown[closer.start..]).map(|(url, _)| url);
+                    }
+                    _ => style.set(&mut span),
+                }
+                self.format_spans.push(span);
+                Some(closer.end)
+            }
+            _ => {
+                self.restore(checkpoint);
+                self.unclosed
+                    .insert((ix, delimiter, self.open_styles.clone()));
+                None
+            }
+        }
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            text_len: self.text.len(),
+            span_count: self.format_spans.len(),
+            stray_markers: self.stray_markers,
+        }
+    }
+
+    /// Forgets what was read since the checkpoint.
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.text.truncate(checkpoint.text_len);
+        self.format_spans.truncate(checkpoint.span_count);
+        self.stray_markers = checkpoint.stray_markers;
+    }
+
+    /// Returns whether `delimiter` at `ix` could open formatted text. Text in `**` or `*` has to
+    /// start with a non-whitespace character after any further markers, and `_` can't open it
+    /// in the middle of a word either.
+    fn opens(&self, ix: usize, delimiter: Delimiter) -> bool {
+        let opener = delimiter.opener();
+        if !self.markdown[ix..].starts_with(opener) {
+            return false;
+        }
+        if matches!(delimiter, Delimiter::Link | Delimiter::Underline) {
+            return true;
+        }
+
+        let before = self.markdown[..ix].chars().next_back();
+        let marker_char = opener.chars().next().unwrap_or_default();
+        let after = self.markdown[ix..]
+            .trim_start_matches(marker_char)
+            .chars()
+            .next();
+        after.is_some_and(|char| !char.is_whitespace())
+            && !(delimiter == Delimiter::Underscore && before.is_some_and(char::is_alphanumeric))
+    }
+
+    /// Returns the range of a closer of `delimiter` at `ix`, if there's one. Text in `**` or `*`
+    /// has to end with a non-whitespace character, and `_` can't close it in the middle of a
+    /// word either.
+    fn closer_at(&self, ix: usize, delimiter: Delimiter) -> Option<Range<usize>> {
+        let rest = &self.markdown[ix..];
+        let closer_len = match delimiter {
+            Delimiter::Link => return parse_link_closer(rest).map(|(_, len)| ix..ix + len),
+            Delimiter::Underline => {
+                return rest.starts_with("</u>").then_some(ix..ix + "</u>".len());
+            }
+            _ => delimiter.opener().len(),
+        };
+        let after = rest.strip_prefix(delimiter.opener())?.chars().next();
+        let before = self.markdown[..ix].chars().next_back();
+        let closes = before.is_some_and(|char| !char.is_whitespace())
+            && !(delimiter == Delimiter::Underscore && after.is_some_and(char::is_alphanumeric));
+        closes.then_some(ix..ix + closer_len)
+    }
+}
@@ -0,0 +1,228 @@
+use std::ops::Range;
+
+/// Narrowest a column is written, which is the shortest separator Markdown accepts.
+const MIN_COLUMN_WIDTH: usize = 3;
+
+/// A cell of a table row written as `| one | two |`, by its byte ranges within the line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableCell {
+    /// Everything between the pipes around the cell.
+    pub range: Range<usize>,
+    /// The cell's text without the spaces around it. In an empty cell, it's empty and after the
+    /// first space.
+    pub text: Range<usize>,
+}
+
+/// How the text in a table column is aligned, set by colons in the separator row below the
+/// header.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ColumnAlignment {
+    #[default]
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+/// A Markdown pipe table, with a header row and a separator row below it:
+///
+/// ```text
+/// | Name | Role     |
+/// | ---- | -------- |
+/// | Ada  | Engineer |
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Table {
+    /// The text of every cell, by row and starting with the header row. Every row has a cell
+    /// for each column.
+    pub rows: Vec<Vec<String>>,
+    pub alignments: Vec<ColumnAlignment>,
+}
+
+/// Returns the cells of a line written as a table row, which starts and ends with a pipe.
+/// Pipes escaped as `\|` are part of a cell's text.
+pub fn table_cells(line: &str) -> Option<Vec<TableCell>> {
+    let start = line.find(|char: char| !char.is_whitespace())?;
+    let end = line.trim_end().len();
+    let row = &line[start..end];
+    if row.len() < 2 || !row.starts_with('|') || !row.ends_with('|') {
+        return None;
+    }
+
+    let mut cells = Vec::new();
+    let mut cell_start = start + 1;
+    let mut escaped = false;
+    for (ix, char) in line[start + 1..end].char_indices() {
+        let ix = start + 1 + ix;
+        if char == '|' && !escaped {
+            cells.push(table_cell(line, cell_start..ix));
+            cell_start = ix + 1;
+        }
+        escaped = char == '\\' && !escaped;
+    }
+
+    Some(cells)
+}
+
+/// Returns whether a line is the separator row below a table's header, such as `| --- | :-: |`.
+pub fn is_table_separator(line: &str) -> bool {
+    table_cells(line).is_some_and(|cells| {
+        !cells.is_empty()
+            && cells
+                .iter()
+                .all(|cell| ColumnAlignment::parse(&line[cell.text.clone()]).is_some())
+    })
+}
+
+fn table_cell(line: &str, range: Range<usize>) -> TableCell {
+    let content = &line[range.clone()];
+    let leading = content.len() - content.trim_start().len();
+    let text = if content.trim().is_empty() {
+        let start = range.start + content.len().min(1);
+        start..start
+    } else {
+        let start = range.start + leading;
+        start..start + content.trim().len()
+    };
+
+    TableCell { range, text }
+}
+
+impl ColumnAlignment {
+    /// Parses a cell of the separator row, such as `---`, `:---`, `:---:` or `---:`.
+    pub fn parse(cell: &str) -> Option<Self> {
+        let cell = cell.trim();
+        let left = cell.starts_with(':');
+        let right = cell.len() > 1 && cell.ends_with(':');
+        let dashes = &cell[left as usize..cell.len() - right as usize];
+        if dashes.is_empty() || !dashes.chars().all(|char| char == '-') {
+            return None;
+        }
+
+        Some(match (left, right) {
+            (false, false) => Self::None,
+            (true, false) => Self::Left,
+            (true, true) => Self::Center,
+            (false, true) => Self::Right,
+        })
+    }
+
+    /// Formats the column's cell of the separator row, `width` characters wide.
+    fn separator(self, width: usize) -> String {
+        let dashes = |count: usize| "-".repeat(count);
+        match self {
+            Self::None => dashes(width),
+            Self::Left => format!(":{}", dashes(width - 1)),
+            Self::Center => format!(":{}:", dashes(width - 2)),
+            Self::Right => format!("{}:", dashes(width - 1)),
+        }
+    }
+}
+
+impl Table {
+    /// Creates a table of empty cells, with a header row and `rows` rows below it.
+    pub fn new(columns: usize, rows: usize) -> Self {
+        let columns = columns.max(1);
+        Self {
+            rows: vec![vec![String::new(); columns]; rows + 1],
+            alignments: vec![ColumnAlignment::None; columns],
+        }
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.alignments.len()
+    }
+
+    /// Parses a Markdown pipe table, such as one pasted from elsewhere. The pipes at the start
+    /// and end of each row are optional, and rows with missing cells are filled with empty
+    /// ones.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let header = lines.next().filter(|line| line.contains('|'))?;
+        let header = split_row(header)?;
+        let alignments = split_row(lines.next()?)?
+            .iter()
+            .map(|cell| ColumnAlignment::parse(cell))
+            .collect::<Option<Vec<_>>>()?;
+        if alignments.len() != header.len() {
+            return None;
+        }
+
+        let mut rows = vec![header];
+        for line in lines {
+            let mut row = split_row(line)?;
+            row.resize(alignments.len(), String::new());
+            rows.push(row);
+        }
+
+        Some(Self { rows, alignments })
+    }
+
+    /// Formats the table as Markdown, padding the cells so that the columns line up.
+    pub fn to_markdown(&self) -> String {
+        let widths = (0..self.column_count())
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(column))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+                    .max(MIN_COLUMN_WIDTH)
+            })
+            .collect::<Vec<_>>();
+
+        let format_row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+        let mut lines = Vec::with_capacity(self.rows.len() + 1);
+        for (ix, row) in self.rows.iter().enumerate() {
+            let cells = widths
+                .iter()
+                .zip(&self.alignments)
+                .enumerate()
+                .map(|(column, (&width, &alignment))| {
+                    let cell = row.get(column).map_or("", String::as_str);
+                    pad_cell(cell, width, alignment)
+                })
+                .collect();
+            lines.push(format_row(cells));
+
+            if ix == 0 {
+                let separators = widths
+                    .iter()
+                    .zip(&self.alignments)
+                    .map(|(&width, alignment)| alignment.separator(width))
+                    .collect();
+                lines.push(format_row(separators));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+/// Splits a row of a pasted table into the text of its cells.
+fn split_row(line: &str) -> Option<Vec<String>> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(stripped) if !stripped.ends_with('\\') => stripped,
+        _ => line,
+    };
+    let row = format!("|{line}|");
+    let cells = table_cells(&row)?;
+    Some(
+        cells
+            .into_iter()
+            .map(|cell| row[cell.text].to_string())
+            .collect(),
+    )
+}
+
+fn pad_cell(cell: &str, width: usize, alignment: ColumnAlignment) -> String {
+    let padding = width.saturating_sub(cell.chars().count());
+    let (before, after) = match alignment {
+        ColumnAlignment::None | ColumnAlignment::Left => (0, padding),
+        ColumnAlignment::Center => (padding / 2, padding - padding / 2),
+        ColumnAlignment::Right => (padding, 0),
+    };
+    format!("{}{cell}{}", " ".repeat(before), " ".repeat(after))
+}
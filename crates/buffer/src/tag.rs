@@ -0,0 +1,88 @@
+use std::ops::Range;
+
+/// A `#tag` found in a text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tag {
+    /// The whole tag, including its `#`.
+    pub range: Range<usize>,
+    /// The tag's name, after the `#`.
+    pub name: Range<usize>,
+}
+
+/// Returns the tags in a text, in order.
+///
+/// A tag starts a word and is made of letters, digits, `-`, `_` and `/` for nested tags, with at
+/// least one letter so that `#1` isn't one. Tags in `` `code` `` spans are skipped.
+pub fn tags(text: &str) -> Vec<Tag> {
+    let mut tags = Vec::new();
+    let mut previous = None;
+    let code_spans = code_spans(text);
+
+    for (ix, char) in text.char_indices() {
+        let starts_word = previous
+            .is_none_or(|previous: char| previous.is_whitespace() || "([{,;".contains(previous));
+        previous = Some(char);
+        if char != '#' || !starts_word || code_spans.iter().any(|span| span.contains(&ix)) {
+            continue;
+        }
+
+        let name_start = ix + 1;
+        let name_len = text[name_start..]
+            .find(|char: char| !is_tag_char(char))
+            .unwrap_or(text.len() - name_start);
+        let name = text[name_start..name_start + name_len].trim_end_matches(['/', '-']);
+        if name.contains(char::is_alphabetic) {
+            tags.push(Tag {
+                range: ix..name_start + name.len(),
+                name: name_start..name_start + name.len(),
+            });
+        }
+    }
+
+    tags
+}
+
+fn is_tag_char(char: char) -> bool {
+    char.is_alphanumeric() || matches!(char, '-' | '_' | '/')
+}
+
+/// Returns the ranges of the `` `code` `` spans in a text, including their backticks. A span is
+/// closed by the next run of as many backticks as opened it.
+pub(crate) fn code_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut search_start = 0;
+
+    while let Some(open) = text[search_start..].find('`') {
+        let open = search_start + open;
+        let fence_len = backtick_count(&text[open..]);
+        let code_start = open + fence_len;
+
+        let mut close_start = code_start;
+        let mut span_end = None;
+        while let Some(close) = text[close_start..].find('`') {
+            let close = close_start + close;
+            let close_len = backtick_count(&text[close..]);
+            if close_len == fence_len {
+                span_end = Some(close + close_len);
+                break;
+            }
+            close_start = close + close_len;
+        }
+
+        match span_end {
+            Some(span_end) => {
+                spans.push(open..span_end);
+                search_start = span_end;
+            }
+            // Backticks that aren't closed are just text
+            None => search_start = code_start,
+        }
+    }
+
+    spans
+}
+
+/// Returns the number of backticks a text starts with.
+fn backtick_count(text: &str) -> usize {
+    text.len() - text.trim_start_matches('`').len()
+}
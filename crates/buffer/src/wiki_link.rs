@@ -0,0 +1,41 @@
+use std::ops::Range;
+
+/// A `[[Note Title]]` link to another note, found in a text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WikiLink {
+    /// The whole link, including its brackets.
+    pub range: Range<usize>,
+    /// The title of the note it links to, between the brackets.
+    pub title: Range<usize>,
+}
+
+/// Returns the wiki links in a text, in order. Links can't span lines or be empty.
+pub fn wiki_links(text: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut search_start = 0;
+
+    while let Some(open) = text[search_start..].find("[[") {
+        let title_start = search_start + open + 2;
+        let rest = &text[title_start..];
+        let Some(close) = rest.find("]]") else {
+            break;
+        };
+
+        let title = &rest[..close];
+        if let Some(nested) = title.rfind("[[") {
+            // Only the innermost opening brackets start the link
+            search_start = title_start + nested;
+            continue;
+        }
+        search_start = title_start + close + 2;
+
+        if !title.trim().is_empty() && !title.contains('\n') {
+            links.push(WikiLink {
+                range: title_start - 2..search_start,
+                title: title_start..title_start + close,
+            });
+        }
+    }
+
+    links
+}
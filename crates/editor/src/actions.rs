@@ -5,6 +5,11 @@ use gpui::Action;
 #[action(namespace = editor)]
 pub struct Backspace;
 
+/// Revert the substitutions made by the last input
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct Undo;
+
 /// Delete from cursor to beginning of the line
 #[derive(PartialEq, Clone, Default, Action)]
 #[action(namespace = editor)]
@@ -59,3 +64,118 @@ pub struct MoveLeft;
 #[derive(PartialEq, Clone, Default, Action)]
 #[action(namespace = editor)]
 pub struct MoveRight;
+
+/// Add a cursor on the line above the topmost selection
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct AddSelectionAbove;
+
+/// Add a cursor on the line below the bottommost selection
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct AddSelectionBelow;
+
+/// Select the word under the cursor, or add a selection at the next occurrence of the selected text
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct SelectNext;
+
+/// Move the selected lines up one line
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct MoveLineUp;
+
+/// Move the selected lines down one line
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct MoveLineDown;
+
+/// Duplicate the selected lines
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct DuplicateLine;
+
+/// Join the selected lines, or the current line with the next one
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct JoinLines;
+
+/// Delete the selected lines
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct DeleteLine;
+
+/// Indent the selected lines, or nest the current list item one level deeper
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct Tab;
+
+/// Outdent the selected lines, or move the current list item one level out
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct ShiftTab;
+
+/// Link the selected text to the URL on the clipboard, or insert that URL as a link
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct InsertLink;
+
+/// Point the link under the cursor to the URL on the clipboard
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct EditLink;
+
+/// Remove the links from the selected text, or the link under the cursor
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct RemoveLink;
+
+/// Copy the selected text to the clipboard
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct Copy;
+
+/// Copy the selected text to the clipboard and delete it
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct Cut;
+
+/// Paste the text or image on the clipboard
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct Paste;
+
+/// Wrap the selected text in a code block, or insert an empty one
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct InsertCodeBlock;
+
+/// Insert a divider on a line of its own
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct InsertDivider;
+
+/// Insert an empty table with a header row and two rows below it
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct InsertTable;
+
+/// Add a row below the current table row
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct InsertTableRow;
+
+/// Delete the current table row
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct DeleteTableRow;
+
+/// Add a column after the current table cell
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct InsertTableColumn;
+
+/// Delete the column of the current table cell
+#[derive(PartialEq, Clone, Default, Action)]
+#[action(namespace = editor)]
+pub struct DeleteTableColumn;
@@ -0,0 +1,43 @@
+/// Characters that get their closing counterpart inserted after them, with that counterpart.
+const PAIRS: [(&str, &str); 5] = [("(", ")"), ("[", "]"), ("\"", "\""), ("*", "*"), ("“", "”")];
+
+/// Returns the text that closes a pair opened by typing `open`, if it opens one.
+pub fn closing(open: &str) -> Option<&'static str> {
+    PAIRS
+        .iter()
+        .find(|(pair_open, _)| *pair_open == open)
+        .map(|(_, close)| *close)
+}
+
+/// Returns whether typing `text` right before the same text steps over it instead.
+pub fn is_closing(text: &str) -> bool {
+    PAIRS.iter().any(|(_, close)| *close == text)
+}
+
+/// Returns whether typing `open` after `text_before` on its line, and before `next`, should
+/// insert its closing text too.
+///
+/// Pairs are only inserted before whitespace, closing characters or the end of the line, and
+/// symmetric pairs like quotes not right after a word or themselves, where they more likely close
+/// something.
+/// An asterisk starting a line is left alone, as it more likely starts a list item.
+pub fn should_pair(open: &str, text_before: &str, next: Option<char>) -> bool {
+    let before_boundary = next.is_none_or(|char| {
+        char.is_whitespace() || PAIRS.iter().any(|(_, close)| close.starts_with(char))
+    });
+    let symmetric = closing(open) == Some(open);
+    let closes = text_before
+        .chars()
+        .next_back()
+        .is_some_and(|char| char.is_alphanumeric() || open.starts_with(char));
+    let starts_list_item = open == "*" && text_before.trim_start().is_empty();
+
+    before_boundary && !(symmetric && closes) && !starts_list_item
+}
+
+/// Returns whether the characters around a cursor form a pair with nothing inside it.
+pub fn is_empty_pair(previous: char, next: char) -> bool {
+    PAIRS
+        .iter()
+        .any(|(open, close)| open.chars().eq([previous]) && close.chars().eq([next]))
+}
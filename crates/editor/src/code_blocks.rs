@@ -0,0 +1,117 @@
+use gpui::{Font, Hsla};
+use std::{collections::HashMap, mem, ops::Range, sync::Arc};
+
+use languages::{HighlightKind, Language};
+
+/// Font family code blocks are drawn in.
+const CODE_FONT_FAMILY: &str = "Menlo";
+
+/// Highlighted ranges of some code, relative to its start.
+pub type Highlights = Arc<[(Range<usize>, HighlightKind)]>;
+
+/// Highlights of code blocks reused across frames, keyed by the rows of each block.
+///
+/// While the buffer keeps its version a block's highlights are reused without reading its code.
+/// After an edit the code is compared with the code last highlighted, so editing a block only
+/// highlights that block again. Like shaped lines, blocks not highlighted while laying out a
+/// frame are evicted when the next frame starts.
+#[derive(Default)]
+pub struct HighlightCache {
+    previous_frame: HashMap<Range<usize>, CachedHighlights>,
+    current_frame: HashMap<Range<usize>, CachedHighlights>,
+}
+
+struct CachedHighlights {
+    language: &'static str,
+    /// Buffer version the code was last seen at.
+    version: usize,
+    code: String,
+    highlights: Highlights,
+}
+
+impl HighlightCache {
+    pub fn start_frame(&mut self) {
+        self.previous_frame = mem::take(&mut self.current_frame);
+    }
+
+    /// Forgets every block, such as after switching to another buffer.
+    pub fn clear(&mut self) {
+        self.previous_frame.clear();
+        self.current_frame.clear();
+    }
+
+    /// Returns the highlights of the code block on some rows, reading its code with `code` and
+    /// highlighting it only if it changed.
+    pub fn highlight(
+        &mut self,
+        language: &'static Language,
+        rows: Range<usize>,
+        version: usize,
+        code: impl FnOnce() -> String,
+    ) -> Highlights {
+        let cached = self
+            .current_frame
+            .remove(&rows)
+            .or_else(|| self.previous_frame.remove(&rows))
+            .filter(|cached| cached.language == language.name);
+        let cached = match cached {
+            Some(cached) if cached.version == version => cached,
+            cached => {
+                let code = code();
+                match cached {
+                    Some(cached) if cached.code == code => CachedHighlights { version, ..cached },
+                    _ => CachedHighlights {
+                        language: language.name,
+                        version,
+                        highlights: language.highlight(&code).into(),
+                        code,
+                    },
+                }
+            }
+        };
+
+        let highlights = cached.highlights.clone();
+        self.current_frame.insert(rows, cached);
+        highlights
+    }
+}
+
+/// Returns the monospace font code is drawn in, at the size and weight of `font`.
+pub fn code_font(font: &Font) -> Font {
+    Font {
+        family: CODE_FONT_FAMILY.into(),
+        ..font.clone()
+    }
+}
+
+/// Returns the color code of a highlight kind is drawn in.
+pub fn highlight_color(kind: HighlightKind) -> Hsla {
+    let color = match kind {
+        HighlightKind::Comment => 0x7f848e,
+        HighlightKind::Constant | HighlightKind::Number => 0xd19a66,
+        HighlightKind::Function => 0x61afef,
+        HighlightKind::Keyword => 0xc678dd,
+        HighlightKind::Operator => 0x56b6c2,
+        HighlightKind::Property => 0xe06c75,
+        HighlightKind::Punctuation => 0xabb2bf,
+        HighlightKind::String => 0x98c379,
+        HighlightKind::Type => 0xe5c07b,
+    };
+    gpui::rgb(color).into()
+}
+
+/// Returns the highlights overlapping a line of code, relative to the start of the line.
+pub fn line_highlights(
+    highlights: &[(Range<usize>, HighlightKind)],
+    line_range: Range<usize>,
+) -> Vec<(Range<usize>, HighlightKind)> {
+    highlights
+        .iter()
+        .filter(|(range, _)| range.start < line_range.end && range.end > line_range.start)
+        .map(|(range, kind)| {
+            let start = range.start.max(line_range.start) - line_range.start;
+            let end = range.end.min(line_range.end) - line_range.start;
+            (start..end, *kind)
+        })
+        .collect()
+}
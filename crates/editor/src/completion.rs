@@ -0,0 +1,42 @@
+use std::ops::Range;
+
+/// Most note titles offered at once while typing a wiki link.
+const MAX_MATCHES: usize = 8;
+
+/// Note titles offered while typing a `[[` wiki link, and which of them is highlighted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WikiLinkCompletion {
+    /// The partial title typed after `[[`, which accepting a title replaces.
+    pub query_range: Range<usize>,
+    pub matches: Vec<String>,
+    pub selected: usize,
+    /// Buffer version the titles were matched at, after which the completion is stale.
+    pub version: usize,
+}
+
+/// Returns the range of the partial title of a wiki link being typed at the end of `text`.
+pub fn wiki_link_query(text: &str) -> Option<Range<usize>> {
+    let open = text.rfind("[[")?;
+    let query = open + 2..text.len();
+    (!text[query.clone()].contains(['[', ']'])).then_some(query)
+}
+
+/// Returns the titles containing `query`, ignoring case, with those starting with it first.
+pub fn matching_titles(titles: &[String], query: &str) -> Vec<String> {
+    let query = query.to_lowercase();
+    let mut matches = titles
+        .iter()
+        .filter_map(|title| {
+            let lowercase = title.to_lowercase();
+            let ix = lowercase.find(&query)?;
+            Some((ix > 0, lowercase, title))
+        })
+        .collect::<Vec<_>>();
+    matches.sort();
+
+    matches
+        .into_iter()
+        .take(MAX_MATCHES)
+        .map(|(_, _, title)| title.clone())
+        .collect()
+}
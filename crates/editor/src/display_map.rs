@@ -0,0 +1,139 @@
+use gpui::{App, Font, Pixels};
+use std::ops::Range;
+
+use buffer::Buffer;
+use text::TextPoint;
+
+use crate::wrap_map::{WrapMap, WrapPoint};
+
+/// A position on screen, where each row is a single line of the editor.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct DisplayPoint {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl DisplayPoint {
+    pub fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+}
+
+/// The part of the buffer shown on a display row.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisplayLine {
+    /// Buffer row the display row belongs to.
+    pub buffer_row: usize,
+    /// Byte range of the buffer shown on the display row.
+    pub range: Range<usize>,
+    /// Whether the buffer row continues on the next display row.
+    pub is_wrapped: bool,
+    /// For rows showing an image, which of the image's rows this is.
+    pub image_row: Option<usize>,
+}
+
+/// Maps buffer positions to display positions, accounting for soft wraps.
+#[derive(Default)]
+pub struct DisplayMap {
+    wrap_map: WrapMap,
+}
+
+impl DisplayMap {
+    /// Sets the width lines are soft wrapped at, or disables soft wrapping when `None`.
+    pub fn set_wrap_width(&mut self, width: Option<Pixels>, font: Font, font_size: Pixels) {
+        self.wrap_map.set_wrap_width(width, font, font_size);
+    }
+
+    /// Sets the height of a display row, which images take a whole number of.
+    pub fn set_line_height(&mut self, line_height: Pixels) {
+        self.wrap_map.set_line_height(line_height);
+    }
+
+    /// Makes the next sync rebuild the display map, such as after switching buffers.
+    pub fn invalidate(&mut self) {
+        self.wrap_map.invalidate();
+    }
+
+    /// Brings the display map up to date with the buffer.
+    pub fn sync(&mut self, buffer: &Buffer, cx: &App) {
+        self.wrap_map.sync(buffer, cx.text_system());
+    }
+
+    /// Returns the number of display rows, which is at least the number of buffer rows.
+    pub fn row_count(&self) -> usize {
+        self.wrap_map.row_count()
+    }
+
+    pub fn max_row(&self) -> usize {
+        self.wrap_map.max_row()
+    }
+
+    pub fn point_to_display_point(&self, point: TextPoint) -> DisplayPoint {
+        let WrapPoint { row, column } = self.wrap_map.to_wrap_point(point);
+        DisplayPoint::new(row, column)
+    }
+
+    pub fn display_point_to_point(&self, point: DisplayPoint) -> TextPoint {
+        self.wrap_map
+            .to_text_point(WrapPoint::new(point.row, point.column))
+    }
+
+    pub fn offset_to_display_point(&self, offset: usize, buffer: &Buffer) -> DisplayPoint {
+        self.point_to_display_point(buffer.offset_to_point(offset))
+    }
+
+    /// Converts a display point to a buffer offset, clipping it to its display row.
+    pub fn display_point_to_offset(&self, point: DisplayPoint, buffer: &Buffer) -> usize {
+        let point = self.clip_point(point, buffer);
+        buffer.point_to_offset(self.display_point_to_point(point))
+    }
+
+    /// Clamps a display point to the nearest valid position.
+    pub fn clip_point(&self, point: DisplayPoint, buffer: &Buffer) -> DisplayPoint {
+        let row = point.row.min(self.max_row());
+        let column = point.column.min(self.line_len(row, buffer));
+        DisplayPoint::new(row, column)
+    }
+
+    /// Returns the display rows of the image shown on a display row, if it shows one.
+    pub fn image_rows(&self, display_row: usize) -> Option<Range<usize>> {
+        self.wrap_map.image_rows(display_row)
+    }
+
+    /// Returns the display rows the buffer row on a display row takes as a single block, which is
+    /// all of an image's rows, or just the display row itself for text.
+    pub fn block_rows(&self, display_row: usize) -> Range<usize> {
+        self.image_rows(display_row)
+            .unwrap_or(display_row..display_row + 1)
+    }
+
+    /// Returns the byte length of a display row, excluding the character it wraps after.
+    pub fn line_len(&self, display_row: usize, buffer: &Buffer) -> usize {
+        self.wrap_map.line_len(display_row, buffer)
+    }
+
+    /// Returns the part of the buffer shown on each of the given display rows.
+    pub fn lines<'a>(
+        &'a self,
+        display_rows: Range<usize>,
+        buffer: &'a Buffer,
+    ) -> impl Iterator<Item = DisplayLine> + 'a {
+        display_rows
+            .take_while(|&display_row| display_row <= self.max_row())
+            .map(move |display_row| {
+                let (row, columns) = self.wrap_map.buffer_row_for_wrap_row(display_row);
+                let line_start = buffer.point_to_offset(TextPoint::new(row, 0));
+                let line_len = buffer.line_len(row);
+
+                DisplayLine {
+                    buffer_row: row,
+                    range: line_start + columns.start.min(line_len)
+                        ..line_start + columns.end.min(line_len),
+                    is_wrapped: columns.end < line_len,
+                    image_row: self
+                        .image_rows(display_row)
+                        .map(|rows| display_row - rows.start),
+                }
+            })
+    }
+}
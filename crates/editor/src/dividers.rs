@@ -0,0 +1,36 @@
+use gpui::Pixels;
+use std::ops::Range;
+
+use buffer::Buffer;
+use text::TextPoint;
+
+/// Thickness of the line a divider is drawn as.
+pub const DIVIDER_THICKNESS: Pixels = gpui::px(1.);
+
+/// Returns the range a divider takes up along with one of the line breaks around it, so that
+/// removing it leaves no empty line behind.
+pub fn divider_range(buffer: &Buffer, row: usize) -> Range<usize> {
+    let line_start = buffer.point_to_offset(TextPoint::new(row, 0));
+    let line_end = line_start + buffer.line_len(row);
+    if row < buffer.max_point().row {
+        line_start..line_end + 1
+    } else {
+        line_start.saturating_sub(1)..line_end
+    }
+}
+
+/// Returns the divider deleting from a cursor removes whole, which is the divider the cursor is
+/// at the far side of, or the one on the adjacent line when deleting would join the two.
+pub fn divider_deletion(buffer: &Buffer, offset: usize, forward: bool) -> Option<Range<usize>> {
+    let point = buffer.offset_to_point(offset);
+    let at_line_end = point.column == buffer.line_len(point.row);
+    let row = match (forward, point.column == 0, at_line_end) {
+        (false, _, true) if buffer.is_divider(point.row) => point.row,
+        (false, true, _) => point.row.checked_sub(1)?,
+        (true, true, _) if buffer.is_divider(point.row) => point.row,
+        (true, _, true) => point.row + 1,
+        _ => return None,
+    };
+
+    buffer.is_divider(row).then(|| divider_range(buffer, row))
+}
@@ -1,322 +1,2192 @@
 mod actions;
+mod autopair;
+mod code_blocks;
+mod completion;
+mod display_map;
+mod dividers;
 mod element;
+mod images;
+mod indent;
+mod input_rules;
+mod line_layout_cache;
+mod links;
 mod movement;
+mod scroll;
+mod selections_collection;
+mod tables;
+mod typography;
+mod wrap_map;
 
 #[cfg(test)]
 mod tests;
 
 pub use actions::*;
+pub use display_map::{DisplayLine, DisplayMap, DisplayPoint};
+pub use indent::{IndentUnit, ListItem};
+pub use selections_collection::SelectionsCollection;
 
 use gpui::{
-    App, Bounds, CursorStyle, Entity, EntityInputHandler, FocusHandle, Focusable,
-    InteractiveElement, MouseDownEvent, MouseMoveEvent, Pixels, Point, UTF16Selection, Window,
-    prelude::*,
+    App, Bounds, ClipboardEntry, ClipboardItem, CursorStyle, Entity, EntityInputHandler,
+    EventEmitter, ExternalPaths, FocusHandle, Focusable, Font, Image, InteractiveElement,
+    MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels, Point, Subscription, UTF16Selection,
+    Window, prelude::*,
+};
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
-use std::ops::Range;
 
-use buffer::{Buffer, Selection, SelectionGoal};
+use buffer::{
+    Anchor, Bias, Buffer, BufferEvent, DIVIDER, FormatSpan, ImageBlock, LineEnding, Selection,
+    SelectionGoal, Table,
+};
 use text::TextPoint;
 
-use crate::element::{EditorElement, PositionMap};
+use crate::{
+    code_blocks::HighlightCache,
+    completion::WikiLinkCompletion,
+    element::{EditorElement, PositionMap},
+    input_rules::{InlineFormat, InputRule},
+    line_layout_cache::LineLayoutCache,
+    scroll::ScrollManager,
+    tables::CellPosition,
+};
+
+/// Events the editor emits for its surroundings to handle.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditorEvent {
+    /// A wiki link was cmd-clicked, asking to open the note with this title.
+    OpenNote { title: String },
+    /// An image was pasted or dropped, to be stored as an attachment and inserted with
+    /// [`Editor::insert_image`].
+    InsertImage { image: Image },
+}
+
+/// Granularity a mouse selection extends by while dragging.
+#[derive(Clone, Debug, Default, PartialEq)]
+enum SelectMode {
+    #[default]
+    Character,
+    /// Selecting by word, starting from the word that was double-clicked.
+    Word(Range<usize>),
+    /// Selecting by line, starting from the line that was triple-clicked.
+    Line(Range<usize>),
+}
+
+/// A replacement made at one selection, and where that selection goes afterwards.
+struct SelectionEdit {
+    range: Range<usize>,
+    text: String,
+    /// The selection after the edit, relative to the start of the new text.
+    selection: Range<usize>,
+}
+
+impl SelectionEdit {
+    /// Replaces a range, leaving a cursor at the end of the new text.
+    fn new(range: Range<usize>, text: String) -> Self {
+        let len = text.len();
+        Self {
+            range,
+            text,
+            selection: len..len,
+        }
+    }
+}
+
+/// Substitutions made by the last input, which Backspace or Undo reverts if nothing changed since.
+struct AppliedSubstitutions {
+    /// Buffer version right after the substitutions were made.
+    version: usize,
+    /// For each selection, the range of its replacement and the text that was typed.
+    replacements: Vec<Option<(Range<usize>, String)>>,
+    /// Rows given a block style by the substitutions, which reverting them clears.
+    block_rows: Vec<usize>,
+}
+
+/// Progress of dragging the selected text to a new position.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum SelectionDragState {
+    #[default]
+    None,
+    /// The mouse went down inside the selection but hasn't moved yet.
+    ReadyToDrag {
+        selection: Selection,
+        click_position: usize,
+    },
+    /// The selection is being dragged, and would be dropped at `drop_position`.
+    Dragging {
+        selection: Selection,
+        drop_position: usize,
+    },
+}
+
+/// An image being resized by dragging its handle.
+#[derive(Clone, Copy)]
+struct ImageResize {
+    buffer_row: usize,
+    /// The image's left edge, which stays in place.
+    left: Pixels,
+}
+
+pub struct Editor {
+    focus_handle: FocusHandle,
+    buffer: Entity<Buffer>,
+    _buffer_subscription: Subscription,
+    selections: SelectionsCollection,
+    /// Text being composed by an input method, anchored so it tracks edits made around it.
+    marked_range: Option<Range<Anchor>>,
+    scroll_manager: ScrollManager,
+    line_layout_cache: LineLayoutCache,
+    highlight_cache: HighlightCache,
+    display_map: DisplayMap,
+    last_position_map: Option<Rc<PositionMap>>,
+    select_mode: SelectMode,
+    selection_drag_state: SelectionDragState,
+    image_resize: Option<ImageResize>,
+    /// Directory that images with a relative source are loaded from, which is the note's.
+    base_dir: Option<PathBuf>,
+    indent_unit: IndentUnit,
+    /// Whether typed quotes, dashes and ellipses are replaced with their typographic forms.
+    smart_typography: bool,
+    /// Whether typed markdown, such as `**bold**` or `# `, is converted into formatting.
+    markdown_shortcuts: bool,
+    applied_substitutions: Option<AppliedSubstitutions>,
+    /// Titles of the notes wiki links can point to, offered while typing `[[`.
+    note_titles: Vec<String>,
+    wiki_link_completion: Option<WikiLinkCompletion>,
+}
+
+impl Editor {
+    pub fn new(cx: &mut Context<Self>) -> Self {
+        let buffer = cx.new(|_cx| Buffer::new());
+        let buffer_subscription = cx.subscribe(&buffer, Self::on_buffer_event);
+
+        Self {
+            focus_handle: cx.focus_handle(),
+            buffer,
+            _buffer_subscription: buffer_subscription,
+            selections: SelectionsCollection::default(),
+            marked_range: None,
+            scroll_manager: ScrollManager::default(),
+            line_layout_cache: LineLayoutCache::default(),
+            highlight_cache: HighlightCache::default(),
+            display_map: DisplayMap::default(),
+            last_position_map: None,
+            select_mode: SelectMode::default(),
+            selection_drag_state: SelectionDragState::default(),
+            image_resize: None,
+            base_dir: None,
+            indent_unit: IndentUnit::default(),
+            smart_typography: false,
+            markdown_shortcuts: true,
+            applied_substitutions: None,
+            note_titles: Vec::new(),
+            wiki_link_completion: None,
+        }
+    }
+
+    pub fn buffer(&self) -> &Entity<Buffer> {
+        &self.buffer
+    }
+
+    /// Switches to editing another buffer, such as when opening another note.
+    pub fn set_buffer(&mut self, buffer: Entity<Buffer>, cx: &mut Context<Self>) {
+        self._buffer_subscription = cx.subscribe(&buffer, Self::on_buffer_event);
+        self.buffer = buffer;
+        self.selections.select(vec![Selection::cursor(0)]);
+        self.marked_range = None;
+        self.select_mode = SelectMode::default();
+        self.selection_drag_state = SelectionDragState::default();
+        self.image_resize = None;
+        self.applied_substitutions = None;
+        self.wiki_link_completion = None;
+        self.display_map.invalidate();
+        self.highlight_cache.clear();
+        self.set_scroll_position(0., cx);
+    }
+
+    fn on_buffer_event(
+        &mut self,
+        _buffer: Entity<Buffer>,
+        event: &BufferEvent,
+        cx: &mut Context<Self>,
+    ) {
+        match event {
+            BufferEvent::Edited { .. } | BufferEvent::FormatChanged { .. } => cx.notify(),
+            BufferEvent::Reloaded => {
+                self.selections.select(vec![Selection::cursor(0)]);
+                self.marked_range = None;
+                cx.notify();
+            }
+            BufferEvent::LineEndingChanged | BufferEvent::Saved => {}
+        }
+    }
+
+    /// Returns the current byte range of the text being composed by an input method.
+    pub(crate) fn marked_range(&self, buffer: &Buffer) -> Option<Range<usize>> {
+        let marked_range = self.marked_range.as_ref()?;
+        Some(
+            buffer.offset_for_anchor(&marked_range.start)
+                ..buffer.offset_for_anchor(&marked_range.end),
+        )
+    }
+
+    /// Converts UTF-16 byte range to UTF-8 byte range.
+    fn range_from_utf16(&self, utf16_range: &Range<usize>, cx: &App) -> Range<usize> {
+        let buffer = self.buffer.read(cx);
+        let start = buffer.utf16_to_byte(utf16_range.start);
+        let end = buffer.utf16_to_byte(utf16_range.end);
+        start..end
+    }
+
+    /// Returns the selections, sorted by position.
+    pub fn selections(&self) -> &[Selection] {
+        self.selections.all()
+    }
+
+    /// Returns the most recently added selection.
+    pub fn newest_selection(&self) -> Selection {
+        self.selections.newest()
+    }
+
+    pub fn change_selections<R>(
+        &mut self,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+        change: impl FnOnce(&mut SelectionsCollection) -> R,
+    ) -> R {
+        let result = change(&mut self.selections);
+        self.request_autoscroll();
+        cx.notify();
+        result
+    }
+
+    /// Returns the topmost visible row.
+    pub fn scroll_position(&self) -> f32 {
+        self.scroll_manager.scroll_position()
+    }
+
+    pub fn set_scroll_position(&mut self, position: f32, cx: &mut Context<Self>) {
+        self.sync_display_map(cx);
+        let max_row = self.display_map.max_row();
+        self.scroll_manager.set_scroll_position(position, max_row);
+        cx.notify();
+    }
+
+    /// Scrolls by a number of rows, negative values scrolling towards the start of the buffer.
+    pub fn scroll_by(&mut self, rows: f32, cx: &mut Context<Self>) {
+        self.set_scroll_position(self.scroll_position() + rows, cx);
+    }
+
+    pub(crate) fn set_visible_line_count(&mut self, count: f32) {
+        self.scroll_manager.set_visible_line_count(count);
+    }
+
+    /// Requests the cursor to be scrolled into view during the next layout.
+    fn request_autoscroll(&mut self) {
+        self.scroll_manager.request_autoscroll();
+    }
+
+    /// Scrolls the newest cursor into view if an autoscroll was requested since the last layout.
+    pub(crate) fn autoscroll_vertically(&mut self, cx: &mut Context<Self>) {
+        if !self.scroll_manager.take_autoscroll_request() {
+            return;
+        }
+
+        self.sync_display_map(cx);
+        let buffer = self.buffer.read(cx);
+        let row = self
+            .display_map
+            .offset_to_display_point(self.selections.newest().head(), buffer)
+            .row;
+        let max_row = self.display_map.max_row();
+        self.scroll_manager.autoscroll_to_row(row, max_row);
+    }
+
+    /// Sets the width lines are soft wrapped at, or disables soft wrapping when `None`.
+    pub(crate) fn set_wrap_width(&mut self, width: Option<Pixels>, font: Font, font_size: Pixels) {
+        self.display_map.set_wrap_width(width, font, font_size);
+    }
+
+    /// Sets the height of a display row, which images take a whole number of.
+    pub(crate) fn set_line_height(&mut self, line_height: Pixels) {
+        self.display_map.set_line_height(line_height);
+    }
+
+    /// Brings the display map up to date with the buffer.
+    pub(crate) fn sync_display_map(&mut self, cx: &App) {
+        let buffer = self.buffer.read(cx);
+        self.display_map.sync(buffer, cx);
+    }
+
+    pub fn display_map(&self) -> &DisplayMap {
+        &self.display_map
+    }
+
+    pub fn indent_unit(&self) -> IndentUnit {
+        self.indent_unit
+    }
+
+    /// Enables replacing typed straight quotes with curly ones, `--` with an em dash and `...`
+    /// with an ellipsis, for notes written as prose.
+    pub fn set_smart_typography(&mut self, enabled: bool) {
+        self.smart_typography = enabled;
+        self.applied_substitutions = None;
+    }
+
+    /// Sets the directory images with a relative source, such as attachments, are loaded from.
+    pub fn set_base_dir(&mut self, base_dir: Option<PathBuf>, cx: &mut Context<Self>) {
+        self.base_dir = base_dir;
+        cx.notify();
+    }
+
+    /// Sets the titles of the notes offered while typing a `[[` wiki link.
+    pub fn set_note_titles(&mut self, titles: Vec<String>) {
+        self.note_titles = titles;
+    }
+
+    /// Enables converting typed markdown, such as `**bold**`, `*italic*`, `# ` or `> `, into
+    /// formatting and block styles as soon as it's complete.
+    pub fn set_markdown_shortcuts(&mut self, enabled: bool) {
+        self.markdown_shortcuts = enabled;
+        self.applied_substitutions = None;
+    }
+
+    /// Sets the whitespace inserted for each level of indentation.
+    pub fn set_indent_unit(&mut self, indent_unit: IndentUnit, cx: &mut Context<Self>) {
+        self.indent_unit = indent_unit;
+        cx.notify();
+    }
+
+    /// Replaces all selections with a cursor at the specified offset.
+    fn move_to(&mut self, offset: usize, _window: &mut Window, cx: &mut Context<Self>) {
+        self.selections.select(vec![Selection::cursor(offset)]);
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Extends the newest selection from its anchor to the specified offset.
+    fn select_to(&mut self, offset: usize, _window: &mut Window, cx: &mut Context<Self>) {
+        let mut selection = self.selections.newest();
+        if selection.reversed {
+            selection.start = offset;
+        } else {
+            selection.end = offset;
+        }
+
+        if selection.end < selection.start {
+            let start = selection.start;
+            let end = selection.end;
+            selection.start = end;
+            selection.end = start;
+            selection.reversed = !selection.reversed;
+        }
+
+        selection.goal = SelectionGoal::None;
+        self.selections.replace_newest(selection);
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Moves every selection, merging selections that end up overlapping.
+    fn move_selections(
+        &mut self,
+        cx: &mut Context<Self>,
+        mut move_selection: impl FnMut(&Buffer, &DisplayMap, Selection) -> Selection,
+    ) {
+        self.sync_display_map(cx);
+        let buffer = self.buffer.read(cx);
+        let newest = self.selections.newest();
+
+        let mut selections = Vec::with_capacity(self.selections.count());
+        let mut new_newest = None;
+        for &selection in self.selections.all() {
+            let new_selection = move_selection(buffer, &self.display_map, selection);
+            if selection == newest {
+                new_newest = Some(new_selection);
+            } else {
+                selections.push(new_selection);
+            }
+        }
+        selections.extend(new_newest);
+
+        self.selections.select(selections);
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Edits the text at every selection, from first to last.
+    ///
+    /// `edit` returns the range to replace and its new text, after which the selection becomes a
+    /// cursor at the end of the new text. Selections it returns `None` for are left unchanged.
+    fn edit_selections(
+        &mut self,
+        cx: &mut Context<Self>,
+        mut edit: impl FnMut(&Buffer, Selection) -> Option<(Range<usize>, String)>,
+    ) {
+        self.edit_selections_with(cx, |buffer, selection| {
+            edit(buffer, selection).map(|(range, text)| SelectionEdit::new(range, text))
+        });
+    }
+
+    /// Like [`Self::edit_selections`], but `edit` also decides where each selection ends up.
+    fn edit_selections_with(
+        &mut self,
+        cx: &mut Context<Self>,
+        mut edit: impl FnMut(&Buffer, Selection) -> Option<SelectionEdit>,
+    ) {
+        let buffer = self.buffer.read(cx);
+        let newest = self.selections.newest();
+
+        let mut edits = Vec::new();
+        let mut previous_end = 0;
+        for &selection in self.selections.all() {
+            let selection_edit = edit(buffer, selection).map(|mut edit| {
+                let start = edit.range.start.max(previous_end);
+                edit.range = start..edit.range.end.max(start);
+                edit
+            });
+            if let Some(edit) = &selection_edit {
+                previous_end = edit.range.end;
+            }
+            edits.push((selection, selection_edit));
+        }
+
+        let buffer_edits = edits
+            .iter()
+            .filter_map(|(_, edit)| edit.as_ref())
+            .filter(|edit| !edit.range.is_empty() || !edit.text.is_empty())
+            .map(|edit| (edit.range.clone(), edit.text.clone()))
+            .collect::<Vec<_>>();
+        self.buffer
+            .update(cx, |buffer, cx| buffer.edit(buffer_edits, cx));
+
+        let mut delta = 0isize;
+        let mut selections = Vec::with_capacity(edits.len());
+        let mut new_newest = None;
+        for (selection, edit) in edits {
+            let new_selection = match edit {
+                Some(edit) => {
+                    let start = edit.range.start.saturating_add_signed(delta);
+                    delta += edit.text.len() as isize - edit.range.len() as isize;
+                    Selection::new(start + edit.selection.start, start + edit.selection.end)
+                }
+                None => Selection {
+                    start: selection.start.saturating_add_signed(delta),
+                    end: selection.end.saturating_add_signed(delta),
+                    ..selection
+                },
+            };
+
+            if selection == newest {
+                new_newest = Some(new_selection);
+            } else {
+                selections.push(new_selection);
+            }
+        }
+        selections.extend(new_newest);
+
+        self.selections.select(selections);
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Inserts text at every cursor, replacing any selected text.
+    ///
+    /// Line breaks in the text are stored as `\n`, whichever line ending it came with.
+    ///
+    /// With smart typography enabled, punctuation is replaced with its typographic form, which
+    /// the next Backspace or Undo reverts to what was typed.
+    ///
+    /// Typing an opening bracket, quote or asterisk also inserts its closing counterpart, or
+    /// wraps the selected text in the pair. Typing the closing character right before the same
+    /// character steps over it, as does typing a quote into an empty pair of curly quotes.
+    ///
+    /// A markdown shortcut the input completes, such as `**bold**`, is then converted into
+    /// formatting, which the next Backspace or Undo also reverts, restoring the markers.
+    pub fn handle_input(&mut self, text: &str, _window: &mut Window, cx: &mut Context<Self>) {
+        let text = LineEnding::normalize(text);
+        let smart_typography = self.smart_typography;
+        self.applied_substitutions = None;
+
+        let mut substitutions = Vec::with_capacity(self.selections.count());
+        self.edit_selections_with(cx, |buffer, selection| {
+            let (text_before, text_after) = line_around(buffer, selection.range());
+            let next = text_after.chars().next();
+
+            // Selected text is wrapped in the pair, staying selected
+            if !selection.is_empty() {
+                let open = if smart_typography && text == "\"" {
+                    "“"
+                } else {
+                    text.as_ref()
+                };
+                if let Some(close) = autopair::closing(open) {
+                    let selected = buffer.slice(selection.range());
+                    substitutions.push(None);
+                    return Some(SelectionEdit {
+                        range: selection.range(),
+                        text: format!("{open}{selected}{close}"),
+                        selection: open.len()..open.len() + selected.len(),
+                    });
+                }
+            }
+
+            let closing_quote = smart_typography
+                .then(|| crate::typography::closing_quote(&text_before, &text, &text_after))
+                .flatten();
+            let substitution = match closing_quote {
+                Some(close) => Some(crate::typography::Substitution {
+                    replaced_len: 0,
+                    text: close,
+                }),
+                None => smart_typography
+                    .then(|| crate::typography::substitution(&text_before, &text))
+                    .flatten(),
+            };
+            let typed = substitution
+                .as_ref()
+                .map_or(text.as_ref(), |substitution| substitution.text);
+
+            if selection.is_empty() && substitution.as_ref().is_none_or(|s| s.replaced_len == 0) {
+                let is_closing = autopair::is_closing(typed) || closing_quote.is_some();
+                if is_closing && text_after.starts_with(typed) {
+                    substitutions.push(None);
+                    return Some(SelectionEdit {
+                        range: selection.range(),
+                        text: String::new(),
+                        selection: typed.len()..typed.len(),
+                    });
+                }
+
+                if let Some(close) = autopair::closing(typed)
+                    && autopair::should_pair(typed, &text_before, next)
+                {
+                    substitutions.push(None);
+                    return Some(SelectionEdit {
+                        range: selection.range(),
+                        text: format!("{typed}{close}"),
+                        selection: typed.len()..typed.len(),
+                    });
+                }
+            }
+
+            let Some(substitution) = substitution else {
+                substitutions.push(None);
+                return Some(SelectionEdit::new(selection.range(), text.to_string()));
+            };
+
+            let start = selection.start - substitution.replaced_len;
+            let typed = buffer.slice(start..selection.start) + &text;
+            substitutions.push(Some((substitution.text.len(), typed)));
+            Some(SelectionEdit::new(
+                start..selection.end,
+                substitution.text.to_string(),
+            ))
+        });
+
+        if substitutions.iter().any(Option::is_some)
+            && substitutions.len() == self.selections.count()
+        {
+            let replacements = self
+                .selections
+                .all()
+                .iter()
+                .zip(substitutions)
+                .map(|(selection, substitution)| {
+                    substitution.map(|(len, typed)| (selection.start - len..selection.start, typed))
+                })
+                .collect();
+            self.applied_substitutions = Some(AppliedSubstitutions {
+                version: self.buffer.read(cx).version(),
+                replacements,
+                block_rows: Vec::new(),
+            });
+        }
+
+        self.apply_input_rules(cx);
+        if text.ends_with(char::is_whitespace) {
+            self.link_typed_urls(cx);
+        }
+        self.update_wiki_link_completion(cx);
+    }
+
+    /// Returns the titles offered for the wiki link being typed, as long as the cursor is still
+    /// at the end of its partial title.
+    pub(crate) fn wiki_link_completion(&self, cx: &App) -> Option<&WikiLinkCompletion> {
+        let completion = self.wiki_link_completion.as_ref()?;
+        let selection = self.selections.newest();
+        let is_current = self.selections.count() == 1
+            && selection.is_empty()
+            && selection.start == completion.query_range.end
+            && completion.version == self.buffer.read(cx).version();
+        is_current.then_some(completion)
+    }
+
+    /// Offers the note titles matching the wiki link being typed at the cursor, if any.
+    fn update_wiki_link_completion(&mut self, cx: &mut Context<Self>) {
+        self.wiki_link_completion = None;
+        let selection = self.selections.newest();
+        if self.selections.count() > 1 || !selection.is_empty() {
+            return;
+        }
+
+        let buffer = self.buffer.read(cx);
+        let (text_before, _) = line_around(buffer, selection.range());
+        let Some(query) = crate::completion::wiki_link_query(&text_before) else {
+            return;
+        };
+
+        let matches =
+            crate::completion::matching_titles(&self.note_titles, &text_before[query.clone()]);
+        if matches.is_empty() {
+            return;
+        }
+
+        let line_start = selection.start - text_before.len();
+        self.wiki_link_completion = Some(WikiLinkCompletion {
+            query_range: line_start + query.start..line_start + query.end,
+            matches,
+            selected: 0,
+            version: buffer.version(),
+        });
+        cx.notify();
+    }
+
+    /// Completes the wiki link being typed with the highlighted title. Returns whether there
+    /// was a completion to accept.
+    fn confirm_wiki_link_completion(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(completion) = self.wiki_link_completion(cx) else {
+            return false;
+        };
+
+        let title = completion.matches[completion.selected].clone();
+        let query_range = completion.query_range.clone();
+        self.wiki_link_completion = None;
+        self.edit_selections(cx, |buffer, _| {
+            // The closing brackets may already follow, such as after being auto-paired
+            let (_, text_after) = line_around(buffer, query_range.end..query_range.end);
+            let end = if text_after.starts_with("]]") {
+                query_range.end + 2
+            } else {
+                query_range.end
+            };
+            Some((query_range.start..end, format!("{title}]]")))
+        });
+        true
+    }
+
+    /// Moves the highlight through the offered wiki link titles, wrapping around. Returns
+    /// whether titles are being offered.
+    fn select_wiki_link_completion(&mut self, delta: isize, cx: &mut Context<Self>) -> bool {
+        if self.wiki_link_completion(cx).is_none() {
+            return false;
+        }
+
+        if let Some(completion) = &mut self.wiki_link_completion {
+            let len = completion.matches.len() as isize;
+            completion.selected = (completion.selected as isize + delta).rem_euclid(len) as usize;
+        }
+        cx.notify();
+        true
+    }
+
+    /// Converts a markdown shortcut completed by the last input into formatting or a block
+    /// style, removing its markers, or finishes a divider. Only applies to a single cursor
+    /// outside code blocks.
+    fn apply_input_rules(&mut self, cx: &mut Context<Self>) {
+        let selection = self.selections.newest();
+        if !self.markdown_shortcuts || self.selections.count() > 1 || !selection.is_empty() {
+            return;
+        }
+
+        let buffer = self.buffer.read(cx);
+        let point = buffer.offset_to_point(selection.start);
+        let in_code_block = buffer
+            .code_blocks()
+            .iter()
+            .any(|block| block.contains_row(point.row));
+        if in_code_block {
+            return;
+        }
+
+        let line_start = selection.start - point.column;
+        let text_before = buffer.slice(line_start..selection.start);
+        let Some(rule) = crate::input_rules::input_rule(&text_before) else {
+            return;
+        };
+
+        let (replacement, block_rows) = match rule {
+            InputRule::Inline {
+                content,
+                marker_len,
+                format,
+            } => {
+                let content = line_start + content.start..line_start + content.end;
+                let literal = buffer.slice(content.start - marker_len..content.end + marker_len);
+                let new_content = content.start - marker_len..content.end - marker_len;
+
+                self.buffer.update(cx, |buffer, cx| {
+                    buffer.edit(
+                        [
+                            (content.start - marker_len..content.start, String::new()),
+                            (content.end..content.end + marker_len, String::new()),
+                        ],
+                        cx,
+                    );
+                    match format {
+                        InlineFormat::Bold => buffer.toggle_bold(new_content.clone(), cx),
+                        InlineFormat::Italic => buffer.toggle_italic(new_content.clone(), cx),
+                    }
+                });
+                ((new_content, literal), Vec::new())
+            }
+            InputRule::Block {
+                marker_len,
+                block_style,
+            } => {
+                self.buffer.update(cx, |buffer, cx| {
+                    buffer.remove(line_start..line_start + marker_len, cx);
+                    buffer.set_block_style(point.row, Some(block_style), cx);
+                });
+                ((line_start..line_start, text_before), vec![point.row])
+            }
+            // The divider is kept as typed, and the cursor moves on to a new line below it
+            InputRule::Divider => {
+                if !buffer.is_divider(point.row) {
+                    return;
+                }
+                self.buffer.update(cx, |buffer, cx| {
+                    buffer.insert(selection.start, "\n", cx);
+                });
+                (
+                    (selection.start..selection.start + 1, String::new()),
+                    Vec::new(),
+                )
+            }
+        };
+
+        self.selections
+            .select(vec![Selection::cursor(replacement.0.end)]);
+        self.applied_substitutions = Some(AppliedSubstitutions {
+            version: self.buffer.read(cx).version(),
+            replacements: vec![Some(replacement)],
+            block_rows,
+        });
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Puts back what was typed before the last input's typographic substitutions, as long as
+    /// the text and cursors haven't changed since. Returns whether anything was reverted.
+    fn revert_substitutions(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(applied) = self.applied_substitutions.take() else {
+            return false;
+        };
+
+        let unchanged = applied.version == self.buffer.read(cx).version()
+            && applied.replacements.len() == self.selections.count()
+            && self.selections.all().iter().zip(&applied.replacements).all(
+                |(selection, replacement)| {
+                    selection.is_empty()
+                        && replacement
+                            .as_ref()
+                            .is_none_or(|(range, _)| selection.start == range.end)
+                },
+            );
+        if !unchanged {
+            return false;
+        }
+
+        let mut replacements = applied.replacements.into_iter();
+        self.edit_selections(cx, |_, _| replacements.next().flatten());
+        self.buffer.update(cx, |buffer, cx| {
+            for row in applied.block_rows {
+                buffer.set_block_style(row, None, cx);
+            }
+        });
+        true
+    }
+
+    /// Handles left mouse clicks for cursor placement and selection.
+    pub fn mouse_left_down(
+        &mut self,
+        event: &MouseDownEvent,
+        position_map: &PositionMap,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(image) = position_map.image_resize_handle_at(event.position) {
+            self.image_resize = Some(ImageResize {
+                buffer_row: image.buffer_row,
+                left: image.bounds.left(),
+            });
+            return;
+        }
+
+        let Some(point) = position_map.point_for_position(event.position) else {
+            return;
+        };
+        let position = self
+            .display_map
+            .display_point_to_offset(point, self.buffer.read(cx));
+
+        if event.modifiers.platform {
+            if let Some(title) = self.wiki_link_at(position, cx) {
+                cx.emit(EditorEvent::OpenNote { title });
+                return;
+            }
+            if self.open_link_at(position, cx) {
+                return;
+            }
+        }
+
+        self.begin_selection(
+            position,
+            event.click_count,
+            event.modifiers.shift,
+            event.modifiers.alt,
+            window,
+            cx,
+        );
+    }
+
+    /// Handles mouse drag events for text selection.
+    pub fn mouse_dragged(
+        &mut self,
+        event: &MouseMoveEvent,
+        position_map: &PositionMap,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let Some(image_resize) = self.image_resize {
+            let width = event.position.x - image_resize.left;
+            self.resize_image(image_resize.buffer_row, width, cx);
+            return;
+        }
+
+        let Some(point) = position_map.point_for_position(event.position) else {
+            return;
+        };
+        let position = self
+            .display_map
+            .display_point_to_offset(point, self.buffer.read(cx));
+
+        self.extend_selection(position, window, cx);
+    }
+
+    /// Handles left mouse releases, dropping the selection if it was being dragged.
+    pub fn mouse_left_up(
+        &mut self,
+        event: &MouseUpEvent,
+        position_map: &PositionMap,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if self.image_resize.take().is_some() {
+            return;
+        }
+
+        let position = position_map
+            .point_for_position(event.position)
+            .map(|point| {
+                self.display_map
+                    .display_point_to_offset(point, self.buffer.read(cx))
+            });
+
+        self.end_selection(position, event.modifiers.alt, window, cx);
+    }
+
+    /// Starts a mouse selection, selecting the word on double-click and the line on triple-click.
+    ///
+    /// The new selection replaces the existing ones, unless `add` is set.
+    pub fn begin_selection(
+        &mut self,
+        position: usize,
+        click_count: usize,
+        extend: bool,
+        add: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if click_count <= 1 && !extend && !add && self.selection_contains(position) {
+            self.selection_drag_state = SelectionDragState::ReadyToDrag {
+                selection: self.selections.newest(),
+                click_position: position,
+            };
+            return;
+        }
+
+        self.selection_drag_state = SelectionDragState::None;
+        let buffer = self.buffer.read(cx);
+        let select_mode = match click_count {
+            0 | 1 => SelectMode::Character,
+            2 => SelectMode::Word(crate::movement::surrounding_word(buffer, position)),
+            _ => SelectMode::Line(crate::movement::line_range(buffer, position)),
+        };
+        self.select_mode = select_mode.clone();
+
+        let selection = match select_mode {
+            SelectMode::Character if extend => {
+                self.select_to(position, window, cx);
+                return;
+            }
+            SelectMode::Character => Selection::cursor(position),
+            SelectMode::Word(range) | SelectMode::Line(range) => {
+                Selection::new(range.start, range.end)
+            }
+        };
+
+        if add {
+            self.selections.insert(selection);
+        } else {
+            self.selections.select(vec![selection]);
+        }
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Extends the current mouse selection to a position, by whole words or lines if the
+    /// selection was started with a double or triple click.
+    pub fn extend_selection(
+        &mut self,
+        position: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        if let SelectionDragState::ReadyToDrag { selection, .. }
+        | SelectionDragState::Dragging { selection, .. } = self.selection_drag_state
+        {
+            self.selection_drag_state = SelectionDragState::Dragging {
+                selection,
+                drop_position: position,
+            };
+            cx.notify();
+            return;
+        }
+
+        let buffer = self.buffer.read(cx);
+        let (original, target) = match self.select_mode.clone() {
+            SelectMode::Character => {
+                self.select_to(position, window, cx);
+                return;
+            }
+            SelectMode::Word(original) => {
+                let target = crate::movement::surrounding_word(buffer, position);
+                (original, target)
+            }
+            SelectMode::Line(original) => {
+                let target = crate::movement::line_range(buffer, position);
+                (original, target)
+            }
+        };
+
+        let selection = if target.start < original.start {
+            Selection {
+                reversed: true,
+                ..Selection::new(target.start, original.end)
+            }
+        } else {
+            Selection::new(original.start, target.end.max(original.end))
+        };
+        self.selections.replace_newest(selection);
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Finishes a mouse selection, moving the dragged text to `position`, or copying it there
+    /// when `copy` is set.
+    ///
+    /// A drop outside the editor cancels the drag once the mouse release has been handled
+    /// everywhere, so that another note it was dropped on can still take the text with
+    /// [`Self::take_dragged_text`].
+    pub fn end_selection(
+        &mut self,
+        position: Option<usize>,
+        copy: bool,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match std::mem::take(&mut self.selection_drag_state) {
+            SelectionDragState::None => {}
+            SelectionDragState::ReadyToDrag { click_position, .. } => {
+                self.move_to(click_position, window, cx);
+            }
+            drag @ SelectionDragState::Dragging { selection, .. } => {
+                let Some(position) = position else {
+                    self.selection_drag_state = drag;
+                    cx.defer(|editor, cx| {
+                        editor.selection_drag_state = SelectionDragState::None;
+                        cx.notify();
+                    });
+                    return;
+                };
+
+                if self.selection_contains(position) {
+                    self.move_to(position, window, cx);
+                    return;
+                }
+
+                let range = selection.range();
+                let new_range = self.buffer.update(cx, |buffer, cx| {
+                    if copy {
+                        buffer.copy_range(range, position, cx)
+                    } else {
+                        buffer.move_range(range, position, cx)
+                    }
+                });
+
+                self.selections
+                    .select(vec![Selection::new(new_range.start, new_range.end)]);
+                self.request_autoscroll();
+                cx.notify();
+            }
+        }
+    }
+
+    /// Takes the text being dragged to drop it outside the editor, such as into another note,
+    /// removing it from this note unless `copy` is set. Returns the text along with its format
+    /// spans, relative to its start.
+    pub fn take_dragged_text(
+        &mut self,
+        copy: bool,
+        cx: &mut Context<Self>,
+    ) -> Option<(String, Vec<FormatSpan>)> {
+        let SelectionDragState::Dragging { selection, .. } =
+            std::mem::take(&mut self.selection_drag_state)
+        else {
+            return None;
+        };
+
+        let range = selection.range();
+        let buffer = self.buffer.read(cx);
+        let text = buffer.slice(range.clone());
+        let format_spans = buffer.format_spans_in_range(range.clone());
+        if !copy {
+            self.buffer
+                .update(cx, |buffer, cx| buffer.remove(range.clone(), cx));
+            self.selections.select(vec![Selection::cursor(range.start)]);
+        }
+        cx.notify();
+
+        Some((text, format_spans))
+    }
+
+    /// Returns where the dragged selection would be dropped, if it is being dragged.
+    pub(crate) fn drop_position(&self) -> Option<usize> {
+        match self.selection_drag_state {
+            SelectionDragState::Dragging { drop_position, .. }
+                if !self.selection_contains(drop_position) =>
+            {
+                Some(drop_position)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether an offset lies strictly inside the selected text, which can only be
+    /// dragged when there is a single selection.
+    fn selection_contains(&self, offset: usize) -> bool {
+        let selection = self.selections.newest();
+        self.selections.count() == 1 && selection.start < offset && offset < selection.end
+    }
+
+    /// Adds a cursor on the display row above the topmost selection.
+    pub fn add_selection_above(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.sync_display_map(cx);
+        let buffer = self.buffer.read(cx);
+        let selection = self.selections.first();
+        let head = selection.head();
+        if self.display_map.offset_to_display_point(head, buffer).row == 0 {
+            return;
+        }
+
+        let (offset, goal) = crate::movement::up(buffer, &self.display_map, head, selection.goal);
+        self.selections.insert(Selection {
+            goal,
+            ..Selection::cursor(offset)
+        });
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Adds a cursor on the display row below the bottommost selection.
+    pub fn add_selection_below(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.sync_display_map(cx);
+        let buffer = self.buffer.read(cx);
+        let selection = self.selections.last();
+        let head = selection.head();
+        let row = self.display_map.offset_to_display_point(head, buffer).row;
+        if row >= self.display_map.max_row() {
+            return;
+        }
+
+        let (offset, goal) = crate::movement::down(buffer, &self.display_map, head, selection.goal);
+        self.selections.insert(Selection {
+            goal,
+            ..Selection::cursor(offset)
+        });
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Selects the word under the newest cursor, or adds a selection at the next occurrence of
+    /// the newest selection's text, wrapping around to the start of the buffer.
+    pub fn select_next(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let newest = self.selections.newest();
+
+        if newest.is_empty() {
+            let word = crate::movement::surrounding_word(buffer, newest.start);
+            self.selections
+                .replace_newest(Selection::new(word.start, word.end));
+        } else {
+            let text = buffer.text();
+            let query = &text[newest.range()];
+            let search_start = self.selections.last().end;
+            let is_selected = |start: usize| {
+                self.selections
+                    .all()
+                    .iter()
+                    .any(|selection| selection.start < start + query.len() && start < selection.end)
+            };
+
+            let next = text[search_start..]
+                .match_indices(query)
+                .map(|(ix, _)| search_start + ix)
+                .chain(text[..search_start].match_indices(query).map(|(ix, _)| ix))
+                .find(|&start| !is_selected(start));
+            let Some(start) = next else {
+                return;
+            };
+
+            self.selections
+                .insert(Selection::new(start, start + query.len()));
+        }
+
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Reverts the substitutions made by the last input, putting back what was typed.
+    ///
+    /// The editor keeps no undo history, so this is the only change that can be undone, and only
+    /// while the text and cursors are as the substitutions left them.
+    pub fn undo(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.revert_substitutions(cx);
+    }
+
+    /// Deletes the character before each cursor, or the selected text. Dividers are deleted whole.
+    pub fn backspace(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.revert_substitutions(cx) {
+            return;
+        }
+
+        self.edit_selections(cx, |buffer, selection| {
+            if selection.is_empty()
+                && let Some(range) =
+                    crate::dividers::divider_deletion(buffer, selection.start, false)
+            {
+                return Some((range, String::new()));
+            }
+
+            if selection.is_empty() && selection.start > 0 {
+                // An empty pair is removed along with its closing character
+                let (text_before, text_after) = line_around(buffer, selection.range());
+                if let Some(previous) = text_before.chars().next_back()
+                    && let Some(next) = text_after.chars().next()
+                    && autopair::is_empty_pair(previous, next)
+                {
+                    let range =
+                        selection.start - previous.len_utf8()..selection.start + next.len_utf8();
+                    return Some((range, String::new()));
+                }
+
+                Some((selection.start - 1..selection.start, String::new()))
+            } else {
+                Some((selection.range(), String::new()))
+            }
+        });
+        self.update_wiki_link_completion(cx);
+    }
+
+    /// Delete from each cursor to the beginning of its line.
+    pub fn delete_to_beginning_of_line(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selections(cx, |buffer, selection| {
+            let cursor = selection.start;
+            let point = buffer.offset_to_point(cursor);
+            let line_start = buffer.point_to_offset(TextPoint::new(point.row, 0));
+
+            (cursor != line_start).then(|| (line_start..cursor, String::new()))
+        });
+    }
+
+    /// Deletes the character after each cursor, or the selected text. Dividers are deleted whole.
+    pub fn delete(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selections(cx, |buffer, selection| {
+            if selection.is_empty()
+                && let Some(range) = crate::dividers::divider_deletion(buffer, selection.end, true)
+            {
+                Some((range, String::new()))
+            } else if selection.is_empty() && selection.end < buffer.len() {
+                Some((selection.end..selection.end + 1, String::new()))
+            } else {
+                Some((selection.range(), String::new()))
+            }
+        });
+    }
+
+    /// Delete from each cursor to the end of its line.
+    pub fn delete_to_end_of_line(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selections(cx, |buffer, selection| {
+            let cursor = selection.start;
+            let point = buffer.offset_to_point(cursor);
+            let line_len = buffer.line_len(point.row);
+            let line_end = buffer.point_to_offset(TextPoint::new(point.row, line_len));
+
+            (cursor != line_end).then(|| (cursor..line_end, String::new()))
+        });
+    }
+
+    /// Inserts a newline character at each cursor, or accepts the highlighted wiki link title.
+    pub fn newline(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.confirm_wiki_link_completion(cx) {
+            return;
+        }
+
+        self.edit_selections(cx, |_, selection| {
+            Some((selection.range(), "\n".to_string()))
+        });
+        self.link_typed_urls(cx);
+    }
+
+    /// Moves each cursor up one line, preserving column position when possible.
+    pub fn move_up(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.select_wiki_link_completion(-1, cx) {
+            return;
+        }
+
+        self.move_selections(cx, |buffer, display_map, selection| {
+            let goal = if !selection.is_empty() {
+                SelectionGoal::None
+            } else {
+                selection.goal
+            };
+
+            let (offset, goal) = crate::movement::up(buffer, display_map, selection.head(), goal);
+            Selection {
+                goal,
+                ..Selection::cursor(offset)
+            }
+        });
+    }
+
+    /// Moves each cursor down one line, preserving column position when possible.
+    pub fn move_down(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.select_wiki_link_completion(1, cx) {
+            return;
+        }
+
+        self.move_selections(cx, |buffer, display_map, selection| {
+            let goal = if !selection.is_empty() {
+                SelectionGoal::None
+            } else {
+                selection.goal
+            };
+
+            let (offset, goal) = crate::movement::down(buffer, display_map, selection.head(), goal);
+            Selection {
+                goal,
+                ..Selection::cursor(offset)
+            }
+        });
+    }
+
+    /// Move each cursor left one character, wrapping to previous line if at start of line.
+    pub fn move_left(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections(cx, |buffer, _, selection| {
+            let cursor = if selection.is_empty() {
+                crate::movement::left(buffer, selection.start).unwrap_or(selection.start)
+            } else {
+                selection.start
+            };
+
+            Selection::cursor(cursor)
+        });
+    }
+
+    /// Move each cursor right one character, wrapping to next line if at end of line.
+    pub fn move_right(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.move_selections(cx, |buffer, _, selection| {
+            let cursor = if selection.is_empty() {
+                crate::movement::right(buffer, selection.end).unwrap_or(selection.end)
+            } else {
+                selection.end
+            };
+
+            Selection::cursor(cursor)
+        });
+    }
+
+    /// Groups the selections by the buffer rows they touch, merging groups whose rows overlap or
+    /// border each other. Each group is its rows and the indices of its selections.
+    fn selected_row_groups(&self, buffer: &Buffer) -> Vec<(Range<usize>, Range<usize>)> {
+        let mut groups: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+        for (ix, selection) in self.selections.all().iter().enumerate() {
+            let start = buffer.offset_to_point(selection.start);
+            let end = buffer.offset_to_point(selection.end);
+            // A selection ending at the start of a line doesn't touch that line
+            let end_row = if !selection.is_empty() && end.column == 0 && end.row > start.row {
+                end.row - 1
+            } else {
+                end.row
+            };
+
+            if let Some((rows, selection_ixs)) = groups.last_mut()
+                && start.row <= rows.end
+            {
+                rows.end = rows.end.max(end_row + 1);
+                selection_ixs.end = ix + 1;
+                continue;
+            }
+            groups.push((start.row..end_row + 1, ix..ix + 1));
+        }
+        groups
+    }
+
+    /// Edits the lines touched by the selections, one group of rows at a time from top to bottom.
+    ///
+    /// `edit` gets each group's rows and selections, adjusted for the lines earlier groups added
+    /// or removed, and updates the selections to follow the edited lines.
+    fn edit_selected_lines(
+        &mut self,
+        cx: &mut Context<Self>,
+        mut edit: impl FnMut(&mut Buffer, Range<usize>, &mut [Selection], &mut Context<Buffer>),
+    ) {
+        let groups = self.selected_row_groups(self.buffer.read(cx));
+        let newest = self.selections.newest();
+        let mut selections = self.selections.all().to_vec();
+
+        self.buffer.update(cx, |buffer, cx| {
+            let mut row_delta = 0isize;
+            let mut offset_delta = 0isize;
+            for (rows, selection_ixs) in groups {
+                let group = &mut selections[selection_ixs];
+                shift_selections(group, offset_delta);
+
+                let line_count = buffer.line_count();
+                let len = buffer.len();
+                let rows = rows.start.saturating_add_signed(row_delta)
+                    ..rows.end.saturating_add_signed(row_delta);
+                edit(buffer, rows, group, cx);
+
+                row_delta += buffer.line_count() as isize - line_count as isize;
+                offset_delta += buffer.len() as isize - len as isize;
+            }
+        });
+
+        let newest_ix = self
+            .selections
+            .all()
+            .iter()
+            .position(|selection| *selection == newest)
+            .unwrap_or(selections.len() - 1);
+        let new_newest = selections.remove(newest_ix);
+        selections.push(new_newest);
+
+        self.selections.select(selections);
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Swaps the selected lines with the line above them.
+    pub fn move_line_up(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selected_lines(cx, |buffer, rows, selections, cx| {
+            if rows.start == 0 {
+                return;
+            }
+
+            let delta = buffer.move_lines(rows.clone(), rows.start - 1, cx);
+            shift_selections(selections, delta);
+        });
+    }
+
+    /// Swaps the selected lines with the line below them.
+    pub fn move_line_down(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selected_lines(cx, |buffer, rows, selections, cx| {
+            if rows.end >= buffer.line_count() {
+                return;
+            }
+
+            let delta = buffer.move_lines(rows.clone(), rows.start + 1, cx);
+            shift_selections(selections, delta);
+        });
+    }
+
+    /// Inserts a copy of the selected lines above them, leaving the selections on the lower copy.
+    pub fn duplicate_line(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selected_lines(cx, |buffer, rows, selections, cx| {
+            let copy = buffer.duplicate_lines(rows, cx);
+            shift_selections(selections, copy.len() as isize);
+        });
+    }
 
-pub struct Editor {
-    focus_handle: FocusHandle,
-    buffer: Entity<Buffer>,
-    selection: Selection,
-    marked_range: Option<Selection>,
-}
+    /// Deletes the selected lines, leaving a cursor at the start of the line that takes their
+    /// place.
+    pub fn delete_line(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selected_lines(cx, |buffer, rows, selections, cx| {
+            buffer.delete_lines(rows.clone(), cx);
 
-impl Editor {
-    pub fn new(cx: &mut Context<Self>) -> Self {
-        let buffer = cx.new(|_cx| Buffer::new());
-        Self {
-            focus_handle: cx.focus_handle(),
-            buffer,
-            selection: Selection::cursor(0),
-            marked_range: None,
-        }
+            let offset = buffer.point_to_offset(TextPoint::new(rows.start, 0));
+            for selection in selections {
+                *selection = Selection::cursor(offset);
+            }
+        });
     }
 
-    pub fn buffer(&self) -> &Entity<Buffer> {
-        &self.buffer
+    /// Joins the selected lines into one, or the cursor's line with the next one, replacing each
+    /// line break and the indentation after it with a single space.
+    pub fn join_lines(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selected_lines(cx, |buffer, rows, selections, cx| {
+            let last_row = if rows.len() > 1 {
+                rows.end - 1
+            } else {
+                rows.end.min(buffer.line_count() - 1)
+            };
+
+            let mut edits = Vec::new();
+            for row in rows.start..last_row {
+                let line = buffer.line(row).unwrap_or_default();
+                let next_line = buffer.line(row + 1).unwrap_or_default();
+                let indent = next_line.len() - next_line.trim_start().len();
+
+                let line_end = buffer.point_to_offset(TextPoint::new(row, line.len()));
+                let next_start = buffer.point_to_offset(TextPoint::new(row + 1, 0));
+                let separator = if line.is_empty()
+                    || line.ends_with(char::is_whitespace)
+                    || next_line.trim_start().is_empty()
+                {
+                    ""
+                } else {
+                    " "
+                };
+                edits.push((line_end..next_start + indent, separator.to_string()));
+            }
+
+            let anchors = selections
+                .iter()
+                .map(|selection| {
+                    (
+                        buffer.anchor_before(selection.start),
+                        buffer.anchor_before(selection.end),
+                    )
+                })
+                .collect::<Vec<_>>();
+            buffer.edit(edits, cx);
+
+            for (selection, (start, end)) in selections.iter_mut().zip(anchors) {
+                selection.start = buffer.offset_for_anchor(&start);
+                selection.end = buffer.offset_for_anchor(&end);
+            }
+        });
     }
 
-    /// Converts UTF-16 byte range to UTF-8 byte range.
-    fn range_from_utf16(&self, utf16_range: &Range<usize>, cx: &App) -> Range<usize> {
+    /// Indents the selected lines, nesting list items one level deeper.
+    ///
+    /// When every selection is a cursor outside a list item, inserts one level of indentation at
+    /// each cursor instead. While typing a wiki link, accepts the highlighted title, and in a
+    /// table, moves to the next cell.
+    pub fn tab(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if self.confirm_wiki_link_completion(cx) || self.move_to_adjacent_cell(true, cx) {
+            return;
+        }
+
         let buffer = self.buffer.read(cx);
-        let start = buffer.utf16_to_byte(utf16_range.start);
-        let end = buffer.utf16_to_byte(utf16_range.end);
-        start..end
+        let indent_unit = self.indent_unit;
+        let is_list_item = |offset| {
+            let row = buffer.offset_to_point(offset).row;
+            let line = buffer.line(row).unwrap_or_default();
+            ListItem::parse(&line, indent_unit).is_some()
+        };
+        let inserts_at_cursors = self
+            .selections
+            .all()
+            .iter()
+            .all(|selection| selection.is_empty() && !is_list_item(selection.start));
+
+        if inserts_at_cursors {
+            self.handle_input(&indent_unit.text(), window, cx);
+            return;
+        }
+
+        let multiple_lines = self.selections.count() > 1
+            || self
+                .selected_row_groups(buffer)
+                .iter()
+                .any(|(rows, _)| rows.len() > 1);
+        self.edit_line_starts(cx, |line| {
+            // Blank lines inside a selection are left as they are
+            if multiple_lines && line.trim().is_empty() {
+                return None;
+            }
+            Some((0, indent_unit.text()))
+        });
     }
 
-    pub fn change_selections<R>(
+    /// Removes one level of indentation from the selected lines, moving list items one level
+    /// out. In a table, moves to the previous cell instead.
+    pub fn shift_tab(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.move_to_adjacent_cell(false, cx) {
+            return;
+        }
+
+        let indent_unit = self.indent_unit;
+        self.edit_line_starts(cx, |line| {
+            let len = indent_unit.outdent_len(line);
+            (len > 0).then(|| (len, String::new()))
+        });
+    }
+
+    /// Replaces the start of every line touched by the selections, keeping the selections on the
+    /// same text.
+    ///
+    /// `edit` gets each line's text and returns how many bytes at its start to replace, and with
+    /// what.
+    fn edit_line_starts(
         &mut self,
-        _window: &mut Window,
-        _cx: &mut Context<Self>,
-        change: impl FnOnce(&mut Selection) -> R,
-    ) -> R {
-        change(&mut self.selection)
+        cx: &mut Context<Self>,
+        mut edit: impl FnMut(&str) -> Option<(usize, String)>,
+    ) {
+        self.edit_selected_lines(cx, |buffer, rows, selections, cx| {
+            let edits = rows
+                .filter_map(|row| {
+                    let line = buffer.line(row)?;
+                    let (len, text) = edit(&line)?;
+                    let start = buffer.point_to_offset(TextPoint::new(row, 0));
+                    Some((start..start + len, text))
+                })
+                .collect::<Vec<_>>();
+
+            // Cursors at the start of a line move past the new indentation, while selections
+            // keep covering the whole line
+            let anchors = selections
+                .iter()
+                .map(|selection| {
+                    let start_bias = if selection.is_empty() {
+                        Bias::Right
+                    } else {
+                        Bias::Left
+                    };
+                    (
+                        buffer.anchor_at(selection.start, start_bias),
+                        buffer.anchor_after(selection.end),
+                    )
+                })
+                .collect::<Vec<_>>();
+            buffer.edit(edits, cx);
+
+            for (selection, (start, end)) in selections.iter_mut().zip(anchors) {
+                selection.start = buffer.offset_for_anchor(&start);
+                selection.end = buffer.offset_for_anchor(&end);
+            }
+        });
     }
 
-    /// Moves cursor to the specified offset, clearing any selection.
-    fn move_to(&mut self, offset: usize, _window: &mut Window, cx: &mut Context<Self>) {
-        self.selection = Selection::cursor(offset);
-        cx.notify();
+    /// Returns the ranges of the non-empty selections.
+    fn selected_ranges(&self) -> Vec<Range<usize>> {
+        self.selections
+            .all()
+            .iter()
+            .filter(|selection| !selection.is_empty())
+            .map(Selection::range)
+            .collect()
     }
 
-    /// Extends selection from current anchor to the specified offset.
-    fn select_to(&mut self, offset: usize, _window: &mut Window, cx: &mut Context<Self>) {
-        if self.selection.reversed {
-            self.selection.start = offset;
-        } else {
-            self.selection.end = offset;
+    /// Toggles bold formatting on the selected text.
+    pub fn toggle_bold(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let ranges = self.selected_ranges();
+        if ranges.is_empty() {
+            return;
         }
 
-        if self.selection.end < self.selection.start {
-            let start = self.selection.start;
-            let end = self.selection.end;
-            self.selection.start = end;
-            self.selection.end = start;
-            self.selection.reversed = !self.selection.reversed;
-        }
+        self.buffer.update(cx, |buffer, cx| {
+            for range in ranges {
+                buffer.toggle_bold(range, cx);
+            }
+        });
 
-        self.selection.goal = SelectionGoal::None;
         cx.notify();
     }
 
-    /// Inserts text at cursor, replacing any selected text.
-    pub fn handle_input(&mut self, text: &str, _window: &mut Window, cx: &mut Context<Self>) {
-        let range = self.selection.range();
+    /// Toggles italic formatting on the selected text.
+    pub fn toggle_italic(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let ranges = self.selected_ranges();
+        if ranges.is_empty() {
+            return;
+        }
 
-        self.buffer.update(cx, |buffer, _| {
-            buffer.replace(range.clone(), text);
+        self.buffer.update(cx, |buffer, cx| {
+            for range in ranges {
+                buffer.toggle_italic(range, cx);
+            }
         });
 
-        let new_offset = range.start + text.len();
-        self.selection = Selection::cursor(new_offset);
         cx.notify();
     }
 
-    /// Handles left mouse clicks for cursor placement and selection.
-    pub fn mouse_left_down(
-        &mut self,
-        event: &MouseDownEvent,
-        position_map: &PositionMap,
-        window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        let buffer = self.buffer.read(cx);
-        let Some(position) = position_map.point_for_position(event.position, buffer) else {
+    /// Toggles underline formatting on the selected text.
+    pub fn toggle_underline(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let ranges = self.selected_ranges();
+        if ranges.is_empty() {
             return;
-        };
-
-        if event.modifiers.shift {
-            self.select_to(position, window, cx);
-        } else {
-            self.move_to(position, window, cx);
         }
+
+        self.buffer.update(cx, |buffer, cx| {
+            for range in ranges {
+                buffer.toggle_underline(range, cx);
+            }
+        });
+
+        cx.notify();
     }
 
-    /// Handles mouse drag events for text selection.
-    pub fn mouse_dragged(
-        &mut self,
-        event: &MouseMoveEvent,
-        position_map: &PositionMap,
-        window: &mut Window,
-        cx: &mut Context<Self>,
-    ) {
-        let buffer = self.buffer.read(cx);
-        let Some(position) = position_map.point_for_position(event.position, buffer) else {
-            return;
-        };
+    /// Links the selected text to `url`. Cursors insert the URL itself as the link's text.
+    pub fn insert_link(&mut self, url: &str, _window: &mut Window, cx: &mut Context<Self>) {
+        let mut inserted = Vec::with_capacity(self.selections.count());
+        self.edit_selections_with(cx, |_, selection| {
+            inserted.push(selection.is_empty());
+            selection
+                .is_empty()
+                .then(|| SelectionEdit::new(selection.range(), url.to_string()))
+        });
 
-        self.select_to(position, window, cx);
+        let ranges = self
+            .selections
+            .all()
+            .iter()
+            .zip(inserted)
+            .map(|(selection, inserted)| {
+                if inserted {
+                    selection.start - url.len()..selection.start
+                } else {
+                    selection.range()
+                }
+            })
+            .collect::<Vec<_>>();
+        self.buffer.update(cx, |buffer, cx| {
+            for range in ranges {
+                buffer.set_link(range, Some(url.to_string()), cx);
+            }
+        });
+
+        cx.notify();
     }
 
-    /// Deletes the character before the cursor or the selected text.
-    pub fn backspace(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        let selection = self.selection;
-        if selection.is_empty() && selection.start > 0 {
-            self.selection = Selection::new(selection.start - 1, selection.start);
-        }
+    /// Points the links at each selection to `url`, keeping their text.
+    pub fn edit_link(&mut self, url: &str, _window: &mut Window, cx: &mut Context<Self>) {
+        let ranges = self.link_ranges(cx);
+        self.buffer.update(cx, |buffer, cx| {
+            for range in ranges {
+                buffer.set_link(range, Some(url.to_string()), cx);
+            }
+        });
 
-        if !self.selection.is_empty() {
-            self.buffer.update(cx, |buffer, _| {
-                buffer.remove(self.selection.range());
-            });
+        cx.notify();
+    }
 
-            self.selection = Selection::cursor(self.selection.start)
-        }
+    /// Removes the links from the selected text, or the whole link at each cursor.
+    pub fn remove_link(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let ranges = self
+            .selections
+            .all()
+            .iter()
+            .filter_map(|selection| {
+                if selection.is_empty() {
+                    buffer.link_at(selection.start).map(|(range, _)| range)
+                } else {
+                    Some(selection.range())
+                }
+            })
+            .collect::<Vec<_>>();
+        self.buffer.update(cx, |buffer, cx| {
+            for range in ranges {
+                buffer.set_link(range, None, cx);
+            }
+        });
 
-        self.selection.goal = SelectionGoal::None;
         cx.notify();
     }
 
-    /// Delete from cursor to beginning of the current line.
-    pub fn delete_to_beginning_of_line(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        let buffer = self.buffer().read(cx);
-        let cursor = self.selection.start;
-        let point = buffer.offset_to_point(cursor);
-        let line_start = buffer.point_to_offset(TextPoint::new(point.row, 0));
+    /// Returns the ranges of the links at each selection's start.
+    fn link_ranges(&self, cx: &App) -> Vec<Range<usize>> {
+        let buffer = self.buffer.read(cx);
+        let mut ranges = self
+            .selections
+            .all()
+            .iter()
+            .filter_map(|selection| buffer.link_at(selection.start).map(|(range, _)| range))
+            .collect::<Vec<_>>();
+        ranges.dedup();
+        ranges
+    }
+
+    /// Returns the title of the note the wiki link at an offset points to.
+    fn wiki_link_at(&self, offset: usize, cx: &App) -> Option<String> {
+        let buffer = self.buffer.read(cx);
+        let (text_before, text_after) = line_around(buffer, offset..offset);
+        let column = text_before.len();
+        let line = text_before + &text_after;
+
+        buffer::wiki_links(&line)
+            .into_iter()
+            .find(|link| link.range.start <= column && column <= link.range.end)
+            .map(|link| line[link.title].trim().to_string())
+    }
+
+    /// Opens the link at an offset through the platform. Returns whether there was one.
+    fn open_link_at(&mut self, offset: usize, cx: &mut Context<Self>) -> bool {
+        let Some((_, url)) = self.buffer.read(cx).link_at(offset) else {
+            return false;
+        };
+
+        let url = url.to_string();
+        cx.open_url(&url);
+        true
+    }
 
-        if cursor == line_start {
+    /// Links the bare URL each cursor just finished typing, now that whitespace follows it.
+    fn link_typed_urls(&mut self, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let links = self
+            .selections
+            .all()
+            .iter()
+            .filter(|selection| selection.is_empty() && selection.start > 0)
+            .filter_map(|selection| {
+                // The whitespace may have been a line break, leaving the URL on the line above
+                let row = buffer.offset_to_point(selection.start - 1).row;
+                let line_start = buffer.point_to_offset(TextPoint::new(row, 0));
+                let text_before = buffer.slice(line_start..selection.start);
+
+                let url = crate::links::url_before(text_before.trim_end())?;
+                let range = line_start + url.start..line_start + url.end;
+                if buffer.link_at(range.end).is_some() {
+                    return None;
+                }
+                Some((range, crate::links::link_target(&text_before[url])))
+            })
+            .collect::<Vec<_>>();
+        if links.is_empty() {
             return;
         }
 
-        self.selection = Selection::new(line_start, cursor);
-        self.backspace(window, cx);
+        self.buffer.update(cx, |buffer, cx| {
+            for (range, url) in links {
+                buffer.set_link(range, Some(url), cx);
+            }
+        });
+        cx.notify();
+    }
+
+    /// Inserts an image on a line of its own at every selection, replacing any selected text,
+    /// and leaves the cursor after it.
+    pub fn insert_image(&mut self, image: &ImageBlock, cx: &mut Context<Self>) {
+        self.insert_block(&image.to_markdown(), cx);
     }
 
-    /// Deletes the character after the cursor or the selected text.
-    pub fn delete(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        let selection = self.selection;
-        let buffer_len = self.buffer.read(cx).len();
+    /// Inserts Markdown on lines of its own at every selection, replacing any selected text,
+    /// and leaves the cursor after it.
+    fn insert_block(&mut self, markdown: &str, cx: &mut Context<Self>) {
+        self.edit_selections_with(cx, |buffer, selection| {
+            let (prefix, suffix) = block_line_breaks(buffer, selection.range());
+            let end = prefix.len() + markdown.len();
+
+            Some(SelectionEdit {
+                range: selection.range(),
+                text: format!("{prefix}{markdown}{suffix}"),
+                selection: end..end,
+            })
+        });
+    }
 
-        if selection.is_empty() && selection.end < buffer_len {
-            self.selection = Selection::new(selection.end, selection.end + 1);
+    /// Resizes the image on a buffer row to a width, keeping its aspect ratio.
+    pub fn resize_image(&mut self, row: usize, width: Pixels, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let Some(image) = buffer.image_block(row) else {
+            return;
+        };
+        let markdown =
+            crate::images::resized(&image, f32::from(width).round() as u32).to_markdown();
+        let line_start = buffer.point_to_offset(TextPoint::new(row, 0));
+        let line_range = line_start..line_start + buffer.line_len(row);
+        if buffer.slice(line_range.clone()) == markdown {
+            return;
         }
 
-        if !self.selection.is_empty() {
-            self.buffer.update(cx, |buffer, _| {
-                buffer.remove(self.selection.range());
-            });
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.replace(line_range.clone(), &markdown, cx)
+        });
 
-            self.selection = Selection::cursor(self.selection.start)
+        // Selections after the image follow it, and those inside it move to its end
+        let delta = markdown.len() as isize - line_range.len() as isize;
+        let newest = self.selections.newest();
+        let mut selections = Vec::with_capacity(self.selections.count());
+        let mut new_newest = None;
+        for &selection in self.selections.all() {
+            let adjust = |offset: usize| {
+                if offset >= line_range.end {
+                    offset.saturating_add_signed(delta)
+                } else if offset > line_range.start {
+                    line_range.start + markdown.len()
+                } else {
+                    offset
+                }
+            };
+            let new_selection = Selection {
+                start: adjust(selection.start),
+                end: adjust(selection.end),
+                ..selection
+            };
+
+            if selection == newest {
+                new_newest = Some(new_selection);
+            } else {
+                selections.push(new_selection);
+            }
         }
+        selections.extend(new_newest);
 
-        self.selection.goal = SelectionGoal::None;
+        self.selections.select(selections);
         cx.notify();
     }
 
-    /// Delete from cursor to end of the current line.
-    pub fn delete_to_end_of_line(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        let buffer = self.buffer().read(cx);
-        let cursor = self.selection.start;
-        let point = buffer.offset_to_point(cursor);
-        let line_len = buffer.line_len(point.row);
-        let line_end = buffer.point_to_offset(TextPoint::new(point.row, line_len));
+    /// Wraps the selected text in a code block on lines of its own, keeping it selected, or
+    /// inserts an empty code block with the cursor inside it.
+    pub fn insert_code_block(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selections_with(cx, |buffer, selection| {
+            let (prefix, suffix) = block_line_breaks(buffer, selection.range());
+            let code = buffer.slice(selection.range());
+            let code_start = prefix.len() + "```\n".len();
+
+            Some(SelectionEdit {
+                range: selection.range(),
+                text: format!("{prefix}```\n{code}\n```{suffix}"),
+                selection: code_start..code_start + code.len(),
+            })
+        });
+    }
+
+    /// Inserts a divider on a line of its own at every selection, replacing any selected text,
+    /// and leaves the cursor on the line after it.
+    ///
+    /// A blank line is left between the divider and any text above it, which Markdown would
+    /// otherwise read as a heading underlined by the divider.
+    pub fn insert_divider(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.edit_selections_with(cx, |buffer, selection| {
+            let (text_before, _) = line_around(buffer, selection.range());
+            let line_above = if text_before.is_empty() {
+                let row = buffer.offset_to_point(selection.start).row;
+                row.checked_sub(1)
+                    .and_then(|row| buffer.line(row))
+                    .unwrap_or_default()
+            } else {
+                text_before.clone()
+            };
+            let line_break = if text_before.is_empty() { "" } else { "\n" };
+            let blank_line = if buffer::is_heading_underline(DIVIDER, &line_above) {
+                "\n"
+            } else {
+                ""
+            };
+            let prefix = format!("{line_break}{blank_line}");
+            let end = prefix.len() + DIVIDER.len() + 1;
+
+            Some(SelectionEdit {
+                range: selection.range(),
+                text: format!("{prefix}{DIVIDER}\n"),
+                selection: end..end,
+            })
+        });
+    }
+
+    /// Inserts an empty table on lines of its own, and selects its first cell.
+    pub fn insert_table(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        self.insert_block(&Table::new(3, 2).to_markdown(), cx);
 
-        if cursor == line_end {
-            return;
+        let buffer = self.buffer.read(cx);
+        let row = buffer.offset_to_point(self.selections.newest().head()).row;
+        if let Some(table_rows) = buffer.table_rows(row) {
+            let first_cell = CellPosition {
+                row: table_rows.start,
+                column: 0,
+            };
+            self.select_table_cell(first_cell, cx);
         }
+    }
 
-        self.selection = Selection::new(cursor, line_end);
-        self.delete(window, cx);
+    /// Adds an empty row below the table row the cursor is in, or below the separator row when
+    /// on the header, and moves to its first cell.
+    pub fn insert_table_row(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let Some(cell) = tables::cell_at(buffer, self.selections.newest().head()) else {
+            return;
+        };
+        let Some(table_rows) = buffer.table_rows(cell.row) else {
+            return;
+        };
+        self.insert_table_row_below(cell.row.max(table_rows.start + 1), cx);
     }
 
-    /// Inserts a newline character at the cursor position.
-    pub fn newline(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        let selection = self.selection;
-        if !selection.is_empty() {
-            self.buffer.update(cx, |buffer, _| {
-                buffer.remove(selection.range());
-            });
+    /// Deletes the table row the cursor is in, unless it's the header or the separator row
+    /// below it, and moves to the same column of the next row.
+    pub fn delete_table_row(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let Some(cell) = tables::cell_at(buffer, self.selections.newest().head()) else {
+            return;
+        };
+        let Some(table_rows) = buffer.table_rows(cell.row) else {
+            return;
+        };
+        if cell.row < table_rows.start + 2 {
+            return;
         }
 
-        let cursor = selection.start;
-        self.buffer.update(cx, |buffer, _| {
-            buffer.insert(cursor, "\n");
+        let previous_row = cell.row - 1;
+        let start =
+            buffer.point_to_offset(TextPoint::new(previous_row, buffer.line_len(previous_row)));
+        let end = buffer.point_to_offset(TextPoint::new(cell.row, buffer.line_len(cell.row)));
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.edit([(start..end, String::new())], cx)
         });
 
-        self.selection = Selection::cursor(cursor + 1);
-        self.selection.goal = SelectionGoal::None;
-        cx.notify();
+        // Deleting the last row moves up instead, skipping the separator row
+        let row = if cell.row + 1 < table_rows.end {
+            cell.row
+        } else if previous_row == table_rows.start + 1 {
+            table_rows.start
+        } else {
+            previous_row
+        };
+        let column_count =
+            tables::row_cells(self.buffer.read(cx), row).map_or(0, |cells| cells.len());
+        let column = cell.column.min(column_count.saturating_sub(1));
+        self.select_table_cell(CellPosition { row, column }, cx);
     }
 
-    /// Moves the cursor up one line, preserving column position when possible.
-    pub fn move_up(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+    /// Adds an empty column after the column of the table cell the cursor is in, and moves to
+    /// the new cell on the cursor's row.
+    pub fn insert_table_column(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
         let buffer = self.buffer.read(cx);
-        let cursor = self.selection.head();
-        let goal = if !self.selection.is_empty() {
-            SelectionGoal::None
-        } else {
-            self.selection.goal
+        let Some(cell) = tables::cell_at(buffer, self.selections.newest().head()) else {
+            return;
+        };
+        let Some(table_rows) = buffer.table_rows(cell.row) else {
+            return;
         };
 
-        let (new_offset, new_goal) = crate::movement::up(buffer, cursor, goal);
-        self.selection.goal = new_goal;
-        self.move_to(new_offset, window, cx);
+        let separator_row = table_rows.start + 1;
+        let edits = table_rows
+            .filter_map(|row| {
+                // The new cell goes after the pipe closing the cursor's column
+                let column_end = tables::row_cells(buffer, row)?.get(cell.column)?.range.end + 1;
+                let offset = buffer.point_to_offset(TextPoint::new(row, column_end));
+                let text = if row == separator_row {
+                    " --- |"
+                } else {
+                    "     |"
+                };
+                Some((offset..offset, text.to_string()))
+            })
+            .collect::<Vec<_>>();
+        self.buffer.update(cx, |buffer, cx| buffer.edit(edits, cx));
+
+        let new_cell = CellPosition {
+            row: cell.row,
+            column: cell.column + 1,
+        };
+        self.select_table_cell(new_cell, cx);
     }
 
-    /// Moves the cursor down one line, preserving column position when possible.
-    pub fn move_down(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+    /// Deletes the column of the table cell the cursor is in, unless it's the only column.
+    pub fn delete_table_column(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
         let buffer = self.buffer.read(cx);
-        let cursor = self.selection.head();
-        let goal = if !self.selection.is_empty() {
-            SelectionGoal::None
-        } else {
-            self.selection.goal
+        let Some(cell) = tables::cell_at(buffer, self.selections.newest().head()) else {
+            return;
+        };
+        let Some(table_rows) = buffer.table_rows(cell.row) else {
+            return;
         };
+        let column_count = tables::row_cells(buffer, cell.row).map_or(0, |cells| cells.len());
+        if column_count < 2 {
+            return;
+        }
 
-        let (new_offset, new_goal) = crate::movement::down(buffer, cursor, goal);
-        self.selection.goal = new_goal;
-        self.move_to(new_offset, window, cx);
+        let edits = table_rows
+            .filter_map(|row| {
+                // The cell goes with the pipe before it, so the one after it closes the previous
+                // cell instead
+                let range = tables::row_cells(buffer, row)?
+                    .get(cell.column)?
+                    .range
+                    .clone();
+                let start = buffer.point_to_offset(TextPoint::new(row, range.start - 1));
+                Some((start..start + range.len() + 1, String::new()))
+            })
+            .collect::<Vec<_>>();
+        self.buffer.update(cx, |buffer, cx| buffer.edit(edits, cx));
+
+        let column = cell.column.min(column_count - 2);
+        self.select_table_cell(CellPosition { column, ..cell }, cx);
     }
 
-    /// Move cursor left one character, wrapping to previous line if at start of line.
-    pub fn move_left(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        let buffer = self.buffer.read(cx);
+    /// Moves to the next or previous table cell, selecting its text. Moving past the last cell
+    /// adds a row. Returns whether there was a single cursor in a table to move.
+    fn move_to_adjacent_cell(&mut self, forward: bool, cx: &mut Context<Self>) -> bool {
+        if self.selections.count() > 1 {
+            return false;
+        }
 
-        let cursor = if self.selection.is_empty() {
-            crate::movement::left(buffer, self.selection.start).unwrap_or(self.selection.start)
-        } else {
-            self.selection.start
+        let buffer = self.buffer.read(cx);
+        let Some(cell) = tables::cell_at(buffer, self.selections.newest().head()) else {
+            return false;
+        };
+        let Some(table_rows) = buffer.table_rows(cell.row) else {
+            return false;
         };
 
-        self.selection.goal = SelectionGoal::None;
-        self.move_to(cursor, window, cx);
+        match tables::adjacent_cell(buffer, cell, table_rows.clone(), forward) {
+            Some(cell) => self.select_table_cell(cell, cx),
+            None if forward => self.insert_table_row_below(table_rows.end - 1, cx),
+            None => {}
+        }
+        true
     }
 
-    /// Move cursor right one character, wrapping to next line if at end of line.
-    pub fn move_right(&mut self, window: &mut Window, cx: &mut Context<Self>) {
-        let buffer = self.buffer().read(cx);
-        let cursor = if self.selection.is_empty() {
-            crate::movement::right(buffer, self.selection.end).unwrap_or(self.selection.end)
-        } else {
-            self.selection.end
+    /// Adds an empty row below a table row, with cells as wide as that row's, and moves to its
+    /// first cell.
+    fn insert_table_row_below(&mut self, row: usize, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let Some(line) = buffer.line(row) else {
+            return;
         };
+        let Some(cells) = buffer::table_cells(&line) else {
+            return;
+        };
+
+        let indent = &line[..cells.first().map_or(0, |cell| cell.range.start - 1)];
+        let new_row = cells.iter().fold(format!("{indent}|"), |new_row, cell| {
+            new_row + &" ".repeat(cell.range.len().max(2)) + "|"
+        });
+        let line_end = buffer.point_to_offset(TextPoint::new(row, line.len()));
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.edit([(line_end..line_end, format!("\n{new_row}"))], cx)
+        });
 
-        self.selection.goal = SelectionGoal::None;
-        self.move_to(cursor, window, cx);
+        self.select_table_cell(
+            CellPosition {
+                row: row + 1,
+                column: 0,
+            },
+            cx,
+        );
     }
 
-    /// Toggles bold formatting on the current selection.
-    pub fn toggle_bold(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        if self.selection.is_empty() {
+    /// Selects the text of a table cell.
+    fn select_table_cell(&mut self, cell: CellPosition, cx: &mut Context<Self>) {
+        let Some(range) = tables::cell_text_range(self.buffer.read(cx), cell) else {
             return;
+        };
+        self.selections
+            .select(vec![Selection::new(range.start, range.end)]);
+        self.request_autoscroll();
+        cx.notify();
+    }
+
+    /// Copies the selected text to the clipboard, one selection per line.
+    pub fn copy(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let buffer = self.buffer.read(cx);
+        let text = self
+            .selections
+            .all()
+            .iter()
+            .filter(|selection| !selection.is_empty())
+            .map(|selection| buffer.slice(selection.range()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !text.is_empty() {
+            cx.write_to_clipboard(ClipboardItem::new_string(text));
         }
+    }
 
-        self.buffer.update(cx, |buffer, _cx| {
-            buffer.toggle_bold(self.selection.range());
+    /// Copies the selected text to the clipboard and deletes it.
+    pub fn cut(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.copy(window, cx);
+        self.edit_selections(cx, |_, selection| {
+            (!selection.is_empty()).then(|| (selection.range(), String::new()))
         });
-
-        cx.notify();
     }
 
-    /// Toggles italic formatting on the current selection.
-    pub fn toggle_italic(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        if self.selection.is_empty() {
+    /// Pastes the text on the clipboard at every selection. An image on the clipboard is
+    /// instead handed over to be stored as an attachment, and a Markdown table is pasted on
+    /// lines of its own with its columns lined up.
+    pub fn paste(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
+        let Some(item) = cx.read_from_clipboard() else {
             return;
-        }
+        };
 
-        self.buffer.update(cx, |buffer, _cx| {
-            buffer.toggle_italic(self.selection.range());
+        let image = item.entries().iter().find_map(|entry| match entry {
+            ClipboardEntry::Image(image) => Some(image.clone()),
+            _ => None,
         });
+        if let Some(image) = image {
+            cx.emit(EditorEvent::InsertImage { image });
+            return;
+        }
 
-        cx.notify();
+        let Some(text) = item.text() else {
+            return;
+        };
+        let text = LineEnding::normalize(&text).into_owned();
+        if let Some(table) = Table::parse(&text) {
+            self.insert_block(&table.to_markdown(), cx);
+            return;
+        }
+        self.edit_selections(cx, |_, selection| Some((selection.range(), text.clone())));
     }
 
-    /// Toggles underline formatting on the current selection.
-    pub fn toggle_underline(&mut self, _window: &mut Window, cx: &mut Context<Self>) {
-        if self.selection.is_empty() {
+    /// Hands over the images among files dropped on the editor to be stored as attachments,
+    /// after moving the cursor to where they were dropped.
+    pub fn drop_paths(
+        &mut self,
+        paths: &[PathBuf],
+        position: Point<Pixels>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let images = paths
+            .iter()
+            .filter_map(|path| read_image(path))
+            .collect::<Vec<_>>();
+        if images.is_empty() {
             return;
         }
 
-        self.buffer.update(cx, |buffer, _cx| {
-            buffer.toggle_underline(self.selection.range());
-        });
+        let point = self
+            .last_position_map
+            .as_ref()
+            .and_then(|position_map| position_map.point_for_position(position));
+        if let Some(point) = point {
+            let offset = self
+                .display_map
+                .display_point_to_offset(point, self.buffer.read(cx));
+            self.move_to(offset, window, cx);
+        }
 
-        cx.notify();
+        for image in images {
+            cx.emit(EditorEvent::InsertImage { image });
+        }
     }
 }
 
@@ -331,6 +2201,9 @@ impl Render for Editor {
             .on_action(cx.listener(|editor, _action: &Backspace, window, cx| {
                 editor.backspace(window, cx);
             }))
+            .on_action(cx.listener(|editor, _action: &Undo, window, cx| {
+                editor.undo(window, cx);
+            }))
             .on_action(
                 cx.listener(|editor, _action: &DeleteToBeginningOfLine, window, cx| {
                     editor.delete_to_beginning_of_line(window, cx);
@@ -370,10 +2243,98 @@ impl Render for Editor {
             .on_action(cx.listener(|editor, _action: &MoveRight, window, cx| {
                 editor.move_right(window, cx);
             }))
+            .on_action(
+                cx.listener(|editor, _action: &AddSelectionAbove, window, cx| {
+                    editor.add_selection_above(window, cx);
+                }),
+            )
+            .on_action(
+                cx.listener(|editor, _action: &AddSelectionBelow, window, cx| {
+                    editor.add_selection_below(window, cx);
+                }),
+            )
+            .on_action(cx.listener(|editor, _action: &SelectNext, window, cx| {
+                editor.select_next(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &MoveLineUp, window, cx| {
+                editor.move_line_up(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &MoveLineDown, window, cx| {
+                editor.move_line_down(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &DuplicateLine, window, cx| {
+                editor.duplicate_line(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &JoinLines, window, cx| {
+                editor.join_lines(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &DeleteLine, window, cx| {
+                editor.delete_line(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &Tab, window, cx| {
+                editor.tab(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &ShiftTab, window, cx| {
+                editor.shift_tab(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &InsertLink, window, cx| {
+                if let Some(url) = clipboard_url(cx) {
+                    editor.insert_link(&url, window, cx);
+                }
+            }))
+            .on_action(cx.listener(|editor, _action: &EditLink, window, cx| {
+                if let Some(url) = clipboard_url(cx) {
+                    editor.edit_link(&url, window, cx);
+                }
+            }))
+            .on_action(cx.listener(|editor, _action: &RemoveLink, window, cx| {
+                editor.remove_link(window, cx);
+            }))
+            .on_action(
+                cx.listener(|editor, _action: &InsertCodeBlock, window, cx| {
+                    editor.insert_code_block(window, cx);
+                }),
+            )
+            .on_action(cx.listener(|editor, _action: &InsertDivider, window, cx| {
+                editor.insert_divider(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &InsertTable, window, cx| {
+                editor.insert_table(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &InsertTableRow, window, cx| {
+                editor.insert_table_row(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &DeleteTableRow, window, cx| {
+                editor.delete_table_row(window, cx);
+            }))
+            .on_action(
+                cx.listener(|editor, _action: &InsertTableColumn, window, cx| {
+                    editor.insert_table_column(window, cx);
+                }),
+            )
+            .on_action(
+                cx.listener(|editor, _action: &DeleteTableColumn, window, cx| {
+                    editor.delete_table_column(window, cx);
+                }),
+            )
+            .on_action(cx.listener(|editor, _action: &Copy, window, cx| {
+                editor.copy(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &Cut, window, cx| {
+                editor.cut(window, cx);
+            }))
+            .on_action(cx.listener(|editor, _action: &Paste, window, cx| {
+                editor.paste(window, cx);
+            }))
+            .on_drop(cx.listener(|editor, paths: &ExternalPaths, window, cx| {
+                editor.drop_paths(paths.paths(), window.mouse_position(), window, cx);
+            }))
             .child(EditorElement::new(cx.entity().clone()))
     }
 }
 
+impl EventEmitter<EditorEvent> for Editor {}
+
 impl Focusable for Editor {
     fn focus_handle(&self, _cx: &App) -> FocusHandle {
         self.focus_handle.clone()
@@ -400,11 +2361,12 @@ impl EntityInputHandler for Editor {
         cx: &mut Context<Self>,
     ) -> Option<UTF16Selection> {
         let buffer = self.buffer.read(cx);
-        let start = buffer.byte_to_utf16(self.selection.start);
-        let end = buffer.byte_to_utf16(self.selection.end);
+        let selection = self.selections.newest();
+        let start = buffer.byte_to_utf16(selection.start);
+        let end = buffer.byte_to_utf16(selection.end);
         Some(UTF16Selection {
             range: start..end,
-            reversed: self.selection.reversed,
+            reversed: selection.reversed,
         })
     }
 
@@ -413,8 +2375,8 @@ impl EntityInputHandler for Editor {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) -> Option<Range<usize>> {
-        let marked_range = self.marked_range?;
         let buffer = self.buffer.read(cx);
+        let marked_range = self.marked_range(buffer)?;
         let start = buffer.byte_to_utf16(marked_range.start);
         let end = buffer.byte_to_utf16(marked_range.end);
         Some(start..end)
@@ -433,9 +2395,11 @@ impl EntityInputHandler for Editor {
     ) {
         if let Some(range_utf16) = range_utf16 {
             let range = self.range_from_utf16(&range_utf16, cx);
-            self.selection = Selection::new(range.start, range.end);
-        } else if let Some(marked_range) = self.marked_range {
-            self.selection = marked_range;
+            self.selections
+                .select(vec![Selection::new(range.start, range.end)]);
+        } else if let Some(marked_range) = self.marked_range(self.buffer.read(cx)) {
+            self.selections
+                .select(vec![Selection::new(marked_range.start, marked_range.end)]);
         }
 
         self.marked_range = None;
@@ -453,43 +2417,99 @@ impl EntityInputHandler for Editor {
         let range = range_utf16
             .as_ref()
             .map(|range| self.range_from_utf16(range, cx))
-            .unwrap_or_else(|| self.selection.range());
+            .unwrap_or_else(|| self.selections.newest().range());
 
-        self.buffer.update(cx, |buffer, _| {
-            buffer.replace(range.clone(), new_text);
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.replace(range.clone(), new_text, cx);
         });
 
         let new_cursor = range.start + new_text.len();
-        self.selection = Selection::cursor(new_cursor);
+        self.selections.select(vec![Selection::cursor(new_cursor)]);
 
-        let new_marked_range = if let Some(marked_range) = new_selection {
+        let buffer = self.buffer.read(cx);
+        self.marked_range = new_selection.map(|marked_range| {
             let start = range.start + marked_range.start;
             let end = range.start + marked_range.end;
-            Some(Selection::new(start, end))
-        } else {
-            None
-        };
-
-        self.marked_range = new_marked_range;
+            buffer.anchor_after(start)..buffer.anchor_before(end)
+        });
         cx.notify();
     }
 
     fn bounds_for_range(
         &mut self,
-        _range_utf16: Range<usize>,
+        range_utf16: Range<usize>,
         _element_bounds: Bounds<Pixels>,
         _window: &mut Window,
-        _cx: &mut Context<Self>,
+        cx: &mut Context<Self>,
     ) -> Option<Bounds<Pixels>> {
-        None
+        self.sync_display_map(cx);
+        let position_map = self.last_position_map.as_ref()?;
+        let range = self.range_from_utf16(&range_utf16, cx);
+        let buffer = self.buffer.read(cx);
+        let start = self
+            .display_map
+            .offset_to_display_point(range.start, buffer);
+        let end = self.display_map.offset_to_display_point(range.end, buffer);
+        position_map.bounds_for_range(start, end)
     }
 
     fn character_index_for_point(
         &mut self,
-        _point: Point<Pixels>,
+        point: Point<Pixels>,
         _window: &mut Window,
-        _cx: &mut Context<Self>,
+        cx: &mut Context<Self>,
     ) -> Option<usize> {
-        None
+        self.sync_display_map(cx);
+        let position_map = self.last_position_map.as_ref()?;
+        let display_point = position_map.point_for_position(point)?;
+        let buffer = self.buffer.read(cx);
+        let offset = self
+            .display_map
+            .display_point_to_offset(display_point, buffer);
+        Some(buffer.byte_to_utf16(offset))
+    }
+}
+
+/// Moves selections by a number of bytes, such as after the text before them changed length.
+fn shift_selections(selections: &mut [Selection], delta: isize) {
+    for selection in selections {
+        selection.start = selection.start.saturating_add_signed(delta);
+        selection.end = selection.end.saturating_add_signed(delta);
     }
 }
+
+/// Reads an image file, if the path has the extension of an image format.
+fn read_image(path: &Path) -> Option<Image> {
+    let format = crate::images::image_format(path)?;
+    let bytes = std::fs::read(path).ok()?;
+    Some(Image::from_bytes(format, bytes))
+}
+
+/// Returns the URL on the clipboard, if it holds one.
+fn clipboard_url(cx: &App) -> Option<String> {
+    let text = cx.read_from_clipboard()?.text()?;
+    let text = text.trim();
+    crate::links::is_url(text).then(|| crate::links::link_target(text))
+}
+
+/// Returns the line breaks to insert before and after a range for a block to replace it on lines
+/// of its own.
+fn block_line_breaks(buffer: &Buffer, range: Range<usize>) -> (&'static str, &'static str) {
+    let (text_before, text_after) = line_around(buffer, range);
+    let prefix = if text_before.is_empty() { "" } else { "\n" };
+    let suffix = if text_after.is_empty() { "" } else { "\n" };
+    (prefix, suffix)
+}
+
+/// Returns the text of a line before and after a range within it.
+fn line_around(buffer: &Buffer, range: Range<usize>) -> (String, String) {
+    let start = buffer.offset_to_point(range.start);
+    let end = buffer.offset_to_point(range.end);
+    let line_start = buffer.point_to_offset(TextPoint::new(start.row, 0));
+    let line_end = buffer.point_to_offset(TextPoint::new(end.row, buffer.line_len(end.row)));
+
+    (
+        buffer.slice(line_start..range.start),
+        buffer.slice(range.end..line_end),
+    )
+}
@@ -1,52 +1,288 @@
 use gpui::{
-    App, Bounds, ElementId, ElementInputHandler, Entity, Focusable, Font, FontStyle, FontWeight,
-    GlobalElementId, Hsla, InspectorElementId, LayoutId, MouseDownEvent, MouseMoveEvent, PaintQuad,
-    Pixels, Point, ShapedLine, Style, TextRun, UnderlineStyle, Window, prelude::*,
+    AnyElement, App, Bounds, ContentMask, CursorStyle, ElementId, ElementInputHandler, Entity,
+    Focusable, Font, FontStyle, FontWeight, GlobalElementId, Hitbox, HitboxBehavior, Hsla,
+    InspectorElementId, LayoutId, MouseDownEvent, MouseMoveEvent, MouseUpEvent, ObjectFit,
+    PaintQuad, Pixels, Point, ScrollWheelEvent, ShapedLine, Style, TextRun, UnderlineStyle, Window,
+    prelude::*,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    ops::Range,
+    rc::Rc,
 };
-use std::collections::BTreeSet;
 
-use buffer::{Buffer, FormatSpan};
+use buffer::{BlockStyle, Buffer, ColumnAlignment, FormatSpan};
+use languages::{HighlightKind, Language};
 use text::TextPoint;
 
-use crate::Editor;
+use crate::{
+    Editor, ListItem,
+    code_blocks::{Highlights, highlight_color},
+    display_map::DisplayPoint,
+    dividers::DIVIDER_THICKNESS,
+    images::RESIZE_HANDLE_SIZE,
+    tables::TableColumns,
+};
+
+/// Bullets drawn in place of list markers, cycling through them by nesting level.
+const LIST_BULLETS: [&str; 3] = ["•", "◦", "▪"];
 
+/// Layout of a single display row.
 #[derive(Clone)]
 pub struct LineLayout {
     pub shaped_line: ShapedLine,
+    /// The unordered list item the row starts, whose marker is drawn as a bullet.
+    pub list_item: Option<ListItem>,
+    pub block_style: Option<BlockStyle>,
+    /// Width of the image the row shows part of, in place of the image's markdown.
+    pub image_width: Option<Pixels>,
+    /// Where the cells are drawn on rows of a table.
+    pub table_row: Option<TableRowLayout>,
+    /// Whether the row is part of a code block, including its fences.
+    pub in_code_block: bool,
+    /// Width of the line drawn across rows holding a divider, in place of its markdown.
+    pub divider_width: Option<Pixels>,
+}
+
+impl LineLayout {
+    /// Returns the x position of a column. On rows showing an image or a divider, the start of
+    /// the line is before it and any other column after it.
+    pub fn x_for_index(&self, column: usize) -> Pixels {
+        if let Some(table_row) = &self.table_row {
+            return table_row.x_for_index(column, &self.shaped_line);
+        }
+
+        match self.block_width() {
+            Some(block_width) if column > 0 => block_width,
+            Some(_) => Pixels::ZERO,
+            None => self.shaped_line.x_for_index(column),
+        }
+    }
+
+    /// Returns the column closest to an x position, which on rows showing an image or a divider
+    /// is either side of it.
+    pub fn closest_index_for_x(&self, x: Pixels) -> usize {
+        if let Some(table_row) = &self.table_row {
+            return table_row.closest_index_for_x(x, &self.shaped_line);
+        }
+
+        match self.block_width() {
+            Some(block_width) if x < block_width / 2. => 0,
+            Some(_) => usize::MAX,
+            None => self.shaped_line.closest_index_for_x(x),
+        }
+    }
+
+    /// Returns the column at the end of the row.
+    pub fn end_column(&self) -> usize {
+        match self.image_width {
+            Some(_) => 1,
+            None => self.shaped_line.len,
+        }
+    }
+
+    /// Returns the width of the image or divider the row shows, which is selected as a whole.
+    fn block_width(&self) -> Option<Pixels> {
+        self.image_width.or(self.divider_width)
+    }
+}
+
+/// Where the cells of a table row are drawn, lined up in the table's columns rather than where
+/// the row's text puts them. The pipes between cells aren't drawn, and borders are drawn
+/// instead.
+#[derive(Clone, Debug)]
+pub struct TableRowLayout {
+    /// The columns of each cell's text, and how far right of its shaped position it's drawn.
+    pub cells: Vec<(Range<usize>, Pixels)>,
+    /// The borders between the table's columns, from its left edge to its right edge.
+    pub column_edges: Vec<Pixels>,
+    pub is_header: bool,
+    /// Whether this is the row separating the header from the rest, which shows no text.
+    pub is_separator: bool,
+}
+
+impl TableRowLayout {
+    /// Returns the x position of a column, putting columns between cells at the start of the
+    /// next cell's text.
+    fn x_for_index(&self, column: usize, shaped_line: &ShapedLine) -> Pixels {
+        for (columns, shift) in &self.cells {
+            if column <= columns.end {
+                return shaped_line.x_for_index(column.max(columns.start)) + *shift;
+            }
+        }
+
+        match self.column_edges.last() {
+            Some(&right) if column > 0 => right,
+            _ => Pixels::ZERO,
+        }
+    }
+
+    /// Returns the column closest to an x position, within the text of the cell under it.
+    fn closest_index_for_x(&self, x: Pixels, shaped_line: &ShapedLine) -> usize {
+        let Some(last_cell) = self.cells.len().checked_sub(1) else {
+            let right = self.column_edges.last().copied().unwrap_or_default();
+            return if x < right / 2. { 0 } else { usize::MAX };
+        };
+
+        let cell = self
+            .column_edges
+            .partition_point(|&edge| edge <= x)
+            .saturating_sub(1)
+            .min(last_cell);
+        let (columns, shift) = &self.cells[cell];
+        shaped_line
+            .closest_index_for_x(x - *shift)
+            .clamp(columns.start, columns.end)
+    }
+
+    /// Returns the borders of the row, and the background of the header, at a row's origin.
+    fn borders(&self, origin: Point<Pixels>, line_height: Pixels) -> Vec<PaintQuad> {
+        let (Some(&left), Some(&right)) = (self.column_edges.first(), self.column_edges.last())
+        else {
+            return Vec::new();
+        };
+        let border_color = gpui::rgba(0xffffff33);
+        let horizontal_border = |y: Pixels| {
+            gpui::fill(
+                Bounds::new(
+                    gpui::point(origin.x + left, y),
+                    gpui::size(right - left + gpui::px(1.), gpui::px(1.)),
+                ),
+                border_color,
+            )
+        };
+
+        let mut quads = Vec::new();
+        if self.is_header {
+            quads.push(gpui::fill(
+                Bounds::new(
+                    gpui::point(origin.x + left, origin.y),
+                    gpui::size(right - left, line_height),
+                ),
+                gpui::rgba(0xffffff0d),
+            ));
+            quads.push(horizontal_border(origin.y));
+        }
+        quads.extend(self.column_edges.iter().map(|&x| {
+            gpui::fill(
+                Bounds::new(
+                    gpui::point(origin.x + x, origin.y),
+                    gpui::size(gpui::px(1.), line_height),
+                ),
+                border_color,
+            )
+        }));
+        quads.push(horizontal_border(origin.y + line_height - gpui::px(1.)));
+        quads
+    }
+}
+
+/// Where an image is shown.
+#[derive(Clone, Debug)]
+pub struct ImageLayout {
+    /// The buffer row the image is written on.
+    pub buffer_row: usize,
+    pub bounds: Bounds<Pixels>,
+}
+
+impl ImageLayout {
+    /// Returns the bounds of the handle that resizes the image.
+    pub fn resize_handle_bounds(&self) -> Bounds<Pixels> {
+        Bounds::new(
+            self.bounds.bottom_right() - gpui::point(RESIZE_HANDLE_SIZE, RESIZE_HANDLE_SIZE),
+            gpui::size(RESIZE_HANDLE_SIZE, RESIZE_HANDLE_SIZE),
+        )
+    }
 }
 
 pub struct PrepaintState {
+    hitbox: Hitbox,
+    /// Layouts of the visible display rows, starting at `first_row`.
     line_layouts: Vec<LineLayout>,
-    cursor: Option<PaintQuad>,
-    selection: Option<Vec<PaintQuad>>,
+    first_row: usize,
+    scroll_position: f32,
+    cursors: Vec<PaintQuad>,
+    selections: Vec<PaintQuad>,
+    marked_text: Vec<PaintQuad>,
+    drop_cursor: Option<PaintQuad>,
+    /// Bullets drawn over the markers of unordered list items.
+    list_bullets: Vec<(Point<Pixels>, ShapedLine)>,
+    /// Backgrounds of rows with a block style that sets them apart, such as quotes.
+    block_backgrounds: Vec<PaintQuad>,
+    /// Lines drawn across the text in place of dividers.
+    dividers: Vec<PaintQuad>,
+    /// Borders around table cells, and the backgrounds of table headers.
+    table_borders: Vec<PaintQuad>,
+    /// Images with the hitbox of their resize handle.
+    images: Vec<(ImageLayout, AnyElement, Hitbox)>,
+    completion_menu: Option<CompletionMenu>,
 }
 
 #[derive(Clone)]
 pub struct PositionMap {
     pub line_layouts: Vec<LineLayout>,
+    pub first_row: usize,
     pub bounds: Bounds<Pixels>,
     pub line_height: Pixels,
+    pub scroll_position: f32,
+    pub images: Vec<ImageLayout>,
 }
 
 impl PositionMap {
-    pub fn point_for_position(&self, position: Point<Pixels>, buffer: &Buffer) -> Option<usize> {
-        if !self.bounds.contains(&position) {
+    /// Returns the display point under a position, which may lie past the end of its row.
+    pub fn point_for_position(&self, position: Point<Pixels>) -> Option<DisplayPoint> {
+        if !self.bounds.contains(&position) || self.line_layouts.is_empty() {
             return None;
         }
 
-        if buffer.is_empty() {
-            return Some(0);
-        }
-
         let relative_y = position.y - self.bounds.top();
-        let row = (relative_y / self.line_height).floor() as usize;
-        let row = row.min(self.line_layouts.len().saturating_sub(1));
+        let row = (relative_y / self.line_height + self.scroll_position).floor() as usize;
+        let last_row = self.first_row + self.line_layouts.len().saturating_sub(1);
+        let row = row.clamp(self.first_row, last_row);
 
-        let line_layout = &self.line_layouts[row];
+        let line_layout = &self.line_layouts[row - self.first_row];
         let relative_x = position.x - self.bounds.left();
-        let column = line_layout.shaped_line.closest_index_for_x(relative_x);
+        let column = line_layout.closest_index_for_x(relative_x);
+
+        Some(DisplayPoint::new(row, column))
+    }
+
+    /// Returns the top left corner of a display point, or `None` if its row is not laid out.
+    pub fn position_for_point(&self, point: DisplayPoint) -> Option<Point<Pixels>> {
+        let line_layout = self
+            .line_layouts
+            .get(point.row.checked_sub(self.first_row)?)?;
+        let x = line_layout.x_for_index(point.column);
+        let y = (point.row as f32 - self.scroll_position) * self.line_height;
 
-        Some(buffer.point_to_offset(TextPoint::new(row, column)))
+        Some(self.bounds.origin + gpui::point(x, y))
+    }
+
+    /// Returns the bounds of a range on the row its start is on.
+    pub fn bounds_for_range(
+        &self,
+        start: DisplayPoint,
+        end: DisplayPoint,
+    ) -> Option<Bounds<Pixels>> {
+        let origin = self.position_for_point(start)?;
+        let end_x = if end.row == start.row {
+            self.position_for_point(end)?.x
+        } else {
+            let line_layout = &self.line_layouts[start.row - self.first_row];
+            self.bounds.left() + line_layout.x_for_index(line_layout.end_column())
+        };
+
+        Some(Bounds::new(
+            origin,
+            gpui::size(end_x - origin.x, self.line_height),
+        ))
+    }
+
+    /// Returns the image whose resize handle is under a position.
+    pub fn image_resize_handle_at(&self, position: Point<Pixels>) -> Option<&ImageLayout> {
+        self.images
+            .iter()
+            .find(|image| image.resize_handle_bounds().contains(&position))
     }
 }
 
@@ -62,23 +298,23 @@ impl EditorElement {
     /// Calculates cursor position quad for rendering.
     fn layout_cursor(
         &self,
-        point: TextPoint,
+        point: DisplayPoint,
         line_layouts: &[LineLayout],
-        bounds: Bounds<Pixels>,
+        first_row: usize,
+        content_origin: Point<Pixels>,
         line_height: Pixels,
     ) -> Option<PaintQuad> {
-        if point.row >= line_layouts.len() {
+        if point.row < first_row || point.row >= first_row + line_layouts.len() {
             return None;
         }
 
-        let line_layout = &line_layouts[point.row];
-        let shaped_line = &line_layout.shaped_line;
-        let cursor_x = shaped_line.x_for_index(point.column);
-        let cursor_y = bounds.top() + (point.row as f32 * line_height);
+        let line_layout = &line_layouts[point.row - first_row];
+        let cursor_x = line_layout.x_for_index(point.column);
+        let cursor_y = content_origin.y + (point.row as f32 * line_height);
 
         Some(gpui::fill(
             Bounds::new(
-                gpui::point(bounds.left() + cursor_x, cursor_y),
+                gpui::point(content_origin.x + cursor_x, cursor_y),
                 gpui::size(gpui::px(2.), line_height),
             ),
             gpui::white(),
@@ -88,19 +324,66 @@ impl EditorElement {
     /// Calculates selection highlight quads for rendering.
     fn layout_selection(
         &self,
-        start_point: TextPoint,
-        end_point: TextPoint,
+        start_point: DisplayPoint,
+        end_point: DisplayPoint,
         line_layouts: &[LineLayout],
-        bounds: Bounds<Pixels>,
+        first_row: usize,
+        content_origin: Point<Pixels>,
+        line_height: Pixels,
+    ) -> Vec<PaintQuad> {
+        let mut quads = Vec::new();
+        let visible_rows = first_row..first_row + line_layouts.len();
+        let start_row = start_point.row.max(visible_rows.start);
+        let end_row = (end_point.row + 1).min(visible_rows.end);
+
+        for row in start_row..end_row {
+            let line_layout = &line_layouts[row - first_row];
+            let y = content_origin.y + (row as f32 * line_height);
+
+            let start_col = if row == start_point.row {
+                start_point.column
+            } else {
+                0
+            };
+            let end_col = if row == end_point.row {
+                end_point.column
+            } else {
+                line_layout.end_column()
+            };
+
+            let start_x = line_layout.x_for_index(start_col);
+            let end_x = line_layout.x_for_index(end_col);
+
+            quads.push(gpui::fill(
+                Bounds::from_corners(
+                    gpui::point(content_origin.x + start_x, y),
+                    gpui::point(content_origin.x + end_x, y + line_height),
+                ),
+                gpui::rgba(0x3d3d3da1),
+            ));
+        }
+
+        quads
+    }
+
+    /// Calculates underline quads for the marked text of an in-progress composition.
+    fn layout_marked_text(
+        &self,
+        start_point: DisplayPoint,
+        end_point: DisplayPoint,
+        line_layouts: &[LineLayout],
+        first_row: usize,
+        content_origin: Point<Pixels>,
         line_height: Pixels,
     ) -> Vec<PaintQuad> {
         let mut quads = Vec::new();
-        let last_row = end_point.row.min(line_layouts.len().saturating_sub(1));
+        let visible_rows = first_row..first_row + line_layouts.len();
+        let start_row = start_point.row.max(visible_rows.start);
+        let end_row = (end_point.row + 1).min(visible_rows.end);
 
-        for (offset, line_layout) in line_layouts[start_point.row..=last_row].iter().enumerate() {
-            let row = start_point.row + offset;
-            let shaped_line = &line_layout.shaped_line;
-            let y = bounds.top() + (row as f32 * line_height);
+        for row in start_row..end_row {
+            let shaped_line = &line_layouts[row - first_row].shaped_line;
+            let y = content_origin.y + ((row + 1) as f32 * line_height) - gpui::px(2.);
 
             let start_col = if row == start_point.row {
                 start_point.column
@@ -118,10 +401,10 @@ impl EditorElement {
 
             quads.push(gpui::fill(
                 Bounds::from_corners(
-                    gpui::point(bounds.left() + start_x, y),
-                    gpui::point(bounds.left() + end_x, y + line_height),
+                    gpui::point(content_origin.x + start_x, y),
+                    gpui::point(content_origin.x + end_x, y + gpui::px(1.)),
                 ),
-                gpui::rgba(0x3d3d3da1),
+                gpui::white(),
             ));
         }
 
@@ -129,6 +412,14 @@ impl EditorElement {
     }
 }
 
+/// The note titles offered while typing a wiki link, laid out below the link.
+struct CompletionMenu {
+    background: PaintQuad,
+    /// Background of the highlighted title.
+    highlight: PaintQuad,
+    titles: Vec<(Point<Pixels>, ShapedLine)>,
+}
+
 impl Element for EditorElement {
     type RequestLayoutState = ();
     type PrepaintState = PrepaintState;
@@ -144,15 +435,10 @@ impl Element for EditorElement {
         window: &mut Window,
         cx: &mut App,
     ) -> (LayoutId, Self::RequestLayoutState) {
-        let editor = self.editor.read(cx);
-        let buffer = editor.buffer().read(cx);
-        let line_count = buffer.line_count();
-        let line_height = window.line_height();
-
         let mut style = Style::default();
 
         style.size.width = gpui::relative(1.).into();
-        style.size.height = (line_height * line_count as f32).into();
+        style.size.height = gpui::relative(1.).into();
         style.padding.top = gpui::px(8.).into();
         style.padding.bottom = gpui::px(8.).into();
         style.padding.left = gpui::px(12.).into();
@@ -170,60 +456,484 @@ impl Element for EditorElement {
         window: &mut Window,
         cx: &mut App,
     ) -> Self::PrepaintState {
-        let editor = self.editor.read(cx);
-        let buffer = editor.buffer().read(cx);
-        let line_count = buffer.line_count();
-        let format_spans = buffer.format_spans();
+        let line_height = window.line_height();
         let style = window.text_style();
+        let font = style.font();
         let font_size = style.font_size.to_pixels(window.rem_size());
 
-        let mut line_layouts = Vec::with_capacity(line_count);
-        let mut byte_offset = 0;
+        let (line_layouts, first_row, image_blocks) = self.editor.update(cx, |editor, cx| {
+            editor.set_wrap_width(Some(bounds.size.width), font.clone(), font_size);
+            editor.set_line_height(line_height);
+            editor.sync_display_map(cx);
+            editor.set_visible_line_count(bounds.size.height / line_height);
+            editor.autoscroll_vertically(cx);
 
-        for line_idx in 0..line_count {
-            let text = buffer.line(line_idx).unwrap_or_default();
+            let scroll_position = editor.scroll_position();
+            let buffer = editor.buffer.read(cx);
+            let first_row = scroll_position.floor() as usize;
+            let last_row = ((scroll_position + bounds.size.height / line_height).ceil() as usize)
+                .min(editor.display_map.max_row());
 
-            let line_spans: Vec<_> = format_spans
-                .iter()
-                .filter(|s| s.range.start < byte_offset + text.len() && s.range.end > byte_offset)
-                .map(|s| FormatSpan {
-                    range: (s.range.start.max(byte_offset) - byte_offset)
-                        ..(s.range.end.min(byte_offset + text.len()) - byte_offset),
-                    bold: s.bold,
-                    italic: s.italic,
-                    underline: s.underline,
+            editor
+                .line_layout_cache
+                .start_frame(&font, font_size, style.color);
+            editor.highlight_cache.start_frame();
+
+            let indent_unit = editor.indent_unit;
+            let mut image_blocks = Vec::new();
+            let mut tables: Vec<(Range<usize>, TableColumns)> = Vec::new();
+            let code_blocks = buffer.code_blocks();
+            let mut block_highlights: HashMap<usize, Highlights> = HashMap::new();
+            let line_layouts = editor
+                .display_map
+                .lines(first_row..last_row + 1, buffer)
+                .zip(first_row..)
+                .map(|(display_line, display_row)| {
+                    // Rows showing an image are laid out empty, and the image drawn over them
+                    let image_width = display_line.image_row.and_then(|index| {
+                        let image = buffer.image_block(display_line.buffer_row)?;
+                        let size = crate::images::display_size(&image, Some(bounds.size.width));
+                        // An image scrolled partly out of view is still drawn from its top
+                        if index == 0 || display_row == first_row {
+                            image_blocks.push((
+                                display_row - index,
+                                display_line.buffer_row,
+                                image,
+                            ));
+                        }
+                        Some(size.width)
+                    });
+
+                    let (text, line_spans) = if image_width.is_some() {
+                        (String::new(), Vec::new())
+                    } else {
+                        (
+                            buffer.slice(display_line.range.clone()),
+                            line_format_spans(buffer.format_spans(), display_line.range.clone()),
+                        )
+                    };
+                    let block_style = buffer.block_style(display_line.buffer_row);
+
+                    // Code is drawn in a monospace font and highlighted by its language, with
+                    // the fences dimmed like comments
+                    let buffer_row = display_line.buffer_row;
+                    let code_block = code_blocks
+                        .iter()
+                        .find(|block| block.contains_row(buffer_row));
+                    let code_highlights = code_block.map(|block| {
+                        if block.is_fence(buffer_row) {
+                            return vec![(0..text.len(), HighlightKind::Comment)];
+                        }
+                        let Some(language) = block.language.as_deref().and_then(Language::for_tag)
+                        else {
+                            return Vec::new();
+                        };
+
+                        let code_start =
+                            buffer.point_to_offset(TextPoint::new(block.code_rows.start, 0));
+                        let highlights =
+                            block_highlights.entry(block.rows.start).or_insert_with(|| {
+                                let last_row = block.code_rows.end - 1;
+                                let code_end = buffer.point_to_offset(TextPoint::new(
+                                    last_row,
+                                    buffer.line_len(last_row),
+                                ));
+                                editor.highlight_cache.highlight(
+                                    language,
+                                    block.rows.clone(),
+                                    buffer.version(),
+                                    || buffer.slice(code_start..code_end),
+                                )
+                            });
+                        crate::code_blocks::line_highlights(
+                            highlights,
+                            display_line.range.start - code_start
+                                ..display_line.range.end - code_start,
+                        )
+                    });
+
+                    // Markers are only looked for on the first display row of a wrapped line
+                    let starts_buffer_row = display_line.range.start
+                        == buffer.point_to_offset(TextPoint::new(buffer_row, 0));
+
+                    // Dividers are drawn as a line across the text, with their markdown hidden
+                    let divider_width = (image_width.is_none()
+                        && starts_buffer_row
+                        && buffer.is_divider(buffer_row))
+                    .then_some(bounds.size.width);
+
+                    // Rows of a table are laid out in the columns of the whole table
+                    let table = if image_width.is_some() || code_block.is_some() {
+                        None
+                    } else if let Some(table) = tables
+                        .iter()
+                        .find(|(table_rows, _)| table_rows.contains(&buffer_row))
+                    {
+                        Some(table.clone())
+                    } else {
+                        buffer.table_rows(buffer_row).map(|table_rows| {
+                            let font = block_text_style(block_style, &font, style.color).0;
+                            let columns =
+                                table_columns(buffer, table_rows.clone(), &font, font_size, window);
+                            tables.push((table_rows.clone(), columns.clone()));
+                            (table_rows, columns)
+                        })
+                    };
+                    let table_cells = table.as_ref().and_then(|_| buffer::table_cells(&text));
+
+                    // Unordered list markers are hidden and drawn as bullets instead
+                    let list_item =
+                        if !starts_buffer_row || code_block.is_some() || divider_width.is_some() {
+                            None
+                        } else {
+                            ListItem::parse(&text, indent_unit)
+                                .filter(|list_item| !list_item.ordered)
+                        };
+                    let is_table_separator = table
+                        .as_ref()
+                        .is_some_and(|(table_rows, _)| buffer_row == table_rows.start + 1);
+                    let hidden_range = if is_table_separator || divider_width.is_some() {
+                        Some(0..text.len())
+                    } else {
+                        list_item
+                            .as_ref()
+                            .map(|list_item| list_item.marker.start..list_item.marker.start + 1)
+                    };
+
+                    let in_code_block = code_block.is_some();
+                    let shaped_line = editor.line_layout_cache.layout_line(
+                        text,
+                        line_spans,
+                        block_style,
+                        hidden_range.clone(),
+                        code_highlights.clone(),
+                        |text, format_spans| {
+                            let text_runs = match code_highlights {
+                                Some(code_highlights) => {
+                                    let font = crate::code_blocks::code_font(&font);
+                                    let mut text_runs =
+                                        build_text_runs(text, format_spans, &font, &style.color);
+                                    for (range, kind) in code_highlights {
+                                        text_runs =
+                                            color_text(text_runs, range, highlight_color(kind));
+                                    }
+                                    text_runs
+                                }
+                                None => {
+                                    let (font, color) =
+                                        block_text_style(block_style, &font, style.color);
+                                    let mut text_runs =
+                                        build_text_runs(text, format_spans, &font, &color);
+                                    for wiki_link in buffer::wiki_links(text) {
+                                        text_runs =
+                                            color_text(text_runs, wiki_link.range, link_color());
+                                    }
+                                    for tag in buffer::tags(text) {
+                                        text_runs = color_text(text_runs, tag.range, tag_color());
+                                    }
+                                    if let Some(hidden_range) = hidden_range {
+                                        text_runs = color_text(
+                                            text_runs,
+                                            hidden_range,
+                                            gpui::transparent_black(),
+                                        );
+                                    }
+                                    text_runs
+                                }
+                            };
+                            window.text_system().shape_line(
+                                text.to_string().into(),
+                                font_size,
+                                &text_runs,
+                                None,
+                            )
+                        },
+                    );
+
+                    let table_row = table.map(|(table_rows, columns)| {
+                        let cells = if is_table_separator {
+                            Vec::new()
+                        } else {
+                            table_cells
+                                .unwrap_or_default()
+                                .into_iter()
+                                .take(columns.count())
+                                .enumerate()
+                                .map(|(column, cell)| {
+                                    let x = shaped_line.x_for_index(cell.text.start);
+                                    let width = shaped_line.x_for_index(cell.text.end) - x;
+                                    (cell.text, columns.text_x(column, width) - x)
+                                })
+                                .collect()
+                        };
+                        TableRowLayout {
+                            cells,
+                            column_edges: columns.edges,
+                            is_header: buffer_row == table_rows.start,
+                            is_separator: is_table_separator,
+                        }
+                    });
+
+                    LineLayout {
+                        shaped_line,
+                        list_item,
+                        block_style,
+                        image_width,
+                        table_row,
+                        in_code_block,
+                        divider_width,
+                    }
                 })
-                .collect();
+                .collect::<Vec<_>>();
 
-            let text_runs = build_text_runs(&text, &line_spans, &style.font(), &style.color);
-            let shaped_line =
-                window
-                    .text_system()
-                    .shape_line(text.clone().into(), font_size, &text_runs, None);
+            (line_layouts, first_row, image_blocks)
+        });
+
+        let hitbox = window.insert_hitbox(bounds, HitboxBehavior::Normal);
+        let editor = self.editor.read(cx);
+        let buffer = editor.buffer().read(cx);
 
-            line_layouts.push(LineLayout { shaped_line });
-            byte_offset += text.len() + 1;
+        let scroll_position = editor.scroll_position();
+        let content_origin =
+            gpui::point(bounds.left(), bounds.top() - line_height * scroll_position);
+
+        let mut cursors = Vec::new();
+        let mut selections = Vec::new();
+        for selection in editor.selections() {
+            let start_point = editor
+                .display_map
+                .offset_to_display_point(selection.start, buffer);
+
+            if selection.is_empty() {
+                cursors.extend(self.layout_cursor(
+                    start_point,
+                    &line_layouts,
+                    first_row,
+                    content_origin,
+                    line_height,
+                ));
+            } else {
+                let end_point = editor
+                    .display_map
+                    .offset_to_display_point(selection.end, buffer);
+                selections.extend(self.layout_selection(
+                    start_point,
+                    end_point,
+                    &line_layouts,
+                    first_row,
+                    content_origin,
+                    line_height,
+                ));
+            }
         }
 
-        let selection = &editor.selection;
-        let start_point = buffer.offset_to_point(selection.start);
-        let end_point = buffer.offset_to_point(selection.end);
-        let line_height = window.line_height();
-        let cursor = if selection.is_empty() {
-            self.layout_cursor(start_point, &line_layouts, bounds, line_height)
-        } else {
-            None
-        };
-        let selection = if selection.is_empty() {
-            None
-        } else {
-            Some(self.layout_selection(start_point, end_point, &line_layouts, bounds, line_height))
-        };
+        let marked_text = editor
+            .marked_range(buffer)
+            .map(|marked_range| {
+                let start_point = editor
+                    .display_map
+                    .offset_to_display_point(marked_range.start, buffer);
+                let end_point = editor
+                    .display_map
+                    .offset_to_display_point(marked_range.end, buffer);
+                self.layout_marked_text(
+                    start_point,
+                    end_point,
+                    &line_layouts,
+                    first_row,
+                    content_origin,
+                    line_height,
+                )
+            })
+            .unwrap_or_default();
+
+        let drop_cursor = editor.drop_position().and_then(|offset| {
+            let point = editor.display_map.offset_to_display_point(offset, buffer);
+            self.layout_cursor(point, &line_layouts, first_row, content_origin, line_height)
+        });
+
+        let list_bullets = line_layouts
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, line_layout)| {
+                let list_item = line_layout.list_item.as_ref()?;
+                let row = first_row + ix;
+                let x = line_layout.shaped_line.x_for_index(list_item.marker.start);
+                let origin = gpui::point(
+                    content_origin.x + x,
+                    content_origin.y + row as f32 * line_height,
+                );
+
+                let bullet = LIST_BULLETS[list_item.level % LIST_BULLETS.len()];
+                let text_run = TextRun {
+                    len: bullet.len(),
+                    font: font.clone(),
+                    color: style.color,
+                    background_color: None,
+                    underline: None,
+                    strikethrough: None,
+                };
+                let shaped_bullet =
+                    window
+                        .text_system()
+                        .shape_line(bullet.into(), font_size, &[text_run], None);
+
+                Some((origin, shaped_bullet))
+            })
+            .collect();
+
+        let block_backgrounds = line_layouts
+            .iter()
+            .enumerate()
+            .filter(|(_, line_layout)| {
+                line_layout.block_style == Some(BlockStyle::Quote) || line_layout.in_code_block
+            })
+            .map(|(ix, _)| {
+                let y = content_origin.y + (first_row + ix) as f32 * line_height;
+                gpui::fill(
+                    Bounds::new(
+                        gpui::point(bounds.left(), y),
+                        gpui::size(bounds.size.width, line_height),
+                    ),
+                    gpui::rgba(0xffffff0d),
+                )
+            })
+            .collect();
+
+        let dividers = line_layouts
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, line_layout)| {
+                let width = line_layout.divider_width?;
+                let y = content_origin.y
+                    + (first_row + ix) as f32 * line_height
+                    + (line_height - DIVIDER_THICKNESS) / 2.;
+                Some(gpui::fill(
+                    Bounds::new(
+                        gpui::point(content_origin.x, y),
+                        gpui::size(width, DIVIDER_THICKNESS),
+                    ),
+                    gpui::rgba(0xffffff33),
+                ))
+            })
+            .collect();
+
+        let table_borders = line_layouts
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, line_layout)| Some((ix, line_layout.table_row.as_ref()?)))
+            .flat_map(|(ix, table_row)| {
+                let y = content_origin.y + (first_row + ix) as f32 * line_height;
+                table_row.borders(gpui::point(content_origin.x, y), line_height)
+            })
+            .collect();
+
+        let completion_menu = editor.wiki_link_completion(cx).and_then(|completion| {
+            let point = editor
+                .display_map
+                .offset_to_display_point(completion.query_range.start, buffer);
+            let line_layout = line_layouts.get(point.row.checked_sub(first_row)?)?;
+            let origin = gpui::point(
+                content_origin.x + line_layout.shaped_line.x_for_index(point.column),
+                content_origin.y + (point.row + 1) as f32 * line_height,
+            );
+
+            let padding = gpui::px(6.);
+            let titles = completion
+                .matches
+                .iter()
+                .enumerate()
+                .map(|(ix, title)| {
+                    let text_run = TextRun {
+                        len: title.len(),
+                        font: font.clone(),
+                        color: style.color,
+                        background_color: None,
+                        underline: None,
+                        strikethrough: None,
+                    };
+                    let shaped_title = window.text_system().shape_line(
+                        title.clone().into(),
+                        font_size,
+                        &[text_run],
+                        None,
+                    );
+                    let title_origin =
+                        gpui::point(origin.x + padding, origin.y + ix as f32 * line_height);
+                    (title_origin, shaped_title)
+                })
+                .collect::<Vec<_>>();
+
+            let width = titles
+                .iter()
+                .map(|(_, title)| title.width)
+                .max()
+                .unwrap_or_default()
+                + padding * 2.;
+            let background = gpui::fill(
+                Bounds::new(origin, gpui::size(width, line_height * titles.len() as f32)),
+                gpui::rgb(0x2a2a2a),
+            );
+            let highlight = gpui::fill(
+                Bounds::new(
+                    gpui::point(
+                        origin.x,
+                        origin.y + completion.selected as f32 * line_height,
+                    ),
+                    gpui::size(width, line_height),
+                ),
+                gpui::rgb(0x3d4a5c),
+            );
+
+            Some(CompletionMenu {
+                background,
+                highlight,
+                titles,
+            })
+        });
+
+        let base_dir = editor.base_dir.clone();
+        let images = image_blocks
+            .into_iter()
+            .map(|(display_row, buffer_row, image)| {
+                let size = crate::images::display_size(&image, Some(bounds.size.width));
+                let origin = gpui::point(
+                    content_origin.x,
+                    content_origin.y + display_row as f32 * line_height,
+                );
+                let source = crate::images::image_source(&image, base_dir.as_deref());
+                let mut element = gpui::img(source)
+                    .w(size.width)
+                    .h(size.height)
+                    .object_fit(ObjectFit::Contain)
+                    .into_any_element();
+                element.prepaint_as_root(origin, size.into(), window, cx);
+
+                let image_layout = ImageLayout {
+                    buffer_row,
+                    bounds: Bounds::new(origin, size),
+                };
+                let resize_handle = window
+                    .insert_hitbox(image_layout.resize_handle_bounds(), HitboxBehavior::Normal);
+                (image_layout, element, resize_handle)
+            })
+            .collect();
 
         PrepaintState {
+            hitbox,
             line_layouts,
-            cursor,
-            selection,
+            first_row,
+            scroll_position,
+            cursors,
+            selections,
+            marked_text,
+            drop_cursor,
+            list_bullets,
+            block_backgrounds,
+            dividers,
+            table_borders,
+            images,
+            completion_menu,
         }
     }
 
@@ -244,11 +954,22 @@ impl Element for EditorElement {
             cx,
         );
 
-        let position_map = PositionMap {
+        let line_height = window.line_height();
+        let position_map = Rc::new(PositionMap {
             line_layouts: prepaint.line_layouts.clone(),
+            first_row: prepaint.first_row,
             bounds,
-            line_height: window.line_height(),
-        };
+            line_height,
+            scroll_position: prepaint.scroll_position,
+            images: prepaint
+                .images
+                .iter()
+                .map(|(image_layout, _, _)| image_layout.clone())
+                .collect(),
+        });
+        self.editor.update(cx, |editor, _| {
+            editor.last_position_map = Some(position_map.clone());
+        });
 
         window.on_mouse_event({
             let editor = self.editor.clone();
@@ -276,26 +997,117 @@ impl Element for EditorElement {
             }
         });
 
-        if let Some(selection) = prepaint.selection.take() {
-            for quad in selection {
+        window.on_mouse_event({
+            let editor = self.editor.clone();
+            let position_map = position_map.clone();
+            move |event: &MouseUpEvent, phase, window, cx| {
+                if phase == gpui::DispatchPhase::Bubble && event.button == gpui::MouseButton::Left {
+                    editor.update(cx, |editor, cx| {
+                        editor.mouse_left_up(event, &position_map, window, cx);
+                    });
+                }
+            }
+        });
+
+        window.on_mouse_event({
+            let editor = self.editor.clone();
+            let hitbox = prepaint.hitbox.clone();
+            move |event: &ScrollWheelEvent, phase, window, cx| {
+                if phase == gpui::DispatchPhase::Bubble && hitbox.should_handle_scroll(window) {
+                    let delta = event.delta.pixel_delta(line_height);
+                    editor.update(cx, |editor, cx| {
+                        editor.scroll_by(-delta.y / line_height, cx);
+                    });
+                    cx.stop_propagation();
+                }
+            }
+        });
+
+        window.with_content_mask(Some(ContentMask { bounds }), |window| {
+            for quad in prepaint.block_backgrounds.drain(..) {
                 window.paint_quad(quad);
             }
-        }
 
-        let line_height = window.line_height();
-        for (row, line_layout) in prepaint.line_layouts.iter().enumerate() {
-            let y_offset = row as f32 * line_height;
-            let line_origin = gpui::point(bounds.origin.x, bounds.origin.y + y_offset);
-            line_layout
-                .shaped_line
-                .paint(line_origin, line_height, window, cx)
-                .ok();
-        }
+            for quad in prepaint.dividers.drain(..) {
+                window.paint_quad(quad);
+            }
 
-        if focus_handle.is_focused(window)
-            && let Some(cursor) = prepaint.cursor.take()
-        {
-            window.paint_quad(cursor);
+            for quad in prepaint.table_borders.drain(..) {
+                window.paint_quad(quad);
+            }
+
+            // Images get a faint background, which is all that shows until they've loaded
+            for (image_layout, element, resize_handle) in &mut prepaint.images {
+                window.paint_quad(gpui::fill(image_layout.bounds, gpui::rgba(0xffffff0d)));
+                element.paint(window, cx);
+                window.set_cursor_style(CursorStyle::ResizeUpLeftDownRight, resize_handle);
+                window.paint_quad(gpui::fill(
+                    image_layout.resize_handle_bounds(),
+                    gpui::rgba(0xffffffb3),
+                ));
+            }
+
+            for quad in prepaint.selections.drain(..) {
+                window.paint_quad(quad);
+            }
+
+            for (ix, line_layout) in prepaint.line_layouts.iter().enumerate() {
+                let row = prepaint.first_row + ix;
+                let y_offset = (row as f32 - prepaint.scroll_position) * line_height;
+                let line_origin = gpui::point(bounds.origin.x, bounds.origin.y + y_offset);
+                let shaped_line = &line_layout.shaped_line;
+                let Some(table_row) = &line_layout.table_row else {
+                    shaped_line.paint(line_origin, line_height, window, cx).ok();
+                    continue;
+                };
+
+                // Each cell is drawn by drawing the whole row where the cell's text lines up
+                // with its column, clipped to that text
+                for (columns, shift) in &table_row.cells {
+                    let left = line_origin.x + shaped_line.x_for_index(columns.start) + *shift;
+                    let right = line_origin.x + shaped_line.x_for_index(columns.end) + *shift;
+                    let cell_bounds = Bounds::from_corners(
+                        gpui::point(left, line_origin.y),
+                        gpui::point(right, line_origin.y + line_height),
+                    );
+                    window.with_content_mask(
+                        Some(ContentMask {
+                            bounds: cell_bounds,
+                        }),
+                        |window| {
+                            let origin = line_origin + gpui::point(*shift, Pixels::ZERO);
+                            shaped_line.paint(origin, line_height, window, cx).ok();
+                        },
+                    );
+                }
+            }
+
+            for (origin, bullet) in prepaint.list_bullets.drain(..) {
+                bullet.paint(origin, line_height, window, cx).ok();
+            }
+
+            for quad in prepaint.marked_text.drain(..) {
+                window.paint_quad(quad);
+            }
+
+            if focus_handle.is_focused(window) {
+                for cursor in prepaint.cursors.drain(..) {
+                    window.paint_quad(cursor);
+                }
+            }
+
+            if let Some(drop_cursor) = prepaint.drop_cursor.take() {
+                window.paint_quad(drop_cursor);
+            }
+        });
+
+        // The completion menu may reach past the editor's bottom edge, so it isn't clipped
+        if let Some(menu) = prepaint.completion_menu.take() {
+            window.paint_quad(menu.background);
+            window.paint_quad(menu.highlight);
+            for (origin, title) in menu.titles {
+                title.paint(origin, line_height, window, cx).ok();
+            }
         }
     }
 
@@ -312,6 +1124,90 @@ impl IntoElement for EditorElement {
     }
 }
 
+/// Returns the format spans overlapping a line, relative to the start of the line.
+fn line_format_spans(format_spans: &[FormatSpan], line_range: Range<usize>) -> Vec<FormatSpan> {
+    let end_ix = format_spans.partition_point(|span| span.range.start < line_range.end);
+
+    format_spans[..end_ix]
+        .iter()
+        .filter(|span| span.range.end > line_range.start)
+        .map(|span| FormatSpan {
+            range: (span.range.start.max(line_range.start) - line_range.start)
+                ..(span.range.end.min(line_range.end) - line_range.start),
+            ..span.clone()
+        })
+        .collect()
+}
+
+/// Lays out a table's columns to fit the widest text in each, measured with its formatting.
+fn table_columns(
+    buffer: &Buffer,
+    table_rows: Range<usize>,
+    font: &Font,
+    font_size: Pixels,
+    window: &Window,
+) -> TableColumns {
+    let separator_row = table_rows.start + 1;
+    let alignments: Vec<ColumnAlignment> = buffer
+        .line(separator_row)
+        .and_then(|line| {
+            let cells = buffer::table_cells(&line)?;
+            cells
+                .iter()
+                .map(|cell| ColumnAlignment::parse(&line[cell.text.clone()]))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut text_widths = vec![Pixels::ZERO; alignments.len()];
+    for row in table_rows.filter(|row| *row != separator_row) {
+        let Some(line) = buffer.line(row) else {
+            continue;
+        };
+        let Some(cells) = buffer::table_cells(&line) else {
+            continue;
+        };
+
+        let line_start = buffer.point_to_offset(TextPoint::new(row, 0));
+        let line_spans =
+            line_format_spans(buffer.format_spans(), line_start..line_start + line.len());
+        let text_runs = build_text_runs(&line, &line_spans, font, &gpui::white());
+        let shaped_line =
+            window
+                .text_system()
+                .shape_line(line.clone().into(), font_size, &text_runs, None);
+        for (width, cell) in text_widths.iter_mut().zip(cells) {
+            let cell_width =
+                shaped_line.x_for_index(cell.text.end) - shaped_line.x_for_index(cell.text.start);
+            *width = (*width).max(cell_width);
+        }
+    }
+
+    TableColumns::new(&text_widths, alignments)
+}
+
+/// Returns the base font and color for a row with a block style, such as a heading.
+fn block_text_style(block_style: Option<BlockStyle>, font: &Font, color: Hsla) -> (Font, Hsla) {
+    let mut font = font.clone();
+    let color = match block_style {
+        Some(BlockStyle::Heading(level)) => {
+            font.weight = FontWeight::BOLD;
+            match level {
+                1 => gpui::rgb(0xffffff).into(),
+                2 => gpui::rgb(0xe6e6e6).into(),
+                _ => gpui::rgb(0xcccccc).into(),
+            }
+        }
+        Some(BlockStyle::Quote) => {
+            font.style = FontStyle::Italic;
+            gpui::rgb(0xa0a0a0).into()
+        }
+        None => color,
+    };
+
+    (font, color)
+}
+
 /// Builds text runs with styling information for rendering.
 fn build_text_runs(
     text: &str,
@@ -356,6 +1252,7 @@ fn build_text_runs(
         let mut is_bold = false;
         let mut is_italic = false;
         let mut has_underline = false;
+        let mut is_link = false;
 
         for span in format_spans {
             if span.range.start <= start && span.range.end >= end {
@@ -368,6 +1265,9 @@ fn build_text_runs(
                 if span.underline == Some(true) {
                     has_underline = true;
                 }
+                if span.link.is_some() {
+                    is_link = true;
+                }
             }
         }
 
@@ -381,7 +1281,9 @@ fn build_text_runs(
             font.style = FontStyle::Italic;
         }
 
-        let underline = if has_underline {
+        // Links are underlined and colored, whatever their other formatting
+        let color = if is_link { link_color() } else { *base_color };
+        let underline = if has_underline || is_link {
             Some(UnderlineStyle {
                 thickness: gpui::px(1.0),
                 color: None,
@@ -394,7 +1296,7 @@ fn build_text_runs(
         runs.push(TextRun {
             len,
             font,
-            color: *base_color,
+            color,
             background_color: None,
             underline,
             strikethrough: None,
@@ -403,3 +1305,47 @@ fn build_text_runs(
 
     runs
 }
+
+/// Returns the color of links to web pages and other notes.
+fn link_color() -> Hsla {
+    gpui::rgb(0x6cb6ff).into()
+}
+
+/// Returns the color of `#tags`.
+fn tag_color() -> Hsla {
+    gpui::rgb(0xd7a6ff).into()
+}
+
+/// Colors a range of a line, splitting the text runs it overlaps. Transparent colors hide it.
+fn color_text(text_runs: Vec<TextRun>, range: Range<usize>, color: Hsla) -> Vec<TextRun> {
+    let mut result = Vec::with_capacity(text_runs.len() + 2);
+    let mut run_start = 0;
+
+    for run in text_runs {
+        let run_range = run_start..run_start + run.len;
+        run_start = run_range.end;
+
+        let colored = run_range.start.max(range.start)..run_range.end.min(range.end);
+        if colored.is_empty() {
+            result.push(run);
+            continue;
+        }
+
+        let parts = [
+            (run_range.start..colored.start, run.color),
+            (colored.clone(), color),
+            (colored.end..run_range.end, run.color),
+        ];
+        for (part, color) in parts {
+            if !part.is_empty() {
+                result.push(TextRun {
+                    len: part.len(),
+                    color,
+                    ..run.clone()
+                });
+            }
+        }
+    }
+
+    result
+}
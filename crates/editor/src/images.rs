@@ -0,0 +1,73 @@
+use gpui::{ImageFormat, ImageSource, Pixels, Size};
+use std::path::Path;
+
+use buffer::{ImageBlock, ImageSize};
+
+/// Size of images whose size isn't written with them.
+const DEFAULT_IMAGE_SIZE: ImageSize = ImageSize {
+    width: 480,
+    height: 270,
+};
+
+/// Narrowest width an image can be resized to.
+const MIN_IMAGE_WIDTH: u32 = 48;
+
+/// Size of the handle at an image's bottom right corner that resizes it.
+pub const RESIZE_HANDLE_SIZE: Pixels = gpui::px(10.);
+
+/// Returns the size an image is shown at, shrunk to fit `max_width` if it's wider.
+pub fn display_size(image: &ImageBlock, max_width: Option<Pixels>) -> Size<Pixels> {
+    let mut size = image.size.unwrap_or(DEFAULT_IMAGE_SIZE);
+    if let Some(max_width) = max_width.map(f32::from)
+        && size.width as f32 > max_width
+    {
+        size = size.scaled_to_width(max_width as u32);
+    }
+
+    gpui::size(gpui::px(size.width as f32), gpui::px(size.height as f32))
+}
+
+/// Returns the image resized to a width, keeping its aspect ratio.
+pub fn resized(image: &ImageBlock, width: u32) -> ImageBlock {
+    let size = image.size.unwrap_or(DEFAULT_IMAGE_SIZE);
+    ImageBlock {
+        size: Some(size.scaled_to_width(width.max(MIN_IMAGE_WIDTH))),
+        ..image.clone()
+    }
+}
+
+/// Returns the number of display rows an image of a given height takes.
+pub fn row_count(height: Pixels, line_height: Pixels) -> usize {
+    if line_height <= Pixels::ZERO {
+        return 1;
+    }
+    ((height / line_height).ceil() as usize).max(1)
+}
+
+/// Returns where to load an image from, resolving paths relative to `base_dir`.
+pub fn image_source(image: &ImageBlock, base_dir: Option<&Path>) -> ImageSource {
+    let source = image.source.as_str();
+    if crate::links::is_url(source) {
+        return crate::links::link_target(source).into();
+    }
+
+    match base_dir {
+        Some(base_dir) => base_dir.join(source).into(),
+        None => Path::new(source).to_path_buf().into(),
+    }
+}
+
+/// Returns the format of an image file from its extension.
+pub fn image_format(path: &Path) -> Option<ImageFormat> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        "gif" => Some(ImageFormat::Gif),
+        "webp" => Some(ImageFormat::Webp),
+        "svg" => Some(ImageFormat::Svg),
+        "bmp" => Some(ImageFormat::Bmp),
+        "tif" | "tiff" => Some(ImageFormat::Tiff),
+        _ => None,
+    }
+}
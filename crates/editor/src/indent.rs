@@ -0,0 +1,102 @@
+use std::ops::Range;
+
+/// Number of columns a tab character takes up when measuring indentation.
+const TAB_WIDTH: usize = 4;
+
+/// The whitespace inserted for each level of indentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndentUnit {
+    /// Indent with the given number of spaces.
+    Spaces(usize),
+    /// Indent with a tab character.
+    Tab,
+}
+
+impl Default for IndentUnit {
+    fn default() -> Self {
+        Self::Spaces(4)
+    }
+}
+
+impl IndentUnit {
+    /// Returns the text inserted for one level of indentation.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Spaces(count) => " ".repeat(*count),
+            Self::Tab => "\t".to_string(),
+        }
+    }
+
+    /// Returns how many columns one level of indentation takes up.
+    pub fn width(&self) -> usize {
+        match self {
+            Self::Spaces(count) => (*count).max(1),
+            Self::Tab => TAB_WIDTH,
+        }
+    }
+
+    /// Returns how many levels deep a line's leading whitespace is indented.
+    pub fn level(&self, line: &str) -> usize {
+        let columns = line
+            .chars()
+            .map_while(|char| match char {
+                ' ' => Some(1),
+                '\t' => Some(TAB_WIDTH),
+                _ => None,
+            })
+            .sum::<usize>();
+        columns / self.width()
+    }
+
+    /// Returns the length of the leading whitespace that removing one level of indentation from
+    /// a line would delete.
+    pub fn outdent_len(&self, line: &str) -> usize {
+        if line.starts_with('\t') {
+            return 1;
+        }
+
+        line.chars()
+            .take(self.width())
+            .take_while(|char| *char == ' ')
+            .count()
+    }
+}
+
+/// A line that starts a list item, such as `- milk` or `2. eggs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListItem {
+    /// The marker and the space after it, relative to the start of the line.
+    pub marker: Range<usize>,
+    /// How many levels deep the item is nested.
+    pub level: usize,
+    /// Whether the item is numbered rather than bulleted.
+    pub ordered: bool,
+}
+
+impl ListItem {
+    /// Returns the list item a line starts, if any.
+    pub fn parse(line: &str, indent_unit: IndentUnit) -> Option<Self> {
+        let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let rest = &line[indent..];
+
+        let (marker_len, ordered) = if rest.starts_with(['-', '*', '+']) {
+            (1, false)
+        } else {
+            let digits = rest.chars().take_while(char::is_ascii_digit).count();
+            if digits == 0 || !rest[digits..].starts_with(['.', ')']) {
+                return None;
+            }
+            (digits + 1, true)
+        };
+
+        if !rest[marker_len..].starts_with(' ') {
+            return None;
+        }
+
+        Some(Self {
+            marker: indent..indent + marker_len + 1,
+            level: indent_unit.level(line),
+            ordered,
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use std::ops::Range;
+
+use buffer::{BlockStyle, DIVIDER};
+
+/// Inline formatting a markdown shortcut applies.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InlineFormat {
+    Bold,
+    Italic,
+}
+
+/// A markdown shortcut completed by the text typed before the cursor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputRule {
+    /// Text wrapped in markers that end at the cursor, such as `**bold**`.
+    Inline {
+        /// The wrapped text, relative to the start of the line.
+        content: Range<usize>,
+        marker_len: usize,
+        format: InlineFormat,
+    },
+    /// A marker that makes up the line so far, such as `# `.
+    Block {
+        marker_len: usize,
+        block_style: BlockStyle,
+    },
+    /// A divider making up the line so far, `---`.
+    Divider,
+}
+
+/// Markers wrapping inline text and the formatting they stand for, longest first.
+const INLINE_MARKERS: [(&str, InlineFormat); 3] = [
+    ("**", InlineFormat::Bold),
+    ("*", InlineFormat::Italic),
+    ("_", InlineFormat::Italic),
+];
+
+/// Returns the shortcut completed by the text before the cursor on its line, if any.
+pub fn input_rule(text_before: &str) -> Option<InputRule> {
+    if text_before == DIVIDER {
+        return Some(InputRule::Divider);
+    }
+
+    if let Some((block_style, "")) = BlockStyle::strip_marker(text_before) {
+        return Some(InputRule::Block {
+            marker_len: text_before.len(),
+            block_style,
+        });
+    }
+
+    INLINE_MARKERS.iter().find_map(|(marker, format)| {
+        let content = wrapped_content(text_before, marker)?;
+        Some(InputRule::Inline {
+            content,
+            marker_len: marker.len(),
+            format: *format,
+        })
+    })
+}
+
+/// Returns the range of the text wrapped in `marker` at the end of `text`.
+///
+/// The text must not start or end with whitespace, and the opening marker must not be part of a
+/// longer run of marker characters or follow a word, so `snake_case_names` are left alone.
+fn wrapped_content(text: &str, marker: &str) -> Option<Range<usize>> {
+    let marker_char = marker.chars().next()?;
+    let rest = text.strip_suffix(marker)?;
+    if rest.ends_with(marker_char) {
+        return None;
+    }
+
+    let open = rest.rfind(marker)?;
+    let content = open + marker.len()..rest.len();
+    let wrapped = &rest[content.clone()];
+    if wrapped.is_empty()
+        || wrapped.starts_with(char::is_whitespace)
+        || wrapped.ends_with(char::is_whitespace)
+        || wrapped.starts_with(marker_char)
+    {
+        return None;
+    }
+
+    let before_open = rest[..open].chars().next_back();
+    if before_open.is_some_and(|char| char == marker_char || char.is_alphanumeric()) {
+        return None;
+    }
+
+    Some(content)
+}
@@ -0,0 +1,84 @@
+use gpui::{Font, Hsla, Pixels, ShapedLine};
+use std::{collections::HashMap, mem, ops::Range};
+
+use buffer::{BlockStyle, FormatSpan};
+use languages::HighlightKind;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct LineLayoutKey {
+    text: String,
+    format_spans: Vec<FormatSpan>,
+    block_style: Option<BlockStyle>,
+    hidden_range: Option<Range<usize>>,
+    code_highlights: Option<Vec<(Range<usize>, HighlightKind)>>,
+}
+
+#[derive(Clone, PartialEq)]
+struct LineLayoutStyle {
+    font: Font,
+    font_size: Pixels,
+    color: Hsla,
+}
+
+/// Shaped lines reused across frames, keyed by line content and formatting.
+///
+/// Lines not requested while laying out a frame are evicted when the next frame starts,
+/// so an edit only reshapes the lines it touched.
+#[derive(Default)]
+pub struct LineLayoutCache {
+    style: Option<LineLayoutStyle>,
+    previous_frame: HashMap<LineLayoutKey, ShapedLine>,
+    current_frame: HashMap<LineLayoutKey, ShapedLine>,
+}
+
+impl LineLayoutCache {
+    /// Starts laying out a new frame, discarding every layout if the text style changed.
+    pub fn start_frame(&mut self, font: &Font, font_size: Pixels, color: Hsla) {
+        let style = LineLayoutStyle {
+            font: font.clone(),
+            font_size,
+            color,
+        };
+
+        if self.style.as_ref() != Some(&style) {
+            self.style = Some(style);
+            self.previous_frame.clear();
+            self.current_frame.clear();
+        }
+
+        self.previous_frame = mem::take(&mut self.current_frame);
+    }
+
+    /// Returns the cached layout for a line, shaping it only if it wasn't laid out recently.
+    ///
+    /// `hidden_range` is text drawn over by a decoration, such as a list marker replaced by a
+    /// bullet, which `shape` is expected to leave invisible. `code_highlights` are set for rows
+    /// of code blocks, which are shaped as code even without any highlights.
+    pub fn layout_line(
+        &mut self,
+        text: String,
+        format_spans: Vec<FormatSpan>,
+        block_style: Option<BlockStyle>,
+        hidden_range: Option<Range<usize>>,
+        code_highlights: Option<Vec<(Range<usize>, HighlightKind)>>,
+        shape: impl FnOnce(&str, &[FormatSpan]) -> ShapedLine,
+    ) -> ShapedLine {
+        let key = LineLayoutKey {
+            text,
+            format_spans,
+            block_style,
+            hidden_range,
+            code_highlights,
+        };
+        if let Some(shaped_line) = self.current_frame.get(&key) {
+            return shaped_line.clone();
+        }
+
+        let shaped_line = self
+            .previous_frame
+            .remove(&key)
+            .unwrap_or_else(|| shape(&key.text, &key.format_spans));
+        self.current_frame.insert(key, shaped_line.clone());
+        shaped_line
+    }
+}
@@ -0,0 +1,41 @@
+use std::ops::Range;
+
+/// Prefixes that start a URL typed as plain text.
+const URL_PREFIXES: [&str; 3] = ["https://", "http://", "www."];
+
+/// Characters that often wrap a URL in prose without being part of it.
+const OPENING_PUNCTUATION: &[char] = &['(', '[', '<', '"', '\'', '“', '‘'];
+const CLOSING_PUNCTUATION: &[char] = &[
+    '.', ',', ';', ':', '!', '?', ')', ']', '>', '"', '\'', '”', '’',
+];
+
+/// Returns whether `text` is a single URL, such as `https://example.com` or `www.example.com`.
+pub fn is_url(text: &str) -> bool {
+    !text.contains(char::is_whitespace)
+        && URL_PREFIXES
+            .iter()
+            .any(|prefix| text.len() > prefix.len() && text.starts_with(prefix))
+}
+
+/// Returns the URL a link to `text` opens, adding a scheme to addresses starting with `www.`.
+pub fn link_target(text: &str) -> String {
+    if text.starts_with("www.") {
+        format!("https://{text}")
+    } else {
+        text.to_string()
+    }
+}
+
+/// Returns the range of the URL at the end of `text`, leaving out punctuation around it.
+pub fn url_before(text: &str) -> Option<Range<usize>> {
+    let word_start = text.rfind(char::is_whitespace).map_or(0, |ix| {
+        ix + text[ix..].chars().next().map_or(0, char::len_utf8)
+    });
+    let word = &text[word_start..];
+
+    let trimmed = word.trim_start_matches(OPENING_PUNCTUATION);
+    let start = word_start + word.len() - trimmed.len();
+    let end = start + trimmed.trim_end_matches(CLOSING_PUNCTUATION).len();
+
+    is_url(&text[start..end]).then_some(start..end)
+}
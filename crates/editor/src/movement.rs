@@ -1,14 +1,21 @@
+use std::ops::Range;
+
 use buffer::{Buffer, SelectionGoal};
 use text::TextPoint;
 
-/// Move cursor left one character, wrapping to previous line if at start of line.
+use crate::display_map::{DisplayMap, DisplayPoint};
+
+/// Move cursor left one character, wrapping to previous line if at start of line. Dividers are
+/// moved across in one step.
 pub fn left(buffer: &Buffer, offset: usize) -> Option<usize> {
     if offset == 0 {
         return None;
     }
 
     let point = buffer.offset_to_point(offset);
-    if point.column > 0 {
+    if point.column > 0 && buffer.is_divider(point.row) {
+        Some(offset - point.column)
+    } else if point.column > 0 {
         Some(offset - 1)
     } else if point.row > 0 {
         let prev_row = point.row - 1;
@@ -19,14 +26,18 @@ pub fn left(buffer: &Buffer, offset: usize) -> Option<usize> {
     }
 }
 
-/// Move cursor right one character, wrapping to next line if at end of line.
+/// Move cursor right one character, wrapping to next line if at end of line. Dividers are
+/// moved across in one step.
 pub fn right(buffer: &Buffer, offset: usize) -> Option<usize> {
     if offset >= buffer.len() {
         return None;
     }
 
     let point = buffer.offset_to_point(offset);
-    if point.column < buffer.line_len(point.row) {
+    let line_len = buffer.line_len(point.row);
+    if point.column < line_len && buffer.is_divider(point.row) {
+        Some(offset - point.column + line_len)
+    } else if point.column < line_len {
         Some(offset + 1)
     } else if point.row < buffer.max_point().row {
         let next_row = point.row + 1;
@@ -36,46 +47,116 @@ pub fn right(buffer: &Buffer, offset: usize) -> Option<usize> {
     }
 }
 
-/// Move cursor up one line, preserving column position when possible.
-pub fn up(buffer: &Buffer, offset: usize, goal: SelectionGoal) -> (usize, SelectionGoal) {
-    let current_point = buffer.offset_to_point(offset);
+/// Move cursor up one display row, preserving column position when possible.
+pub fn up(
+    buffer: &Buffer,
+    display_map: &DisplayMap,
+    offset: usize,
+    goal: SelectionGoal,
+) -> (usize, SelectionGoal) {
+    let current_point = display_map.offset_to_display_point(offset, buffer);
     let goal_column = match goal {
         SelectionGoal::None => current_point.column as f64,
         SelectionGoal::HorizontalPosition(col) => col,
     };
 
-    if current_point.row == 0 {
-        let new_offset = buffer.point_to_offset(TextPoint::new(0, 0));
-        return (new_offset, SelectionGoal::HorizontalPosition(goal_column));
+    // Rows taken by an image are moved across at once
+    let block_start = display_map.block_rows(current_point.row).start;
+    if block_start == 0 {
+        return (0, SelectionGoal::HorizontalPosition(goal_column));
     }
 
-    let prev_row = current_point.row - 1;
-    let prev_line_len = buffer.line_len(prev_row);
-    let new_column = (goal_column as usize).min(prev_line_len);
-
-    let new_offset = buffer.point_to_offset(TextPoint::new(prev_row, new_column));
+    let prev_row = block_start - 1;
+    let new_point = DisplayPoint::new(prev_row, goal_column as usize);
+    let new_offset = display_map.display_point_to_offset(new_point, buffer);
     (new_offset, SelectionGoal::HorizontalPosition(goal_column))
 }
 
-/// Move cursor down one line, preserving column position when possible.
-pub fn down(buffer: &Buffer, offset: usize, goal: SelectionGoal) -> (usize, SelectionGoal) {
-    let current_point = buffer.offset_to_point(offset);
-    let line_count = buffer.line_count();
+/// Move cursor down one display row, preserving column position when possible.
+pub fn down(
+    buffer: &Buffer,
+    display_map: &DisplayMap,
+    offset: usize,
+    goal: SelectionGoal,
+) -> (usize, SelectionGoal) {
+    let current_point = display_map.offset_to_display_point(offset, buffer);
     let goal_column = match goal {
         SelectionGoal::None => current_point.column as f64,
         SelectionGoal::HorizontalPosition(col) => col,
     };
 
-    if current_point.row >= line_count - 1 {
-        let last_line_len = buffer.line_len(current_point.row);
-        let new_offset = buffer.point_to_offset(TextPoint::new(current_point.row, last_line_len));
-        return (new_offset, SelectionGoal::HorizontalPosition(goal_column));
+    let block_end = display_map.block_rows(current_point.row).end;
+    if block_end > display_map.max_row() {
+        return (buffer.len(), SelectionGoal::HorizontalPosition(goal_column));
     }
 
-    let next_row = current_point.row + 1;
-    let next_line_len = buffer.line_len(next_row);
-    let new_column = (goal_column as usize).min(next_line_len);
-
-    let new_offset = buffer.point_to_offset(TextPoint::new(next_row, new_column));
+    let next_row = block_end;
+    let new_point = DisplayPoint::new(next_row, goal_column as usize);
+    let new_offset = display_map.display_point_to_offset(new_point, buffer);
     (new_offset, SelectionGoal::HorizontalPosition(goal_column))
 }
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum CharKind {
+    Whitespace,
+    Punctuation,
+    Word,
+}
+
+impl CharKind {
+    fn of(ch: char) -> Self {
+        if ch.is_alphanumeric() || ch == '_' {
+            CharKind::Word
+        } else if ch.is_whitespace() {
+            CharKind::Whitespace
+        } else {
+            CharKind::Punctuation
+        }
+    }
+}
+
+/// Returns the range of the word at an offset, or of the run of whitespace or punctuation it is in.
+pub fn surrounding_word(buffer: &Buffer, offset: usize) -> Range<usize> {
+    let point = buffer.offset_to_point(offset);
+    let line = buffer.line(point.row).unwrap_or_default();
+    let line_start = offset - point.column;
+    let column = point.column.min(line.len());
+
+    let (before, after) = line.split_at(column);
+    let Some(kind) = after
+        .chars()
+        .next()
+        .or_else(|| before.chars().next_back())
+        .map(CharKind::of)
+    else {
+        return offset..offset;
+    };
+
+    let start = before
+        .char_indices()
+        .rev()
+        .take_while(|(_, ch)| CharKind::of(*ch) == kind)
+        .last()
+        .map_or(column, |(ix, _)| ix);
+    let end = column
+        + after
+            .chars()
+            .take_while(|ch| CharKind::of(*ch) == kind)
+            .map(char::len_utf8)
+            .sum::<usize>();
+
+    line_start + start..line_start + end
+}
+
+/// Returns the range of the line at an offset, including its trailing newline.
+pub fn line_range(buffer: &Buffer, offset: usize) -> Range<usize> {
+    let row = buffer.offset_to_point(offset).row;
+    let start = buffer.point_to_offset(TextPoint::new(row, 0));
+    let end = if row < buffer.max_point().row {
+        buffer.point_to_offset(TextPoint::new(row + 1, 0))
+    } else {
+        buffer.len()
+    };
+
+    start..end
+}
@@ -0,0 +1,56 @@
+/// Number of rows kept visible above and below the cursor when autoscrolling.
+const VERTICAL_SCROLL_MARGIN: f32 = 3.;
+
+#[derive(Default)]
+pub struct ScrollManager {
+    /// Topmost visible row, fractional when a row is partially scrolled out of view.
+    scroll_position: f32,
+    visible_line_count: Option<f32>,
+    autoscroll_requested: bool,
+}
+
+impl ScrollManager {
+    pub fn scroll_position(&self) -> f32 {
+        self.scroll_position
+    }
+
+    /// Sets the topmost visible row, allowing the last row to be scrolled up to the top.
+    pub fn set_scroll_position(&mut self, position: f32, max_row: usize) {
+        self.scroll_position = position.clamp(0., max_row as f32);
+    }
+
+    pub fn set_visible_line_count(&mut self, count: f32) {
+        self.visible_line_count = Some(count);
+    }
+
+    pub fn request_autoscroll(&mut self) {
+        self.autoscroll_requested = true;
+    }
+
+    pub fn take_autoscroll_request(&mut self) -> bool {
+        std::mem::take(&mut self.autoscroll_requested)
+    }
+
+    /// Scrolls the minimum amount needed to keep `row` inside the visible rows.
+    pub fn autoscroll_to_row(&mut self, row: usize, max_row: usize) {
+        let Some(visible_line_count) = self.visible_line_count else {
+            return;
+        };
+
+        let margin = VERTICAL_SCROLL_MARGIN
+            .min(((visible_line_count - 1.) / 2.).floor())
+            .max(0.);
+        let row = row as f32;
+        let top = self.scroll_position;
+
+        let new_top = if row - margin < top {
+            row - margin
+        } else if row + 1. + margin > top + visible_line_count {
+            row + 1. + margin - visible_line_count
+        } else {
+            return;
+        };
+
+        self.set_scroll_position(new_top, max_row);
+    }
+}
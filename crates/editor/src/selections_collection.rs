@@ -0,0 +1,92 @@
+use buffer::Selection;
+
+/// The editor's selections, kept sorted by position and non-overlapping.
+pub struct SelectionsCollection {
+    selections: Vec<Selection>,
+    /// Index of the most recently added selection.
+    newest: usize,
+}
+
+impl Default for SelectionsCollection {
+    fn default() -> Self {
+        Self {
+            selections: vec![Selection::cursor(0)],
+            newest: 0,
+        }
+    }
+}
+
+impl SelectionsCollection {
+    pub fn all(&self) -> &[Selection] {
+        &self.selections
+    }
+
+    pub fn count(&self) -> usize {
+        self.selections.len()
+    }
+
+    pub fn first(&self) -> Selection {
+        self.selections[0]
+    }
+
+    pub fn last(&self) -> Selection {
+        self.selections[self.selections.len() - 1]
+    }
+
+    pub fn newest(&self) -> Selection {
+        self.selections[self.newest]
+    }
+
+    /// Replaces all selections, treating the last one given as the newest.
+    ///
+    /// Falls back to a cursor at the start of the buffer when `selections` is empty.
+    pub fn select(&mut self, selections: Vec<Selection>) {
+        let newest = selections
+            .last()
+            .copied()
+            .unwrap_or_else(|| Selection::cursor(0));
+        self.selections = selections;
+        self.normalize(newest);
+    }
+
+    /// Adds a selection and makes it the newest, merging it with any it overlaps.
+    pub fn insert(&mut self, selection: Selection) {
+        self.selections.push(selection);
+        self.normalize(selection);
+    }
+
+    /// Replaces the newest selection, merging it with any it now overlaps.
+    pub fn replace_newest(&mut self, selection: Selection) {
+        self.selections.remove(self.newest);
+        self.insert(selection);
+    }
+
+    /// Sorts selections and merges overlapping ones, so each cursor appears once.
+    fn normalize(&mut self, newest: Selection) {
+        if self.selections.is_empty() {
+            self.selections.push(newest);
+        }
+
+        self.selections.sort_by_key(|selection| selection.start);
+
+        let mut merged: Vec<Selection> = Vec::with_capacity(self.selections.len());
+        for selection in self.selections.drain(..) {
+            if let Some(previous) = merged.last_mut()
+                && (selection.start < previous.end
+                    || (selection.start == previous.end
+                        && (selection.is_empty() || previous.is_empty())))
+            {
+                previous.end = previous.end.max(selection.end);
+                continue;
+            }
+            merged.push(selection);
+        }
+
+        self.selections = merged;
+        self.newest = self
+            .selections
+            .iter()
+            .position(|selection| selection.start <= newest.start && newest.end <= selection.end)
+            .unwrap_or(self.selections.len() - 1);
+    }
+}
@@ -0,0 +1,126 @@
+use gpui::Pixels;
+use std::ops::Range;
+
+use buffer::{Buffer, ColumnAlignment, TableCell};
+use text::TextPoint;
+
+/// Space between the text of a table cell and the borders around it.
+pub const CELL_PADDING: Pixels = gpui::px(8.);
+
+/// Width of a column whose cells are all empty.
+const MIN_COLUMN_WIDTH: Pixels = gpui::px(24.);
+
+/// How a table's columns are laid out, each as wide as its widest cell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableColumns {
+    /// The borders between the columns, from the table's left edge to its right edge.
+    pub edges: Vec<Pixels>,
+    pub alignments: Vec<ColumnAlignment>,
+}
+
+impl TableColumns {
+    /// Lays out columns to fit the widest text in each of them.
+    pub fn new(text_widths: &[Pixels], alignments: Vec<ColumnAlignment>) -> Self {
+        let mut edges = Vec::with_capacity(text_widths.len() + 1);
+        let mut x = Pixels::ZERO;
+        edges.push(x);
+        for width in text_widths {
+            x += width.max(MIN_COLUMN_WIDTH) + CELL_PADDING * 2.;
+            edges.push(x);
+        }
+
+        Self { edges, alignments }
+    }
+
+    pub fn count(&self) -> usize {
+        self.edges.len().saturating_sub(1)
+    }
+
+    /// Returns where the text of a cell in a column starts, given the width of the text.
+    pub fn text_x(&self, column: usize, width: Pixels) -> Pixels {
+        let (Some(&left), Some(&right)) = (self.edges.get(column), self.edges.get(column + 1))
+        else {
+            return self.edges.last().copied().unwrap_or_default();
+        };
+
+        match self.alignments.get(column).copied().unwrap_or_default() {
+            ColumnAlignment::None | ColumnAlignment::Left => left + CELL_PADDING,
+            ColumnAlignment::Center => left + (right - left - width) / 2.,
+            ColumnAlignment::Right => right - CELL_PADDING - width,
+        }
+    }
+}
+
+/// A cell of a table in a buffer, by its row and its column in the table.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CellPosition {
+    pub row: usize,
+    pub column: usize,
+}
+
+/// Returns the cells of a table row.
+pub fn row_cells(buffer: &Buffer, row: usize) -> Option<Vec<TableCell>> {
+    buffer::table_cells(&buffer.line(row)?)
+}
+
+/// Returns the table cell at an offset, taking offsets on the pipes and spaces around a cell's
+/// text to be in the cell.
+pub fn cell_at(buffer: &Buffer, offset: usize) -> Option<CellPosition> {
+    let point = buffer.offset_to_point(offset);
+    buffer.table_rows(point.row)?;
+    let cells = row_cells(buffer, point.row)?;
+    let column = cells
+        .iter()
+        .position(|cell| point.column <= cell.range.end)
+        .unwrap_or(cells.len().saturating_sub(1));
+
+    Some(CellPosition {
+        row: point.row,
+        column,
+    })
+}
+
+/// Returns the offsets of the text of a table cell.
+pub fn cell_text_range(buffer: &Buffer, cell: CellPosition) -> Option<Range<usize>> {
+    let text = row_cells(buffer, cell.row)?.get(cell.column)?.text.clone();
+    let line_start = buffer.point_to_offset(TextPoint::new(cell.row, 0));
+    Some(line_start + text.start..line_start + text.end)
+}
+
+/// Returns the cell before or after another one in a table, going through each row from left
+/// to right and skipping the separator row. Returns `None` past the first or last cell.
+pub fn adjacent_cell(
+    buffer: &Buffer,
+    cell: CellPosition,
+    table_rows: Range<usize>,
+    forward: bool,
+) -> Option<CellPosition> {
+    let separator_row = table_rows.start + 1;
+    let mut cell = cell;
+    loop {
+        if forward {
+            let column_count = row_cells(buffer, cell.row)?.len();
+            if cell.column + 1 < column_count {
+                cell.column += 1;
+            } else {
+                cell.row += 1;
+                cell.column = 0;
+                if cell.row >= table_rows.end {
+                    return None;
+                }
+            }
+        } else if cell.column > 0 {
+            cell.column -= 1;
+        } else {
+            cell.row = cell
+                .row
+                .checked_sub(1)
+                .filter(|row| *row >= table_rows.start)?;
+            cell.column = row_cells(buffer, cell.row)?.len().saturating_sub(1);
+        }
+
+        if cell.row != separator_row {
+            return Some(cell);
+        }
+    }
+}
@@ -2,10 +2,24 @@ mod context;
 
 pub use context::EditorTestContext;
 
-use gpui::TestAppContext;
+use gpui::{AppContext, Bounds, EntityInputHandler, Pixels, ShapedLine, TestAppContext};
 use indoc::indoc;
-
-use buffer::{Buffer, FormatSpan, Selection};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::Arc,
+};
+
+use buffer::{
+    AttachmentId, BlockStyle, Buffer, BufferEvent, CodeBlock, FormatSpan, ImageBlock, ImageSize,
+    LineEnding, Selection, Table,
+};
+use languages::{HighlightKind, Language};
+
+use crate::{
+    DisplayPoint, IndentUnit, ListItem, code_blocks::HighlightCache,
+    line_layout_cache::LineLayoutCache, typography,
+};
 
 #[gpui::test]
 fn test_backspace(cx: &mut TestAppContext) {
@@ -197,7 +211,7 @@ fn test_toggle_formatting(cx: &mut TestAppContext) {
     // Apply italic
     cx.update_editor(|editor, window, cx| {
         editor.change_selections(window, cx, |s| {
-            *s = Selection::new(10, 25);
+            s.select(vec![Selection::new(10, 25)]);
         });
     });
     cx.assert_editor_state(indoc! {"
@@ -209,7 +223,7 @@ fn test_toggle_formatting(cx: &mut TestAppContext) {
     // Apply underline
     cx.update_editor(|editor, window, cx| {
         editor.change_selections(window, cx, |s| {
-            *s = Selection::new(16, 30);
+            s.select(vec![Selection::new(16, 30)]);
         });
     });
     cx.assert_editor_state(indoc! {"
@@ -291,7 +305,7 @@ fn test_backspace_with_formatting(cx: &mut TestAppContext) {
     // Apply italic
     cx.update_editor(|editor, window, cx| {
         editor.change_selections(window, cx, |s| {
-            *s = Selection::new(20, 30);
+            s.select(vec![Selection::new(20, 30)]);
         });
     });
     cx.assert_editor_state(indoc! {"
@@ -303,7 +317,7 @@ fn test_backspace_with_formatting(cx: &mut TestAppContext) {
     // Delete formatted text completely ("quick brown ")
     cx.update_editor(|editor, window, cx| {
         editor.change_selections(window, cx, |s| {
-            *s = Selection::new(4, 16);
+            s.select(vec![Selection::new(4, 16)]);
         });
     });
     cx.assert_editor_state(indoc! {"
@@ -325,7 +339,7 @@ fn test_backspace_with_formatting(cx: &mut TestAppContext) {
     // Delete partially formatted text ("fox\njumps over")
     cx.update_editor(|editor, window, cx| {
         editor.change_selections(window, cx, |s| {
-            *s = Selection::new(4, 18);
+            s.select(vec![Selection::new(4, 18)]);
         });
     });
     cx.assert_editor_state(indoc! {"
@@ -343,7 +357,7 @@ fn test_backspace_with_formatting(cx: &mut TestAppContext) {
     // Apply underline
     cx.update_editor(|editor, window, cx| {
         editor.change_selections(window, cx, |s| {
-            *s = Selection::new(4, 13);
+            s.select(vec![Selection::new(4, 13)]);
         });
     });
     cx.assert_editor_state(indoc! {"
@@ -354,7 +368,7 @@ fn test_backspace_with_formatting(cx: &mut TestAppContext) {
     // Delete plain unformatted text
     cx.update_editor(|editor, window, cx| {
         editor.change_selections(window, cx, |s| {
-            *s = Selection::new(0, 4);
+            s.select(vec![Selection::new(0, 4)]);
         });
     });
     cx.assert_editor_state(indoc! {"
@@ -430,6 +444,1514 @@ fn test_selection_movement_collapses(cx: &mut TestAppContext) {
     "});
 }
 
+#[gpui::test]
+fn test_double_click_selects_words(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state(indoc! {"
+        ˇThe quick brown fox
+        jumps over the lazy dog
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.begin_selection(6, 2, false, false, window, cx));
+    cx.assert_editor_state(indoc! {"
+        The «quickˇ» brown fox
+        jumps over the lazy dog
+    "});
+
+    // Dragging extends the selection by whole words
+    cx.update_editor(|editor, window, cx| editor.extend_selection(17, window, cx));
+    cx.assert_editor_state(indoc! {"
+        The «quick brown foxˇ»
+        jumps over the lazy dog
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.extend_selection(1, window, cx));
+    cx.assert_editor_state(indoc! {"
+        «ˇThe quick» brown fox
+        jumps over the lazy dog
+    "});
+
+    // Double-clicking between words selects the whitespace
+    cx.update_editor(|editor, window, cx| editor.begin_selection(9, 2, false, false, window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quick« ˇ»brown fox
+        jumps over the lazy dog
+    "});
+}
+
+#[gpui::test]
+fn test_triple_click_selects_lines(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state(indoc! {"
+        ˇThe quick brown fox
+        jumps over the lazy dog
+        end
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.begin_selection(22, 3, false, false, window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quick brown fox
+        «jumps over the lazy dog
+        ˇ»end
+    "});
+
+    // Dragging extends the selection by whole lines
+    cx.update_editor(|editor, window, cx| editor.extend_selection(2, window, cx));
+    cx.assert_editor_state(indoc! {"
+        «ˇThe quick brown fox
+        jumps over the lazy dog
+        »end
+    "});
+
+    // A single click goes back to placing the cursor
+    cx.update_editor(|editor, window, cx| {
+        editor.begin_selection(2, 1, false, false, window, cx);
+        editor.extend_selection(6, window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        Th«e quˇ»ick brown fox
+        jumps over the lazy dog
+        end
+    "});
+}
+
+#[gpui::test]
+fn test_drag_and_drop_selection(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("one «twoˇ» three");
+    cx.update_editor(|editor, window, cx| editor.toggle_bold(window, cx));
+
+    // Dragging the selection moves it along with its formatting
+    cx.update_editor(|editor, window, cx| {
+        editor.begin_selection(5, 1, false, false, window, cx);
+        editor.extend_selection(11, window, cx);
+        assert_eq!(editor.drop_position(), Some(11));
+        editor.end_selection(Some(11), false, window, cx);
+        assert_eq!(editor.drop_position(), None);
+    });
+    cx.assert_editor_state("one  thr«twoˇ»ee");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(formatting_at(buffer, 9).bold, Some(true));
+        assert_eq!(formatting_at(buffer, 4).bold, None);
+        assert_eq!(formatting_at(buffer, 11).bold, None);
+    });
+
+    // Dropping with the copy modifier leaves the original in place
+    cx.update_editor(|editor, window, cx| {
+        editor.begin_selection(9, 1, false, false, window, cx);
+        editor.extend_selection(0, window, cx);
+        editor.end_selection(Some(0), true, window, cx);
+    });
+    cx.assert_editor_state("«twoˇ»one  thrtwoee");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(formatting_at(buffer, 0).bold, Some(true));
+        assert_eq!(formatting_at(buffer, 12).bold, Some(true));
+    });
+
+    // Dropping inside the selection or outside the editor leaves the text unchanged
+    cx.update_editor(|editor, window, cx| {
+        editor.begin_selection(1, 1, false, false, window, cx);
+        editor.extend_selection(8, window, cx);
+        editor.end_selection(None, false, window, cx);
+    });
+    cx.assert_editor_state("«twoˇ»one  thrtwoee");
+    assert_eq!(
+        cx.update_editor(|editor, _, cx| editor.take_dragged_text(false, cx)),
+        None
+    );
+
+    cx.update_editor(|editor, window, cx| {
+        editor.begin_selection(1, 1, false, false, window, cx);
+        editor.extend_selection(2, window, cx);
+        assert_eq!(editor.drop_position(), None);
+        editor.end_selection(Some(2), false, window, cx);
+    });
+    cx.assert_editor_state("twˇoone  thrtwoee");
+
+    // Clicking inside the selection without dragging places the cursor
+    cx.set_state("one «twoˇ» three");
+    cx.update_editor(|editor, window, cx| {
+        editor.begin_selection(5, 1, false, false, window, cx);
+        editor.end_selection(Some(5), false, window, cx);
+    });
+    cx.assert_editor_state("one tˇwo three");
+
+    // Until then, text dropped outside the editor can be taken into another note, with its
+    // formatting
+    cx.set_state("one «twoˇ» three");
+    cx.update_editor(|editor, window, cx| editor.toggle_bold(window, cx));
+    let (text, format_spans) = cx
+        .update_editor(|editor, window, cx| {
+            editor.begin_selection(5, 1, false, false, window, cx);
+            editor.extend_selection(11, window, cx);
+            editor.end_selection(None, false, window, cx);
+            editor.take_dragged_text(false, cx)
+        })
+        .unwrap();
+    assert_eq!(text, "two");
+    assert_eq!(format_spans.len(), 1);
+    assert_eq!(format_spans[0].range, 0..3);
+    assert_eq!(format_spans[0].bold, Some(true));
+    cx.assert_editor_state("one ˇ three");
+}
+
+#[gpui::test]
+fn test_editing_with_multiple_cursors(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state(indoc! {"
+        oneˇ two
+        three«fourˇ» five
+        sixˇ
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.handle_input("!", window, cx));
+    cx.assert_editor_state(indoc! {"
+        one!ˇ two
+        three!ˇ five
+        six!ˇ
+    "});
+
+    cx.update_editor(|editor, window, cx| {
+        editor.backspace(window, cx);
+        editor.backspace(window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        onˇ two
+        threˇ five
+        siˇ
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.newline(window, cx));
+    cx.assert_editor_state(indoc! {"
+        on
+        ˇ two
+        thre
+        ˇ five
+        si
+        ˇ
+    "});
+
+    // Cursors that end up at the same position are merged
+    cx.set_state("aˇbˇc");
+    cx.update_editor(|editor, window, cx| {
+        editor.move_left(window, cx);
+        editor.move_left(window, cx);
+    });
+    cx.assert_editor_state("ˇabc");
+}
+
+#[gpui::test]
+fn test_add_selection_above_and_below(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state(indoc! {"
+        The quick brown fox
+        jumps
+        over the ˇlazy dog
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.add_selection_above(window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quick brown fox
+        jumpsˇ
+        over the ˇlazy dog
+    "});
+
+    // The goal column is kept across short lines
+    cx.update_editor(|editor, window, cx| editor.add_selection_above(window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quickˇ brown fox
+        jumpsˇ
+        over the ˇlazy dog
+    "});
+
+    cx.update_editor(|editor, window, cx| {
+        editor.add_selection_above(window, cx);
+        editor.add_selection_below(window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        The quickˇ brown fox
+        jumpsˇ
+        over the ˇlazy dog
+        ˇ
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.handle_input("_", window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quick_ˇ brown fox
+        jumps_ˇ
+        over the _ˇlazy dog
+        _ˇ
+    "});
+}
+
+#[gpui::test]
+fn test_select_next(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("a cat, a doˇg, a cat, a dog");
+
+    cx.update_editor(|editor, window, cx| editor.select_next(window, cx));
+    cx.assert_editor_state("a cat, a «dogˇ», a cat, a dog");
+
+    cx.update_editor(|editor, window, cx| editor.select_next(window, cx));
+    cx.assert_editor_state("a cat, a «dogˇ», a cat, a «dogˇ»");
+
+    // Searching wraps around and skips text that is already selected
+    cx.set_state("«aˇ» cat, a dog, a");
+    cx.update_editor(|editor, window, cx| {
+        editor.select_next(window, cx);
+        editor.select_next(window, cx);
+        editor.select_next(window, cx);
+    });
+    cx.assert_editor_state("«aˇ» c«aˇ»t, «aˇ» dog, «aˇ»");
+
+    cx.update_editor(|editor, window, cx| editor.handle_input("X", window, cx));
+    cx.assert_editor_state("Xˇ cXˇt, Xˇ dog, Xˇ");
+}
+
+#[gpui::test]
+fn test_alt_click_adds_cursor(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("ˇone two three");
+
+    cx.update_editor(|editor, window, cx| {
+        editor.begin_selection(7, 1, false, true, window, cx);
+        editor.begin_selection(4, 2, false, true, window, cx);
+    });
+    cx.assert_editor_state("ˇone «twoˇ» three");
+    assert_eq!(
+        cx.editor(|editor, _, _| editor.newest_selection()),
+        Selection::new(4, 7)
+    );
+
+    // A plain click goes back to a single cursor
+    cx.update_editor(|editor, window, cx| editor.begin_selection(9, 1, false, false, window, cx));
+    cx.assert_editor_state("one two tˇhree");
+}
+
+#[gpui::test]
+fn test_move_line_up_and_down(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state(indoc! {"
+        one
+        tw«oˇ»
+        three
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.move_line_up(window, cx));
+    cx.assert_editor_state(indoc! {"
+        tw«oˇ»
+        one
+        three
+    "});
+
+    // Lines already at the top stay where they are
+    cx.update_editor(|editor, window, cx| editor.move_line_up(window, cx));
+    cx.assert_editor_state(indoc! {"
+        tw«oˇ»
+        one
+        three
+    "});
+
+    cx.update_editor(|editor, window, cx| {
+        editor.move_line_down(window, cx);
+        editor.move_line_down(window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        one
+        three
+        tw«oˇ»
+    "});
+
+    // Selections on neighbouring lines move together
+    cx.set_state("ˇa\nbˇ\nc\nd");
+    cx.update_editor(|editor, window, cx| editor.move_line_down(window, cx));
+    cx.assert_editor_state("c\nˇa\nbˇ\nd");
+
+    // Formatting moves along with the line
+    cx.set_state("«oneˇ»\ntwo");
+    cx.update_editor(|editor, window, cx| {
+        editor.toggle_bold(window, cx);
+        editor.move_line_down(window, cx);
+    });
+    cx.assert_editor_state("two\n«oneˇ»");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(formatting_at(buffer, 0).bold, None);
+        assert_eq!(formatting_at(buffer, 4).bold, Some(true));
+    });
+}
+
+#[gpui::test]
+fn test_duplicate_and_delete_line(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("one\ntwˇo\nthree");
+    cx.update_editor(|editor, window, cx| editor.duplicate_line(window, cx));
+    cx.assert_editor_state("one\ntwo\ntwˇo\nthree");
+
+    cx.update_editor(|editor, window, cx| editor.delete_line(window, cx));
+    cx.assert_editor_state("one\ntwo\nˇthree");
+
+    // Deleting the last line removes the line break before it
+    cx.update_editor(|editor, window, cx| editor.delete_line(window, cx));
+    cx.assert_editor_state("one\nˇtwo");
+
+    // Duplicating carries the line's formatting
+    cx.set_state("«oneˇ»");
+    cx.update_editor(|editor, window, cx| {
+        editor.toggle_bold(window, cx);
+        editor.duplicate_line(window, cx);
+    });
+    cx.assert_editor_state("one\n«oneˇ»");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(formatting_at(buffer, 0).bold, Some(true));
+        assert_eq!(formatting_at(buffer, 4).bold, Some(true));
+    });
+}
+
+#[gpui::test]
+fn test_join_lines(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("  oneˇ\n    two\nthree");
+    cx.update_editor(|editor, window, cx| editor.join_lines(window, cx));
+    cx.assert_editor_state("  oneˇ two\nthree");
+
+    cx.set_state("«one\ntwo\nthreeˇ»\nfour");
+    cx.update_editor(|editor, window, cx| editor.join_lines(window, cx));
+    cx.assert_editor_state("«one two threeˇ»\nfour");
+
+    // Empty lines are joined without adding a space
+    cx.set_state("oneˇ\n\ntwo");
+    cx.update_editor(|editor, window, cx| editor.join_lines(window, cx));
+    cx.assert_editor_state("oneˇ\ntwo");
+}
+
+#[gpui::test]
+fn test_tab_and_shift_tab(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("oneˇ");
+    cx.update_editor(|editor, window, cx| editor.tab(window, cx));
+    cx.assert_editor_state("one    ˇ");
+
+    // Selected lines are indented as a whole, skipping blank lines
+    cx.set_state("«one\n\ntwoˇ»");
+    cx.update_editor(|editor, window, cx| editor.tab(window, cx));
+    cx.assert_editor_state("«    one\n\n    twoˇ»");
+
+    cx.update_editor(|editor, window, cx| editor.shift_tab(window, cx));
+    cx.assert_editor_state("«one\n\ntwoˇ»");
+
+    // A cursor in a list item nests the item instead of inserting indentation
+    cx.set_state("- oneˇ\n- two");
+    cx.update_editor(|editor, window, cx| editor.tab(window, cx));
+    cx.assert_editor_state("    - oneˇ\n- two");
+
+    cx.update_editor(|editor, window, cx| {
+        editor.shift_tab(window, cx);
+        editor.shift_tab(window, cx);
+    });
+    cx.assert_editor_state("- oneˇ\n- two");
+
+    cx.set_state("«- one\n- twoˇ»");
+    cx.update_editor(|editor, window, cx| {
+        editor.set_indent_unit(IndentUnit::Tab, cx);
+        editor.tab(window, cx);
+    });
+    cx.assert_editor_state("«\t- one\n\t- twoˇ»");
+}
+
+#[gpui::test]
+fn test_list_items(_cx: &mut TestAppContext) {
+    let indent_unit = IndentUnit::Spaces(2);
+    assert_eq!(
+        ListItem::parse("  - milk", indent_unit),
+        Some(ListItem {
+            marker: 2..4,
+            level: 1,
+            ordered: false,
+        })
+    );
+    assert_eq!(
+        ListItem::parse("12. eggs", indent_unit),
+        Some(ListItem {
+            marker: 0..4,
+            level: 0,
+            ordered: true,
+        })
+    );
+    assert_eq!(
+        ListItem::parse("\t* flour", indent_unit).map(|item| item.level),
+        Some(2)
+    );
+    assert_eq!(ListItem::parse("-not a list", indent_unit), None);
+    assert_eq!(ListItem::parse("1.5 cups", indent_unit), None);
+}
+
+#[gpui::test]
+fn test_autopair(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| editor.handle_input("(", window, cx));
+    cx.assert_editor_state("(ˇ)");
+
+    // Typing the closing character steps over it
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input("a", window, cx);
+        editor.handle_input(")", window, cx);
+    });
+    cx.assert_editor_state("(a)ˇ");
+
+    // Pairs aren't inserted before a word, and quotes aren't inserted after one
+    cx.set_state("ˇword it");
+    cx.update_editor(|editor, window, cx| editor.handle_input("(", window, cx));
+    cx.assert_editor_state("(ˇword it");
+    cx.set_state("word itˇ");
+    cx.update_editor(|editor, window, cx| editor.handle_input("\"", window, cx));
+    cx.assert_editor_state("word it\"ˇ");
+
+    // Selected text is wrapped instead of replaced
+    cx.set_state("one «twoˇ» three");
+    cx.update_editor(|editor, window, cx| editor.handle_input("*", window, cx));
+    cx.assert_editor_state("one *«twoˇ»* three");
+
+    // With smart typography, a quote typed into an empty pair of curly quotes closes it
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.set_smart_typography(true);
+        editor.handle_input("\"", window, cx);
+    });
+    cx.assert_editor_state("“ˇ”");
+    cx.update_editor(|editor, window, cx| editor.handle_input("\"", window, cx));
+    cx.assert_editor_state("“”ˇ");
+    cx.set_state("‘ˇ’");
+    cx.update_editor(|editor, window, cx| editor.handle_input("'", window, cx));
+    cx.assert_editor_state("‘’ˇ");
+    cx.update_editor(|editor, window, cx| editor.set_smart_typography(false));
+
+    // Backspace removes an empty pair as a whole
+    cx.set_state("[aˇ]");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("[ˇ]");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("ˇ");
+}
+
+#[gpui::test]
+fn test_smart_typography(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.set_smart_typography(true);
+        for char in "\"It's...\" -- ok".chars() {
+            editor.handle_input(&char.to_string(), window, cx);
+        }
+    });
+    cx.assert_editor_state("“It’s…” — okˇ");
+
+    // Backspace right after a substitution brings back what was typed
+    cx.set_state("aˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input("-", window, cx);
+        editor.handle_input("-", window, cx);
+    });
+    cx.assert_editor_state("a—ˇ");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("a--ˇ");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("a-ˇ");
+
+    // So does undo, but only right after the substitution
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input(".", window, cx);
+        editor.handle_input(".", window, cx);
+        editor.handle_input(".", window, cx);
+        editor.undo(window, cx);
+    });
+    cx.assert_editor_state("a-...ˇ");
+    cx.update_editor(|editor, window, cx| editor.undo(window, cx));
+    cx.assert_editor_state("a-...ˇ");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+
+    cx.update_editor(|editor, window, cx| {
+        editor.set_smart_typography(false);
+        editor.handle_input("'", window, cx);
+    });
+    cx.assert_editor_state("a-'ˇ");
+}
+
+#[gpui::test]
+fn test_markdown_shortcuts(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        for char in "a **bold** and *it*".chars() {
+            editor.handle_input(&char.to_string(), window, cx);
+        }
+    });
+    cx.assert_editor_state("a bold and itˇ");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(formatting_at(buffer, 2).bold, Some(true));
+        assert_eq!(formatting_at(buffer, 11).italic, Some(true));
+    });
+
+    // Markers inside words are left alone
+    cx.set_state("snake_caseˇ");
+    cx.update_editor(|editor, window, cx| editor.handle_input("_", window, cx));
+    cx.assert_editor_state("snake_case_ˇ");
+
+    // Line-start markers set the line's block style
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input("#", window, cx);
+        editor.handle_input("#", window, cx);
+        editor.handle_input(" ", window, cx);
+        editor.handle_input("Title", window, cx);
+    });
+    cx.assert_editor_state("Titleˇ");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(buffer.block_style(0), Some(BlockStyle::Heading(2)));
+    });
+
+    // The block style moves along with the line
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input("\n", window, cx);
+        editor.move_line_up(window, cx);
+    });
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(buffer.block_style(0), None);
+        assert_eq!(buffer.block_style(1), Some(BlockStyle::Heading(2)));
+    });
+
+    // Backspace right after a shortcut brings back what was typed
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input(">", window, cx);
+        editor.handle_input(" ", window, cx);
+    });
+    cx.assert_editor_state("ˇ");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("> ˇ");
+    cx.editor(|editor, _, cx| assert_eq!(editor.buffer().read(cx).block_style(0), None));
+
+    // Undo restores the markers of an inline shortcut, without its formatting
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        for char in "**bold**".chars() {
+            editor.handle_input(&char.to_string(), window, cx);
+        }
+        editor.undo(window, cx);
+    });
+    cx.assert_editor_state("**bold**ˇ");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(formatting_at(buffer, 3).bold, None);
+    });
+
+    cx.set_state("> ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.set_markdown_shortcuts(false);
+        editor.backspace(window, cx);
+        editor.handle_input(" ", window, cx);
+    });
+    cx.assert_editor_state("> ˇ");
+}
+
+#[gpui::test]
+fn test_markdown(cx: &mut TestAppContext) {
+    let buffer =
+        Buffer::from_markdown("# Title\n> Some **bold** and _italic_ text\nun*believ*able");
+    assert_eq!(
+        buffer.text(),
+        "Title\nSome bold and italic text\nunbelievable"
+    );
+    assert_eq!(buffer.block_style(0), Some(BlockStyle::Heading(1)));
+    assert_eq!(buffer.block_style(1), Some(BlockStyle::Quote));
+    assert_eq!(buffer.block_style(2), None);
+    assert_eq!(formatting_at(&buffer, 11).bold, Some(true));
+    assert_eq!(formatting_at(&buffer, 20).italic, Some(true));
+    assert_eq!(formatting_at(&buffer, 34).italic, Some(true));
+    assert_eq!(formatting_at(&buffer, 40).italic, None);
+
+    let buffer =
+        Buffer::from_markdown("See [the **docs**](https://a.com/x_\\(y\\)) or [this](<b c>)");
+    assert_eq!(buffer.text(), "See the docs or this");
+    assert_eq!(buffer.link_at(5), Some((4..12, "https://a.com/x_(y)")));
+    assert_eq!(buffer.link_at(17), Some((16..20, "b c")));
+    assert_eq!(formatting_at(&buffer, 9).bold, Some(true));
+
+    // Notes are saved as the Markdown they were read from
+    for markdown in [
+        "# Title\n## Section\n### Subsection\n> Quote",
+        "**Bold**, _italic_, **_both_** and **bold _nested_**",
+        "<u>Underlined</u>, <u>**both**</u> and \\<u>escaped\\</u> tags",
+        "A [link](https://a.com), [**bold** link](<a b>) and \\[not a link](url)",
+        "https://a.com/x\\_(y) as [https://a.com/x\\_(y)](https://a.com/x_\\(y\\))",
+        "\\![Not an image](image.png)",
+        "Escaped \\*stars\\*, \\_underscores\\_ and \\\\\\*, but not snake_case or 2 * 3",
+        "\\# Not a heading\n\\> Not a quote\n#tag",
+        "```\n# **Code**\n```\n`**code**` and [[**Wiki** note]]\n---\n![**alt**](image.png)",
+        "Windows\r\n**line** endings\r\n",
+    ] {
+        assert_eq!(Buffer::from_markdown(markdown).text_for_save(), markdown);
+    }
+
+    // Whitespace at the ends of formatted text is left out of the markers
+    let buffer = cx.new(|_| Buffer::from_text("Some bold text"));
+    buffer.update(cx, |buffer, cx| buffer.toggle_bold(4..10, cx));
+    assert_eq!(
+        buffer.read_with(cx, |buffer, _| buffer.text_for_save()),
+        "Some **bold** text"
+    );
+}
+
+#[gpui::test]
+fn test_links(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    let link_at = |cx: &mut EditorTestContext, offset| {
+        cx.editor(|editor, _, cx| {
+            let buffer = editor.buffer().read(cx);
+            buffer
+                .link_at(offset)
+                .map(|(range, url)| (range, url.to_string()))
+        })
+    };
+
+    // Selected text becomes the link's text, and cursors insert the URL itself
+    cx.set_state("see «thisˇ» and ˇ");
+    cx.update_editor(|editor, window, cx| editor.insert_link("https://a.com", window, cx));
+    cx.assert_editor_state("see «thisˇ» and https://a.comˇ");
+    assert_eq!(
+        link_at(&mut cx, 5),
+        Some((4..8, "https://a.com".to_string()))
+    );
+    assert_eq!(
+        link_at(&mut cx, 16),
+        Some((13..26, "https://a.com".to_string()))
+    );
+    assert_eq!(link_at(&mut cx, 10), None);
+
+    cx.set_state("see thˇis");
+    cx.update_editor(|editor, window, cx| editor.insert_link("https://a.com", window, cx));
+    cx.update_editor(|editor, window, cx| editor.edit_link("https://b.com", window, cx));
+    assert_eq!(
+        link_at(&mut cx, 6),
+        Some((6..19, "https://b.com".to_string()))
+    );
+    cx.update_editor(|editor, window, cx| editor.remove_link(window, cx));
+    assert_eq!(link_at(&mut cx, 6), None);
+
+    // Bare URLs are linked once whitespace follows them, leaving out trailing punctuation
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        for char in "visit www.example.org. ok".chars() {
+            editor.handle_input(&char.to_string(), window, cx);
+        }
+    });
+    assert_eq!(
+        link_at(&mut cx, 6),
+        Some((6..21, "https://www.example.org".to_string()))
+    );
+    assert_eq!(link_at(&mut cx, 23), None);
+    cx.editor(|editor, _, cx| {
+        assert_eq!(
+            editor.buffer().read(cx).text_for_save(),
+            "visit [www.example.org](https://www.example.org). ok"
+        );
+    });
+
+    cx.set_state("(https://a.com)ˇ");
+    cx.update_editor(|editor, window, cx| editor.newline(window, cx));
+    assert_eq!(
+        link_at(&mut cx, 1),
+        Some((1..14, "https://a.com".to_string()))
+    );
+}
+
+#[gpui::test]
+fn test_wiki_links(cx: &mut TestAppContext) {
+    let text = "see [[Groceries]], [[ ]] and [[a [[Garden plans]]";
+    let titles = buffer::wiki_links(text)
+        .into_iter()
+        .map(|link| &text[link.title])
+        .collect::<Vec<_>>();
+    assert_eq!(titles, ["Groceries", "Garden plans"]);
+
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.set_note_titles(vec![
+            "Groceries".to_string(),
+            "Reading list".to_string(),
+            "Garden plans".to_string(),
+        ]);
+        for char in "[[g".chars() {
+            editor.handle_input(&char.to_string(), window, cx);
+        }
+    });
+    cx.assert_editor_state("[[gˇ]]");
+    cx.editor(|editor, _, cx| {
+        let completion = editor.wiki_link_completion(cx).unwrap();
+        assert_eq!(completion.matches, ["Garden plans", "Groceries"]);
+    });
+
+    // Accepting a title completes the link, reusing the auto-paired brackets
+    cx.update_editor(|editor, window, cx| {
+        editor.move_down(window, cx);
+        editor.newline(window, cx);
+    });
+    cx.assert_editor_state("[[Groceries]]ˇ");
+    cx.editor(|editor, _, cx| assert!(editor.wiki_link_completion(cx).is_none()));
+
+    // Moving the cursor away dismisses the titles
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input("[[", window, cx);
+        editor.handle_input("read", window, cx);
+        editor.move_left(window, cx);
+    });
+    cx.editor(|editor, _, cx| assert!(editor.wiki_link_completion(cx).is_none()));
+
+    // Switching buffers starts over at the top of the other note
+    cx.update_editor(|editor, _, cx| {
+        let buffer = cx.new(|_| Buffer::from_text("Reading list"));
+        editor.set_buffer(buffer, cx);
+    });
+    cx.assert_editor_state("ˇReading list");
+}
+
+#[gpui::test]
+fn test_tags(_cx: &mut TestAppContext) {
+    let text = "#rust and #project/ideas, not#this, #1 or (#ok-) https://a.com/#top";
+    let names = buffer::tags(text)
+        .into_iter()
+        .map(|tag| &text[tag.name])
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["rust", "project/ideas", "ok"]);
+
+    let tag = &buffer::tags("a #b")[0];
+    assert_eq!((tag.range.clone(), tag.name.clone()), (2..4, 3..4));
+
+    // Tags in code spans are skipped, even when the span holds other backticks
+    let text = "`#code` #a ``#b ` #c`` #d ```#e`` ` #f";
+    let names = buffer::tags(text)
+        .into_iter()
+        .map(|tag| &text[tag.name])
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "d", "f"]);
+}
+
+#[gpui::test]
+fn test_images(cx: &mut TestAppContext) {
+    let image = ImageBlock::parse("![Diagram|640x480](attachments/diagram.png)").unwrap();
+    assert_eq!(
+        image,
+        ImageBlock {
+            alt: "Diagram".into(),
+            source: "attachments/diagram.png".into(),
+            size: Some(ImageSize::new(640, 480)),
+        }
+    );
+    assert_eq!(
+        image.to_markdown(),
+        "![Diagram|640x480](attachments/diagram.png)"
+    );
+    assert_eq!(ImageBlock::parse("See ![](diagram.png)"), None);
+    assert_eq!(ImageBlock::parse("![](diagram.png)").unwrap().size, None);
+
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+    png.extend(320u32.to_be_bytes());
+    png.extend(200u32.to_be_bytes());
+    assert_eq!(ImageSize::from_bytes(&png), Some(ImageSize::new(320, 200)));
+    assert_eq!(ImageSize::from_bytes(b"not an image"), None);
+
+    // Images are inserted on a line of their own, leaving the cursor after them
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("Before ˇafter");
+    cx.update_editor(|editor, _, cx| editor.insert_image(&image, cx));
+    cx.assert_editor_state("Before \n![Diagram|640x480](attachments/diagram.png)ˇ\nafter");
+
+    // Resizing keeps the aspect ratio and the cursor after the image
+    cx.update_editor(|editor, _, cx| editor.resize_image(1, gpui::px(320.), cx));
+    cx.assert_editor_state("Before \n![Diagram|320x240](attachments/diagram.png)ˇ\nafter");
+    cx.update_editor(|editor, _, cx| editor.resize_image(1, gpui::px(1.), cx));
+    cx.assert_editor_state("Before \n![Diagram|48x36](attachments/diagram.png)ˇ\nafter");
+    cx.update_editor(|editor, _, cx| editor.resize_image(1, gpui::px(320.), cx));
+
+    cx.update_editor(|editor, window, cx| {
+        editor.set_line_height(gpui::px(20.));
+        editor.sync_display_map(cx);
+
+        // The image takes as many display rows as its height needs
+        let display_map = editor.display_map();
+        assert_eq!(display_map.image_rows(1), Some(1..13));
+        assert_eq!(display_map.image_rows(12), Some(1..13));
+        assert_eq!(display_map.image_rows(13), None);
+        assert_eq!(display_map.row_count(), 14);
+
+        // Moving up and down crosses the image in one step
+        let image_end = "Before \n![Diagram|320x240](attachments/diagram.png)".len();
+        editor.move_down(window, cx);
+        assert_eq!(
+            editor.newest_selection().head(),
+            editor.buffer().read(cx).len()
+        );
+        editor.move_up(window, cx);
+        assert_eq!(editor.newest_selection().head(), image_end);
+        editor.move_up(window, cx);
+        assert_eq!(editor.newest_selection().head(), 7);
+    });
+}
+
+#[gpui::test]
+fn test_attachment_references(cx: &mut TestAppContext) {
+    // Attachments are referred to from images and links, each counted once
+    let id = AttachmentId::for_contents(b"contents");
+    let source = id.source("png");
+    let other = AttachmentId::for_contents(b"other contents");
+    let text = format!("![]({source})\nSee the file\n![Again]({source})");
+    let buffer = cx.new(|_| Buffer::from_text(&text));
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.attachments().into_iter().collect::<Vec<_>>(), [id]);
+
+        let link = text.find("file").unwrap();
+        buffer.set_link(link..link + 4, Some(other.source("pdf")), cx);
+        assert_eq!(buffer.attachments(), [id, other].into_iter().collect());
+
+        buffer.set_link(link..link + 4, None, cx);
+        assert_eq!(buffer.attachments().into_iter().collect::<Vec<_>>(), [id]);
+    });
+}
+
+#[gpui::test]
+fn test_tables(cx: &mut TestAppContext) {
+    // Pasted tables are imported with their columns lined up
+    let table = Table::parse("Name | Role\n:-- | --:\nAda | Engineer\nGrace").unwrap();
+    assert_eq!(
+        table.to_markdown(),
+        indoc! {"
+            | Name  |     Role |
+            | :---- | -------: |
+            | Ada   | Engineer |
+            | Grace |          |"}
+    );
+    assert_eq!(Table::parse("Name | Role\nAda | Engineer"), None);
+
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state(indoc! {"
+        | Name | Role |
+        | --- | --- |
+        | Ada | ˇEngineer |
+    "});
+
+    // Tab and Shift-Tab move between cells, skipping the separator row
+    cx.update_editor(|editor, window, cx| editor.shift_tab(window, cx));
+    cx.assert_editor_state(indoc! {"
+        | Name | Role |
+        | --- | --- |
+        | «Adaˇ» | Engineer |
+    "});
+    cx.update_editor(|editor, window, cx| editor.shift_tab(window, cx));
+    cx.assert_editor_state(indoc! {"
+        | Name | «Roleˇ» |
+        | --- | --- |
+        | Ada | Engineer |
+    "});
+
+    // Moving past the last cell adds a row
+    cx.update_editor(|editor, window, cx| {
+        editor.tab(window, cx);
+        editor.tab(window, cx);
+        editor.tab(window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        | Name | Role |
+        | --- | --- |
+        | Ada | Engineer |
+        | ˇ    |          |
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.insert_table_column(window, cx));
+    cx.assert_editor_state(indoc! {"
+        | Name |     | Role |
+        | --- | --- | --- |
+        | Ada |     | Engineer |
+        |     | ˇ    |          |
+    "});
+    cx.update_editor(|editor, window, cx| editor.delete_table_column(window, cx));
+    cx.assert_editor_state(indoc! {"
+        | Name | Role |
+        | --- | --- |
+        | Ada | Engineer |
+        |     | ˇ         |
+    "});
+    cx.update_editor(|editor, window, cx| editor.delete_table_row(window, cx));
+    cx.assert_editor_state(indoc! {"
+        | Name | Role |
+        | --- | --- |
+        | Ada | «Engineerˇ» |
+    "});
+
+    // The header and the separator row can't be deleted
+    cx.update_editor(|editor, window, cx| {
+        editor.shift_tab(window, cx);
+        editor.shift_tab(window, cx);
+        editor.delete_table_row(window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        | Name | «Roleˇ» |
+        | --- | --- |
+        | Ada | Engineer |
+    "});
+
+    // A table ends at the first row that isn't written as one
+    cx.update_editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(buffer.table_rows(2), Some(0..3));
+        assert_eq!(buffer.table_rows(3), None);
+    });
+}
+
+#[gpui::test]
+fn test_code_blocks(cx: &mut TestAppContext) {
+    // The opening fence names the language, and a block that isn't closed runs to the end
+    let blocks = buffer::code_blocks("Text\n```rust\nfn main() {}\n```\n```\nopen".split('\n'));
+    assert_eq!(
+        blocks,
+        vec![
+            CodeBlock {
+                rows: 1..4,
+                code_rows: 2..3,
+                language: Some("rust".into()),
+            },
+            CodeBlock {
+                rows: 4..6,
+                code_rows: 5..6,
+                language: None,
+            },
+        ]
+    );
+    assert!(blocks[0].is_fence(3) && !blocks[0].is_fence(2));
+
+    // Code is highlighted in the language the block is tagged with
+    let rust = Language::for_tag("RS").unwrap();
+    assert_eq!(rust.name, "rust");
+    assert!(
+        rust.highlight("fn main() {}")
+            .contains(&(0..2, HighlightKind::Keyword))
+    );
+    assert!(Language::for_tag("cobol").is_none());
+
+    // Highlights are reused until the block's code changes, even across edits elsewhere
+    let mut cache = HighlightCache::default();
+    let highlights = cache.highlight(rust, 1..4, 0, || "fn main() {}".into());
+    let same_version = cache.highlight(rust, 1..4, 0, || unreachable!());
+    assert!(Arc::ptr_eq(&highlights, &same_version));
+    cache.start_frame();
+    let same_code = cache.highlight(rust, 1..4, 1, || "fn main() {}".into());
+    assert!(Arc::ptr_eq(&highlights, &same_code));
+    let edited = cache.highlight(rust, 1..4, 2, || "fn test() {}".into());
+    assert!(!Arc::ptr_eq(&highlights, &edited));
+
+    // Inserting a code block wraps the selection in fences
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state(indoc! {"
+        Run:
+        «cargo testˇ» to check
+    "});
+    cx.update_editor(|editor, _, cx| assert!(editor.buffer().read(cx).code_blocks().is_empty()));
+    cx.update_editor(|editor, window, cx| editor.insert_code_block(window, cx));
+    cx.assert_editor_state(indoc! {"
+        Run:
+        ```
+        «cargo testˇ»
+        ```
+         to check
+    "});
+    cx.update_editor(|editor, _, cx| {
+        assert_eq!(editor.buffer().read(cx).code_blocks()[0].rows, 1..4);
+    });
+
+    // Markdown shortcuts don't apply inside code
+    cx.set_state(indoc! {"
+        ```
+        ˇ
+        ```
+    "});
+    cx.update_editor(|editor, window, cx| {
+        editor.handle_input("#", window, cx);
+        editor.handle_input(" ", window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        ```
+        # ˇ
+        ```
+    "});
+}
+
+#[gpui::test]
+fn test_dividers(cx: &mut TestAppContext) {
+    assert!(buffer::is_divider("---"));
+    assert!(buffer::is_divider("* * *"));
+    assert!(buffer::is_divider("_____"));
+    assert!(!buffer::is_divider("--"));
+    assert!(!buffer::is_divider("-*-"));
+    // Dashes in code are code
+    assert!(!Buffer::from_text("```\n---\n```").is_divider(1));
+    // Dashes right under a line of text underline it as a heading, unlike other dividers
+    assert!(!Buffer::from_text("Title\n---").is_divider(1));
+    assert!(Buffer::from_text("Title\n\n---").is_divider(2));
+    assert!(Buffer::from_text("Title\n***").is_divider(1));
+    assert!(Buffer::from_text("# Title\n---").is_divider(1));
+
+    // Dashes making up the line so far aren't turned into an em dash, so dividers can be typed
+    assert_eq!(typography::substitution("-", "-"), None);
+    assert_eq!(typography::substitution("--", "-"), None);
+    assert!(typography::substitution("a-", "-").is_some());
+
+    // Typing a divider moves on to the line below it, even with smart typography
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("ˇ");
+    cx.update_editor(|editor, window, cx| {
+        editor.set_markdown_shortcuts(true);
+        editor.set_smart_typography(true);
+        for _ in 0..3 {
+            editor.handle_input("-", window, cx);
+        }
+    });
+    cx.assert_editor_state("---\nˇ");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("---ˇ");
+
+    // Backspace after a divider deletes it whole
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("ˇ");
+    cx.set_state("Before\n\nˇ");
+    cx.update_editor(|editor, window, cx| {
+        for _ in 0..3 {
+            editor.handle_input("-", window, cx);
+        }
+    });
+    cx.assert_editor_state("Before\n\n---\nˇ");
+
+    // Inserted dividers are kept apart from the text above them, so it doesn't become a heading
+    cx.set_state("Before\nˇafter");
+    cx.update_editor(|editor, window, cx| editor.insert_divider(window, cx));
+    cx.assert_editor_state("Before\n\n---\nˇafter");
+    cx.set_state("Beforeˇ");
+    cx.update_editor(|editor, window, cx| editor.insert_divider(window, cx));
+    cx.assert_editor_state("Before\n\n---\nˇ");
+    cx.set_state("Before\n\nˇafter");
+    cx.update_editor(|editor, window, cx| editor.insert_divider(window, cx));
+    cx.assert_editor_state("Before\n\n---\nˇafter");
+
+    // The cursor moves across a divider in one step
+    cx.update_editor(|editor, window, cx| editor.move_left(window, cx));
+    cx.assert_editor_state("Before\n\n---ˇ\nafter");
+    cx.update_editor(|editor, window, cx| editor.move_left(window, cx));
+    cx.assert_editor_state("Before\n\nˇ---\nafter");
+    cx.update_editor(|editor, window, cx| editor.move_right(window, cx));
+    cx.assert_editor_state("Before\n\n---ˇ\nafter");
+
+    // Deleting into a divider from the next line takes it along with its line
+    cx.set_state("Before\n\n---\nˇafter");
+    cx.update_editor(|editor, window, cx| editor.backspace(window, cx));
+    cx.assert_editor_state("Before\n\nˇafter");
+    cx.set_state("Before\nˇ***\nafter");
+    cx.update_editor(|editor, window, cx| editor.delete(window, cx));
+    cx.assert_editor_state("Before\nˇafter");
+
+    // Dashes under a heading's text are typed as they are
+    cx.set_state("Title\nˇ");
+    cx.update_editor(|editor, window, cx| {
+        for _ in 0..3 {
+            editor.handle_input("-", window, cx);
+        }
+    });
+    cx.assert_editor_state("Title\n---ˇ");
+}
+
+#[gpui::test]
+fn test_autoscroll(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    let text = (0..50)
+        .map(|row| format!("line {row}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    cx.set_state(&format!("ˇ{text}"));
+
+    cx.update_editor(|editor, _, cx| {
+        editor.set_visible_line_count(10.);
+        editor.autoscroll_vertically(cx);
+    });
+    assert_eq!(cx.editor(|editor, _, _| editor.scroll_position()), 0.);
+
+    // Moving below the visible rows scrolls just enough to keep a margin below the cursor
+    cx.update_editor(|editor, window, cx| {
+        for _ in 0..20 {
+            editor.move_down(window, cx);
+        }
+        editor.autoscroll_vertically(cx);
+    });
+    assert_eq!(cx.editor(|editor, _, _| editor.scroll_position()), 14.);
+
+    // Moving above the visible rows scrolls back up
+    cx.update_editor(|editor, window, cx| {
+        for _ in 0..15 {
+            editor.move_up(window, cx);
+        }
+        editor.autoscroll_vertically(cx);
+    });
+    assert_eq!(cx.editor(|editor, _, _| editor.scroll_position()), 2.);
+
+    // Scrolling is clamped to the buffer
+    cx.update_editor(|editor, _, cx| editor.scroll_by(-10., cx));
+    assert_eq!(cx.editor(|editor, _, _| editor.scroll_position()), 0.);
+    cx.update_editor(|editor, _, cx| editor.scroll_by(100., cx));
+    assert_eq!(cx.editor(|editor, _, _| editor.scroll_position()), 49.);
+}
+
+#[gpui::test]
+fn test_move_up_and_down_with_soft_wrap(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    // Every character is 9.6px wide in the test text system, so rows wrap after 10 characters
+    cx.cx
+        .simulate_resize(gpui::size(gpui::px(96.), gpui::px(200.)));
+    cx.cx.run_until_parked();
+
+    cx.set_state(indoc! {"
+        The quick brown fox jumps ˇover the lazy dog
+        end
+    "});
+    cx.update_editor(|editor, window, cx| editor.move_up(window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quick brown fox ˇjumps over the lazy dog
+        end
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.move_up(window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quick ˇbrown fox jumps over the lazy dog
+        end
+    "});
+
+    cx.update_editor(|editor, window, cx| {
+        editor.move_down(window, cx);
+        editor.move_down(window, cx);
+        editor.move_down(window, cx);
+    });
+    cx.assert_editor_state(indoc! {"
+        The quick brown fox jumps over the ˇlazy dog
+        end
+    "});
+
+    cx.update_editor(|editor, window, cx| editor.move_down(window, cx));
+    cx.assert_editor_state(indoc! {"
+        The quick brown fox jumps over the lazy dog
+        ˇend
+    "});
+}
+
+#[gpui::test]
+fn test_display_map_with_soft_wrap(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.cx
+        .simulate_resize(gpui::size(gpui::px(96.), gpui::px(200.)));
+    cx.cx.run_until_parked();
+    cx.set_state("ˇThe quick brown fox jumps over the lazy dog\nend");
+
+    cx.update_editor(|editor, _, cx| {
+        editor.sync_display_map(cx);
+        let buffer = editor.buffer().read(cx);
+        let display_map = editor.display_map();
+        assert!(display_map.row_count() > buffer.line_count());
+
+        // Display rows cover the buffer rows in order without gaps
+        let lines = display_map
+            .lines(0..display_map.row_count(), buffer)
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), display_map.row_count());
+        assert_eq!(lines.last().unwrap().buffer_row, 1);
+        assert_eq!(lines.last().unwrap().range, buffer.len() - 3..buffer.len());
+        for pair in lines.windows(2) {
+            if pair[0].is_wrapped {
+                assert_eq!(pair[0].buffer_row, pair[1].buffer_row);
+                assert_eq!(pair[0].range.end, pair[1].range.start);
+            }
+        }
+
+        // Every buffer position maps to a display point and back
+        for offset in 0..=buffer.len() {
+            let display_point = display_map.offset_to_display_point(offset, buffer);
+            let point = display_map.display_point_to_point(display_point);
+            assert_eq!(point, buffer.offset_to_point(offset));
+        }
+
+        // Display points past the end of a row are clipped to it
+        let wrapped_row = &lines[0];
+        let clipped = display_map.clip_point(DisplayPoint::new(0, usize::MAX), buffer);
+        assert_eq!(
+            clipped,
+            DisplayPoint::new(0, display_map.line_len(0, buffer))
+        );
+        assert!(display_map.display_point_to_offset(clipped, buffer) < wrapped_row.range.end);
+    });
+}
+
+#[gpui::test]
+fn test_display_map_rewraps_edited_rows(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.cx
+        .simulate_resize(gpui::size(gpui::px(96.), gpui::px(200.)));
+    cx.cx.run_until_parked();
+    cx.set_state(indoc! {"
+        ˇThe quick brown fox jumps over the lazy dog
+        end
+        | a | b |
+        | - | - |
+        short
+    "});
+
+    // After each edit, the rows kept from the last sync match a display map built from scratch
+    let edits = [
+        (0..0, "Lorem ipsum dolor sit amet\n"),
+        (27..31, ""),
+        (71..71, "```\n"),
+        (75..75, "| c | d |\n"),
+        (71..75, ""),
+        (0..40, "A"),
+    ];
+    for (range, text) in edits {
+        cx.update_editor(|editor, _, cx| {
+            editor
+                .buffer()
+                .update(cx, |buffer, cx| buffer.replace(range, text, cx));
+            editor.sync_display_map(cx);
+            let buffer = editor.buffer().read(cx);
+            let synced = editor
+                .display_map
+                .lines(0..editor.display_map.row_count(), buffer)
+                .collect::<Vec<_>>();
+
+            editor.display_map.invalidate();
+            editor.display_map.sync(buffer, cx);
+            let rebuilt = editor
+                .display_map
+                .lines(0..editor.display_map.row_count(), buffer)
+                .collect::<Vec<_>>();
+            assert_eq!(synced, rebuilt);
+        });
+    }
+}
+
+#[gpui::test]
+fn test_bounds_and_character_index_for_ime(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("ˇhello\nworld");
+    cx.cx.run_until_parked();
+
+    cx.update_editor(|editor, window, cx| {
+        let first = editor
+            .bounds_for_range(1..2, Bounds::default(), window, cx)
+            .unwrap();
+        let second = editor
+            .bounds_for_range(7..8, Bounds::default(), window, cx)
+            .unwrap();
+        assert!(first.size.width > gpui::px(0.));
+        assert!(second.top() >= first.bottom());
+
+        // Points just inside the left edge of a character resolve to that character
+        let inside =
+            |bounds: Bounds<Pixels>| gpui::point(bounds.left() + gpui::px(1.), bounds.center().y);
+        assert_eq!(
+            editor.character_index_for_point(inside(first), window, cx),
+            Some(1)
+        );
+        assert_eq!(
+            editor.character_index_for_point(inside(second), window, cx),
+            Some(7)
+        );
+    });
+}
+
+#[gpui::test]
+fn test_anchors(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("one ˇtwo three");
+
+    cx.update_editor(|editor, _, cx| {
+        editor.buffer().update(cx, |buffer, cx| {
+            let before = buffer.anchor_before(4);
+            let after = buffer.anchor_after(4);
+            let end = buffer.anchor_before(13);
+
+            // Insertions at an anchor move it only if it is biased to the right
+            buffer.insert(4, "big ", cx);
+            assert_eq!(buffer.offset_for_anchor(&before), 4);
+            assert_eq!(buffer.offset_for_anchor(&after), 8);
+            assert_eq!(buffer.offset_for_anchor(&end), 17);
+
+            // Anchors inside removed text collapse to the start of the removal
+            buffer.remove(2..10, cx);
+            assert_eq!(buffer.offset_for_anchor(&before), 2);
+            assert_eq!(buffer.offset_for_anchor(&after), 2);
+            assert_eq!(buffer.offset_for_anchor(&end), 9);
+
+            // Clones of an anchor keep moving after the original is dropped
+            let end_clone = end.clone();
+            drop(end);
+            buffer.insert(0, "an", cx);
+            assert_eq!(buffer.offset_for_anchor(&end_clone), 11);
+        });
+    });
+}
+
+#[gpui::test]
+fn test_marked_text_tracks_edits(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("one ˇ three");
+
+    cx.update_editor(|editor, window, cx| {
+        editor.replace_and_mark_text_in_range(None, "two", Some(0..3), window, cx);
+        assert_eq!(editor.marked_text_range(window, cx), Some(4..7));
+
+        // Edits before and right next to the composition shift it without extending it
+        editor.buffer().update(cx, |buffer, cx| {
+            buffer.insert(7, "!", cx);
+            buffer.insert(4, "big ", cx);
+            buffer.insert(0, "> ", cx);
+        });
+        assert_eq!(editor.marked_text_range(window, cx), Some(10..13));
+
+        editor.replace_text_in_range(None, "2", window, cx);
+        assert_eq!(editor.marked_text_range(window, cx), None);
+    });
+    cx.assert_editor_state("> one big 2ˇ! three");
+}
+
+#[gpui::test]
+fn test_buffer_events(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.set_state("oneˇ «twoˇ» three");
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    cx.update_editor(|editor, _, cx| {
+        let events = events.clone();
+        cx.subscribe(editor.buffer(), move |_, _, event: &BufferEvent, _| {
+            events.borrow_mut().push(event.clone());
+        })
+        .detach();
+    });
+
+    // Typing at several cursors is reported as one edit
+    cx.update_editor(|editor, window, cx| editor.handle_input("!", window, cx));
+    cx.update_editor(|editor, window, cx| {
+        editor.change_selections(window, cx, |s| s.select(vec![Selection::new(0, 4)]));
+        editor.toggle_bold(window, cx);
+    });
+    cx.update_editor(|editor, _, cx| {
+        editor.buffer().update(cx, |buffer, cx| {
+            buffer.did_save(cx);
+            buffer.reload("new text", Vec::new(), cx);
+        });
+    });
+
+    assert_eq!(
+        *events.borrow(),
+        vec![
+            BufferEvent::Edited {
+                ranges: vec![3..4, 5..6]
+            },
+            BufferEvent::FormatChanged { range: 0..4 },
+            BufferEvent::Saved,
+            BufferEvent::Reloaded,
+        ]
+    );
+    cx.assert_editor_state("ˇnew text");
+}
+
+#[gpui::test]
+fn test_line_endings(cx: &mut TestAppContext) {
+    let mut cx = EditorTestContext::new(cx);
+    cx.update_editor(|editor, _, cx| {
+        editor.buffer().update(cx, |buffer, cx| {
+            buffer.reload("one\r\ntwo\r\n", Vec::new(), cx);
+            assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        });
+    });
+    cx.assert_editor_state("ˇone\ntwo\n");
+
+    // Pasted line breaks are stored as `\n` too, and saved in the note's line ending
+    cx.update_editor(|editor, window, cx| editor.handle_input("zero\r\n", window, cx));
+    cx.assert_editor_state("zero\nˇone\ntwo\n");
+    cx.editor(|editor, _, cx| {
+        let buffer = editor.buffer().read(cx);
+        assert_eq!(buffer.text_for_save(), "zero\r\none\r\ntwo\r\n");
+    });
+
+    cx.update_editor(|editor, _, cx| {
+        editor.buffer().update(cx, |buffer, cx| {
+            buffer.set_line_ending(LineEnding::Unix, cx);
+            assert_eq!(buffer.text_for_save(), "zero\none\ntwo\n");
+        });
+    });
+
+    assert_eq!(LineEnding::detect("one\ntwo\r\n"), LineEnding::Unix);
+    assert_eq!(LineEnding::detect("no line breaks"), LineEnding::Unix);
+}
+
+#[gpui::test]
+fn test_line_layout_cache(_cx: &mut TestAppContext) {
+    let mut cache = LineLayoutCache::default();
+    let font = gpui::font("Helvetica");
+    let font_size = gpui::px(14.);
+    let color = gpui::white();
+    let shaped_count = Cell::new(0);
+    let layout_line = |cache: &mut LineLayoutCache, text: &str, format_spans: Vec<FormatSpan>| {
+        cache.layout_line(text.to_string(), format_spans, None, None, None, |_, _| {
+            shaped_count.set(shaped_count.get() + 1);
+            ShapedLine::default()
+        });
+    };
+
+    cache.start_frame(&font, font_size, color);
+    layout_line(&mut cache, "first", Vec::new());
+    layout_line(&mut cache, "second", Vec::new());
+    assert_eq!(shaped_count.get(), 2);
+
+    // Unchanged lines are reused across frames, edited lines are reshaped
+    cache.start_frame(&font, font_size, color);
+    layout_line(&mut cache, "first", Vec::new());
+    layout_line(&mut cache, "second!", Vec::new());
+    assert_eq!(shaped_count.get(), 3);
+
+    // Formatting is part of the key
+    cache.start_frame(&font, font_size, color);
+    let mut bold = FormatSpan::new(0..5);
+    bold.bold = Some(true);
+    layout_line(&mut cache, "first", vec![bold]);
+    assert_eq!(shaped_count.get(), 4);
+
+    // Lines skipped for a frame are evicted
+    cache.start_frame(&font, font_size, color);
+    cache.start_frame(&font, font_size, color);
+    layout_line(&mut cache, "second!", Vec::new());
+    assert_eq!(shaped_count.get(), 5);
+
+    // Changing the text style discards every layout
+    cache.start_frame(&font, font_size, color);
+    layout_line(&mut cache, "second!", Vec::new());
+    cache.start_frame(&font, gpui::px(16.), color);
+    layout_line(&mut cache, "second!", Vec::new());
+    assert_eq!(shaped_count.get(), 6);
+}
+
 /// Returns the effective formatting at an offset.
 fn formatting_at(buffer: &Buffer, offset: usize) -> FormatSpan {
     let mut result = FormatSpan {
@@ -437,6 +1959,7 @@ fn formatting_at(buffer: &Buffer, offset: usize) -> FormatSpan {
         bold: None,
         italic: None,
         underline: None,
+        link: None,
     };
 
     for span in buffer.format_spans() {
@@ -450,6 +1973,9 @@ fn formatting_at(buffer: &Buffer, offset: usize) -> FormatSpan {
             if span.underline.is_some() {
                 result.underline = span.underline;
             }
+            if span.link.is_some() {
+                result.link = span.link.clone();
+            }
         }
     }
 
@@ -27,14 +27,25 @@ impl EditorTestContext {
         let (unmarked_text, selection_ranges) = marked_text_ranges(marked_text, true);
 
         self.editor.update_in(&mut self.cx, |editor, window, cx| {
-            editor.buffer().update(cx, |buffer, _| {
-                buffer.replace(0..buffer.len(), &unmarked_text);
+            editor.buffer().update(cx, |buffer, cx| {
+                buffer.replace(0..buffer.len(), &unmarked_text, cx);
             });
 
-            if let Some(range) = selection_ranges.first() {
-                editor.change_selections(window, cx, |selection| {
-                    *selection = Selection::new(range.start, range.end);
-                });
+            if !selection_ranges.is_empty() {
+                let selections = selection_ranges
+                    .iter()
+                    .map(|range| {
+                        if range.start > range.end {
+                            Selection {
+                                reversed: true,
+                                ..Selection::new(range.end, range.start)
+                            }
+                        } else {
+                            Selection::new(range.start, range.end)
+                        }
+                    })
+                    .collect();
+                editor.change_selections(window, cx, |s| s.select(selections));
             }
         });
     }
@@ -46,14 +57,23 @@ impl EditorTestContext {
 
         pretty_assertions::assert_eq!(actual_text, expected_text, "unexpected buffer text");
 
-        if let Some(expected_range) = expected_selection_ranges.first() {
-            let actual_selection = self
-                .editor
-                .update_in(&mut self.cx, |editor, _, _| editor.selection);
-            let actual_range = actual_selection.range();
+        if !expected_selection_ranges.is_empty() {
+            let actual_ranges = self.editor.update_in(&mut self.cx, |editor, _, _| {
+                editor
+                    .selections()
+                    .iter()
+                    .map(|selection| {
+                        if selection.reversed {
+                            selection.end..selection.start
+                        } else {
+                            selection.range()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            });
 
-            if actual_range != *expected_range {
-                let actual_marked_text = generate_marked_text(&actual_text, &[actual_range], true);
+            if actual_ranges != expected_selection_ranges {
+                let actual_marked_text = generate_marked_text(&actual_text, &actual_ranges, true);
                 pretty_assertions::assert_eq!(
                     actual_marked_text,
                     marked_text,
@@ -0,0 +1,41 @@
+/// A typographic replacement for straight punctuation being typed, such as `--` becoming an em
+/// dash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Substitution {
+    /// How many bytes before the cursor the replacement also covers.
+    pub replaced_len: usize,
+    pub text: &'static str,
+}
+
+/// Returns the replacement for typing `input` right after `text_before`, if any.
+///
+/// Quotes curl depending on whether they open or close a quotation, `--` becomes an em dash and
+/// `...` an ellipsis. Dashes starting a line are left alone, as they may be typing a divider.
+pub fn substitution(text_before: &str, input: &str) -> Option<Substitution> {
+    let previous = text_before.chars().next_back();
+    let opens_quote = previous.is_none_or(|char| char.is_whitespace() || "([{<“‘—–".contains(char));
+
+    let (replaced_len, text) = match input {
+        "\"" if opens_quote => (0, "“"),
+        "\"" => (0, "”"),
+        "'" if opens_quote => (0, "‘"),
+        "'" => (0, "’"),
+        "-" if previous == Some('-') && !text_before.chars().all(|char| char == '-') => (1, "—"),
+        "." if text_before.ends_with("..") => (2, "…"),
+        _ => return None,
+    };
+
+    Some(Substitution { replaced_len, text })
+}
+
+/// Returns the closing curly quote a straight quote typed into an empty pair of curly quotes
+/// stands for, such as `"` typed between `“` and `”`, so that it steps over the closing one
+/// instead of opening another pair.
+pub fn closing_quote(text_before: &str, input: &str, text_after: &str) -> Option<&'static str> {
+    let (open, close) = match input {
+        "\"" => ('“', "”"),
+        "'" => ('‘', "’"),
+        _ => return None,
+    };
+    (text_before.ends_with(open) && text_after.starts_with(close)).then_some(close)
+}
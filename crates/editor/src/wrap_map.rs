@@ -0,0 +1,362 @@
+use gpui::{Font, LineFragment, Pixels, TextSystem};
+use std::{iter, ops::Range, sync::Arc};
+
+use buffer::{Buffer, RowEdit};
+use text::TextPoint;
+
+/// A position in soft-wrapped text, where each row is a single line on screen.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct WrapPoint {
+    pub row: usize,
+    pub column: usize,
+}
+
+impl WrapPoint {
+    pub fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct WrapSettings {
+    font: Font,
+    font_size: Pixels,
+    width: Pixels,
+}
+
+/// Soft wraps buffer lines to the editor width.
+///
+/// The wrapping of each buffer row is kept between syncs, and only the rows touched by the
+/// buffer's row edits since the last sync are wrapped again.
+#[derive(Default)]
+pub struct WrapMap {
+    settings: Option<WrapSettings>,
+    buffer_version: Option<usize>,
+    /// Number of row edits the buffer had made at the last sync, or `None` to rebuild every row.
+    row_edit_count: Option<usize>,
+    /// Byte columns at which each buffer row continues onto a new wrap row.
+    wrap_boundaries: Vec<Arc<[usize]>>,
+    /// Wrap row of the first segment of each buffer row.
+    row_starts: Vec<usize>,
+    /// Number of wrap rows taken by each buffer row shown as an image, `None` for text.
+    image_rows: Vec<Option<usize>>,
+    /// Rows of the code blocks at the last sync, which aren't wrapped.
+    code_block_rows: Vec<Range<usize>>,
+    /// Height of a wrap row, which images take a whole number of.
+    line_height: Pixels,
+    row_count: usize,
+}
+
+impl WrapMap {
+    /// Forgets the buffer the wrap map was synced with, such as after switching to another one.
+    pub fn invalidate(&mut self) {
+        self.buffer_version = None;
+        self.row_edit_count = None;
+    }
+
+    /// Sets the width lines are wrapped at, or disables wrapping when `None`.
+    pub fn set_wrap_width(&mut self, width: Option<Pixels>, font: Font, font_size: Pixels) {
+        let settings = width.map(|width| WrapSettings {
+            font,
+            font_size,
+            width,
+        });
+
+        if self.settings != settings {
+            self.settings = settings;
+            self.invalidate();
+        }
+    }
+
+    /// Sets the height of a wrap row, used to fit images into whole rows.
+    pub fn set_line_height(&mut self, line_height: Pixels) {
+        if self.line_height != line_height {
+            self.line_height = line_height;
+            self.invalidate();
+        }
+    }
+
+    /// Wraps the rows edited since the last sync, or every row after the wrap map was
+    /// invalidated or fell too far behind the buffer.
+    pub fn sync(&mut self, buffer: &Buffer, text_system: &Arc<TextSystem>) {
+        if self.buffer_version == Some(buffer.version()) {
+            return;
+        }
+
+        let mut dirty_rows = Vec::new();
+        let row_edits = self
+            .row_edit_count
+            .and_then(|count| buffer.row_edits_since(count));
+        let mut caught_up = false;
+        if let Some(row_edits) = row_edits {
+            caught_up = true;
+            for row_edit in row_edits {
+                if row_edit.old_rows.end > self.wrap_boundaries.len() {
+                    caught_up = false;
+                    break;
+                }
+                self.splice_rows(row_edit, &mut dirty_rows);
+            }
+        }
+        if !caught_up || self.wrap_boundaries.len() != buffer.line_count() {
+            dirty_rows.clear();
+            self.splice_rows(
+                &RowEdit {
+                    old_rows: 0..self.wrap_boundaries.len(),
+                    new_row_count: buffer.line_count(),
+                },
+                &mut dirty_rows,
+            );
+        }
+
+        // Opening or closing a code block changes whether the rows after it are wrapped, and
+        // editing a table row can change which rows around it are part of the table
+        let code_block_rows = buffer
+            .code_blocks()
+            .iter()
+            .map(|block| block.rows.clone())
+            .collect::<Vec<_>>();
+        dirty_rows.extend(changed_rows(&self.code_block_rows, &code_block_rows));
+        self.code_block_rows = code_block_rows;
+        let dirty_rows = coalesce(
+            dirty_rows
+                .into_iter()
+                .map(|rows| table_neighbors(rows, buffer)),
+        );
+
+        let mut wrapper = self.settings.as_ref().map(|settings| {
+            (
+                text_system.line_wrapper(settings.font.clone(), settings.font_size),
+                settings.width,
+            )
+        });
+        for rows in dirty_rows {
+            let mut table_end = rows.start;
+            for row in rows.start..rows.end.min(buffer.line_count()) {
+                if row >= table_end
+                    && let Some(table_rows) = buffer.table_rows(row)
+                {
+                    table_end = table_rows.end;
+                }
+
+                // Images aren't wrapped, taking as many rows as their height needs instead
+                if let Some(image) = buffer.image_block(row) {
+                    let max_width = self.settings.as_ref().map(|settings| settings.width);
+                    let height = crate::images::display_size(&image, max_width).height;
+                    self.image_rows[row] = Some(crate::images::row_count(height, self.line_height));
+                    self.wrap_boundaries[row] = Arc::default();
+                    continue;
+                }
+
+                // Table rows aren't wrapped either, so that their cells stay in line, nor is
+                // code, which is drawn in another font
+                let unwrapped = row < table_end || contains_row(&self.code_block_rows, row);
+                self.image_rows[row] = None;
+                self.wrap_boundaries[row] = match wrapper.as_mut() {
+                    Some((wrapper, width)) if !unwrapped => {
+                        let line = buffer.line(row).unwrap_or_default();
+                        wrapper
+                            .wrap_line(&[LineFragment::text(&line)], *width)
+                            .map(|boundary| boundary.ix)
+                            .collect()
+                    }
+                    _ => Arc::default(),
+                };
+            }
+        }
+
+        self.row_starts.clear();
+        self.row_count = 0;
+        for (boundaries, image_rows) in self.wrap_boundaries.iter().zip(&self.image_rows) {
+            self.row_starts.push(self.row_count);
+            self.row_count += image_rows.unwrap_or(boundaries.len() + 1);
+        }
+
+        self.buffer_version = Some(buffer.version());
+        self.row_edit_count = Some(buffer.row_edit_count());
+    }
+
+    /// Replaces the state of the rows an edit touched with that of unwrapped rows, marking them
+    /// to be wrapped again, and moves the rows after them.
+    fn splice_rows(&mut self, row_edit: &RowEdit, dirty_rows: &mut Vec<Range<usize>>) {
+        let old_rows = &row_edit.old_rows;
+        let new_rows = old_rows.start..old_rows.start + row_edit.new_row_count;
+        self.wrap_boundaries.splice(
+            old_rows.clone(),
+            iter::repeat_n(Arc::default(), new_rows.len()),
+        );
+        self.image_rows
+            .splice(old_rows.clone(), iter::repeat_n(None, new_rows.len()));
+
+        for rows in dirty_rows.iter_mut().chain(&mut self.code_block_rows) {
+            *rows = splice_range(rows, old_rows, &new_rows);
+        }
+        dirty_rows.push(new_rows);
+    }
+
+    /// Returns the number of wrap rows, which is at least the number of buffer rows.
+    pub fn row_count(&self) -> usize {
+        self.row_count.max(1)
+    }
+
+    pub fn max_row(&self) -> usize {
+        self.row_count() - 1
+    }
+
+    pub fn to_wrap_point(&self, point: TextPoint) -> WrapPoint {
+        let Some(boundaries) = self.wrap_boundaries.get(point.row) else {
+            return WrapPoint::new(point.row, point.column);
+        };
+
+        let segment = boundaries.partition_point(|&ix| ix <= point.column);
+        let segment_start = if segment == 0 {
+            0
+        } else {
+            boundaries[segment - 1]
+        };
+
+        WrapPoint::new(
+            self.row_starts[point.row] + segment,
+            point.column - segment_start,
+        )
+    }
+
+    pub fn to_text_point(&self, point: WrapPoint) -> TextPoint {
+        let (row, columns) = self.buffer_row_for_wrap_row(point.row);
+        TextPoint::new(row, columns.start + point.column)
+    }
+
+    /// Returns the buffer row a wrap row belongs to, and the byte columns it spans.
+    ///
+    /// The range is unbounded for the last segment of a buffer row.
+    pub fn buffer_row_for_wrap_row(&self, wrap_row: usize) -> (usize, Range<usize>) {
+        if self.row_starts.is_empty() {
+            return (wrap_row, 0..usize::MAX);
+        }
+
+        let row = self
+            .row_starts
+            .partition_point(|&start| start <= wrap_row)
+            .saturating_sub(1);
+        let boundaries = &self.wrap_boundaries[row];
+        let segment = (wrap_row - self.row_starts[row]).min(boundaries.len());
+
+        let start = if segment == 0 {
+            0
+        } else {
+            boundaries[segment - 1]
+        };
+        let end = boundaries.get(segment).copied().unwrap_or(usize::MAX);
+
+        (row, start..end)
+    }
+
+    /// Returns the wrap rows of the image shown on a wrap row, if it shows one.
+    pub fn image_rows(&self, wrap_row: usize) -> Option<Range<usize>> {
+        let (row, _) = self.buffer_row_for_wrap_row(wrap_row);
+        let rows = self.image_rows.get(row).copied().flatten()?;
+        let start = self.row_starts[row];
+        Some(start..start + rows)
+    }
+
+    /// Returns the byte length of a wrap row, excluding the character it wraps after.
+    pub fn line_len(&self, wrap_row: usize, buffer: &Buffer) -> usize {
+        let (row, columns) = self.buffer_row_for_wrap_row(wrap_row);
+        let line_len = buffer.line_len(row);
+
+        if columns.end < line_len {
+            let segment = buffer.slice(
+                buffer.point_to_offset(TextPoint::new(row, columns.start))
+                    ..buffer.point_to_offset(TextPoint::new(row, columns.end)),
+            );
+            let last_char_len = segment.chars().next_back().map_or(0, char::len_utf8);
+            columns.len() - last_char_len
+        } else {
+            line_len.saturating_sub(columns.start)
+        }
+    }
+}
+
+/// Returns where a range of rows ends up after an edit replaced `old_rows` with `new_rows`,
+/// covering the new rows where it overlapped the old ones.
+fn splice_range(
+    rows: &Range<usize>,
+    old_rows: &Range<usize>,
+    new_rows: &Range<usize>,
+) -> Range<usize> {
+    let shift = |row: usize| row - old_rows.len() + new_rows.len();
+    let start = if rows.start < old_rows.start {
+        rows.start
+    } else if rows.start >= old_rows.end {
+        shift(rows.start)
+    } else {
+        new_rows.start
+    };
+    let end = if rows.end <= old_rows.start {
+        rows.end
+    } else if rows.end >= old_rows.end {
+        shift(rows.end)
+    } else {
+        new_rows.end
+    };
+    start..end.max(start)
+}
+
+/// Returns whether a row is in one of a sorted list of row ranges.
+fn contains_row(ranges: &[Range<usize>], row: usize) -> bool {
+    let ix = ranges.partition_point(|range| range.end <= row);
+    ranges.get(ix).is_some_and(|range| range.contains(&row))
+}
+
+/// Returns the rows in one of two sorted lists of row ranges but not in the other.
+fn changed_rows(old: &[Range<usize>], new: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut edges = old
+        .iter()
+        .chain(new)
+        .flat_map(|range| [range.start, range.end])
+        .collect::<Vec<_>>();
+    edges.sort_unstable();
+    edges.dedup();
+
+    edges
+        .windows(2)
+        .filter(|edges| contains_row(old, edges[0]) != contains_row(new, edges[0]))
+        .map(|edges| edges[0]..edges[1])
+        .collect()
+}
+
+/// Grows a range of rows to take in the table rows directly around it, which may become part of
+/// a table or stop being one when the rows in the range change.
+fn table_neighbors(rows: Range<usize>, buffer: &Buffer) -> Range<usize> {
+    let is_table_row = |row: usize| {
+        buffer
+            .line(row)
+            .and_then(|line| buffer::table_cells(&line))
+            .is_some()
+    };
+
+    let mut start = rows.start;
+    while start > 0 && is_table_row(start - 1) {
+        start -= 1;
+    }
+    let mut end = rows.end;
+    while is_table_row(end) {
+        end += 1;
+    }
+    start..end
+}
+
+/// Sorts ranges of rows and merges those that overlap or touch.
+fn coalesce(ranges: impl IntoIterator<Item = Range<usize>>) -> Vec<Range<usize>> {
+    let mut ranges = ranges.into_iter().collect::<Vec<_>>();
+    ranges.sort_unstable_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
@@ -0,0 +1,140 @@
+use std::{ops::Range, sync::LazyLock};
+use tree_sitter_highlight::{HighlightConfiguration, HighlightEvent, Highlighter};
+
+/// Kinds of syntax that code is highlighted by, each drawn in a color of its own.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    Comment,
+    Constant,
+    Function,
+    Keyword,
+    Number,
+    Operator,
+    Property,
+    Punctuation,
+    String,
+    Type,
+}
+
+/// The highlight names the grammars' queries capture, and the kind each is drawn as. A capture
+/// such as `function.method` takes the kind of the longest name it starts with.
+const HIGHLIGHT_NAMES: [(&str, HighlightKind); 16] = [
+    ("attribute", HighlightKind::Keyword),
+    ("comment", HighlightKind::Comment),
+    ("constant", HighlightKind::Constant),
+    ("constructor", HighlightKind::Type),
+    ("escape", HighlightKind::String),
+    ("function", HighlightKind::Function),
+    ("keyword", HighlightKind::Keyword),
+    ("label", HighlightKind::Keyword),
+    ("number", HighlightKind::Number),
+    ("operator", HighlightKind::Operator),
+    ("property", HighlightKind::Property),
+    ("punctuation", HighlightKind::Punctuation),
+    ("string", HighlightKind::String),
+    ("tag", HighlightKind::Type),
+    ("type", HighlightKind::Type),
+    ("variable.builtin", HighlightKind::Keyword),
+];
+
+/// A language code blocks can be highlighted as, with its grammar compiled in.
+pub struct Language {
+    pub name: &'static str,
+    /// Other tags a code block can be marked with, such as `rs` for Rust.
+    aliases: &'static [&'static str],
+    config: HighlightConfiguration,
+}
+
+static LANGUAGES: LazyLock<Vec<Language>> = LazyLock::new(|| {
+    [
+        Language::new(
+            "rust",
+            &["rs"],
+            tree_sitter_rust::LANGUAGE.into(),
+            tree_sitter_rust::HIGHLIGHTS_QUERY,
+        ),
+        Language::new(
+            "python",
+            &["py"],
+            tree_sitter_python::LANGUAGE.into(),
+            tree_sitter_python::HIGHLIGHTS_QUERY,
+        ),
+        Language::new(
+            "javascript",
+            &["js", "jsx"],
+            tree_sitter_javascript::LANGUAGE.into(),
+            tree_sitter_javascript::HIGHLIGHT_QUERY,
+        ),
+        Language::new(
+            "json",
+            &[],
+            tree_sitter_json::LANGUAGE.into(),
+            tree_sitter_json::HIGHLIGHTS_QUERY,
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+});
+
+impl Language {
+    /// Sets up highlighting with a grammar's highlights query, or returns `None` if the query
+    /// doesn't fit the grammar.
+    fn new(
+        name: &'static str,
+        aliases: &'static [&'static str],
+        grammar: tree_sitter::Language,
+        highlights_query: &str,
+    ) -> Option<Self> {
+        let mut config =
+            HighlightConfiguration::new(grammar, name, highlights_query, "", "").ok()?;
+        config.configure(&HIGHLIGHT_NAMES.map(|(name, _)| name));
+        Some(Self {
+            name,
+            aliases,
+            config,
+        })
+    }
+
+    /// Returns the language a code block is tagged with, ignoring case.
+    pub fn for_tag(tag: &str) -> Option<&'static Self> {
+        LANGUAGES.iter().find(|language| {
+            language.name.eq_ignore_ascii_case(tag)
+                || language
+                    .aliases
+                    .iter()
+                    .any(|alias| alias.eq_ignore_ascii_case(tag))
+        })
+    }
+
+    /// Returns the highlighted ranges of some code, in order. Code that fails to parse is
+    /// highlighted as far as the parser recovers, or not at all.
+    pub fn highlight(&self, code: &str) -> Vec<(Range<usize>, HighlightKind)> {
+        let mut highlighter = Highlighter::new();
+        let Ok(events) = highlighter.highlight(&self.config, code.as_bytes(), None, |_| None)
+        else {
+            return Vec::new();
+        };
+
+        let mut highlights = Vec::new();
+        let mut kinds = Vec::new();
+        for event in events {
+            match event {
+                Ok(HighlightEvent::HighlightStart(highlight)) => {
+                    kinds.push(HIGHLIGHT_NAMES[highlight.0].1);
+                }
+                Ok(HighlightEvent::HighlightEnd) => {
+                    kinds.pop();
+                }
+                Ok(HighlightEvent::Source { start, end }) => {
+                    if let Some(&kind) = kinds.last() {
+                        highlights.push((start..end, kind));
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+
+        highlights
+    }
+}
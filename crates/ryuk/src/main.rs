@@ -4,8 +4,12 @@ use gpui::{
 };
 
 use editor::{
-    Backspace, Delete, DeleteToBeginningOfLine, DeleteToEndOfLine, MoveDown, MoveLeft, MoveRight,
-    MoveUp, Newline, ToggleBold, ToggleItalic, ToggleUnderline,
+    AddSelectionAbove, AddSelectionBelow, Backspace, Copy, Cut, Delete, DeleteLine,
+    DeleteTableColumn, DeleteTableRow, DeleteToBeginningOfLine, DeleteToEndOfLine, DuplicateLine,
+    EditLink, InsertCodeBlock, InsertDivider, InsertLink, InsertTable, InsertTableColumn,
+    InsertTableRow, JoinLines, MoveDown, MoveLeft, MoveLineDown, MoveLineUp, MoveRight, MoveUp,
+    Newline, Paste, RemoveLink, SelectNext, ShiftTab, Tab, ToggleBold, ToggleItalic,
+    ToggleUnderline, Undo,
 };
 use workspace::Workspace;
 
@@ -33,18 +37,55 @@ fn main() {
                 KeyBinding::new("delete", Delete, None),
                 KeyBinding::new("cmd-delete", DeleteToEndOfLine, None),
                 KeyBinding::new("ctrl-k", DeleteToEndOfLine, None),
+                KeyBinding::new("tab", Tab, None),
+                KeyBinding::new("shift-tab", ShiftTab, None),
+                // Clipboard
+                KeyBinding::new("cmd-c", Copy, None),
+                KeyBinding::new("cmd-x", Cut, None),
+                KeyBinding::new("cmd-v", Paste, None),
+                KeyBinding::new("cmd-z", Undo, None),
                 // Format
                 KeyBinding::new("cmd-b", ToggleBold, None),
                 KeyBinding::new("cmd-i", ToggleItalic, None),
                 KeyBinding::new("cmd-u", ToggleUnderline, None),
+                KeyBinding::new("cmd-k", InsertLink, None),
+                KeyBinding::new("cmd-alt-k", EditLink, None),
+                KeyBinding::new("cmd-shift-l", RemoveLink, None),
+                KeyBinding::new("cmd-alt-c", InsertCodeBlock, None),
+                KeyBinding::new("cmd-alt--", InsertDivider, None),
+                // Tables
+                KeyBinding::new("cmd-alt-t", InsertTable, None),
+                KeyBinding::new("cmd-alt-enter", InsertTableRow, None),
+                KeyBinding::new("cmd-alt-backspace", DeleteTableRow, None),
+                KeyBinding::new("cmd-alt-right", InsertTableColumn, None),
+                KeyBinding::new("cmd-alt-left", DeleteTableColumn, None),
                 // Navigation
                 KeyBinding::new("enter", Newline, None),
                 KeyBinding::new("up", MoveUp, None),
                 KeyBinding::new("down", MoveDown, None),
                 KeyBinding::new("left", MoveLeft, None),
                 KeyBinding::new("right", MoveRight, None),
+                // Multiple cursors
+                KeyBinding::new("cmd-alt-up", AddSelectionAbove, None),
+                KeyBinding::new("cmd-alt-down", AddSelectionBelow, None),
+                KeyBinding::new("cmd-d", SelectNext, None),
+                // Lines
+                KeyBinding::new("alt-up", MoveLineUp, None),
+                KeyBinding::new("alt-down", MoveLineDown, None),
+                KeyBinding::new("cmd-shift-d", DuplicateLine, None),
+                KeyBinding::new("cmd-j", JoinLines, None),
+                KeyBinding::new("cmd-shift-k", DeleteLine, None),
             ]);
 
+            let notes_dir = match workspace::notes_dir() {
+                Ok(notes_dir) => notes_dir,
+                Err(error) => {
+                    eprintln!("Couldn't find the notes directory: {error}");
+                    cx.quit();
+                    return;
+                }
+            };
+
             cx.activate(true);
 
             let window_size = size(px(1180.0), px(760.0));
@@ -56,7 +97,7 @@ fn main() {
                     window_bounds: Some(WindowBounds::Windowed(bounds)),
                     ..Default::default()
                 },
-                |window, cx| cx.new(|cx| Workspace::new(window, cx)),
+                |window, cx| cx.new(|cx| Workspace::new(notes_dir, window, cx)),
             )
             .unwrap();
         });
@@ -19,23 +19,44 @@ impl TextBuffer {
     }
 
     pub fn line_len(&self, row: usize) -> usize {
-        self.line(row).map(|s| s.len()).unwrap_or(0)
+        if row >= self.rope.len_lines() {
+            return 0;
+        }
+
+        let line = self.rope.line(row);
+        let mut len = line.len_bytes();
+        let mut chars = line.chars_at(line.len_chars()).reversed();
+
+        if chars.next() == Some('\n') {
+            len -= 1;
+
+            if chars.next() == Some('\r') {
+                len -= 1;
+            }
+        }
+
+        len
     }
 
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
-    pub fn insert(&mut self, char_idx: usize, text: &str) {
+    /// Inserts text at a byte offset, which the rope converts to a char index.
+    pub fn insert(&mut self, offset: usize, text: &str) {
+        let char_idx = self.rope.byte_to_char(offset);
         self.rope.insert(char_idx, text);
     }
 
+    /// Removes a range of bytes, which the rope converts to a range of chars.
     pub fn remove(&mut self, range: Range<usize>) {
-        self.rope.remove(range);
+        let start = self.rope.byte_to_char(range.start);
+        let end = self.rope.byte_to_char(range.end);
+        self.rope.remove(start..end);
     }
 
     pub fn slice(&self, range: Range<usize>) -> String {
-        self.rope.slice(range).to_string()
+        self.rope.byte_slice(range).to_string()
     }
 
     pub fn byte_to_utf16(&self, byte_offset: usize) -> usize {
@@ -137,3 +158,24 @@ impl TextPoint {
         Self { row: 0, column: 0 }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_at_byte_offsets_in_multibyte_text() {
+        let mut text = TextBuffer::from("héllo wörld");
+
+        // `ö` starts at byte 8 but char 7
+        text.insert(8, "→");
+        assert_eq!(text.to_string(), "héllo w→örld");
+        assert_eq!(text.slice(8..11), "→");
+
+        text.remove(1..3);
+        assert_eq!(text.to_string(), "hllo w→örld");
+        text.remove(6..11);
+        assert_eq!(text.to_string(), "hllo wrld");
+        assert_eq!(text.len(), 9);
+    }
+}
@@ -0,0 +1,66 @@
+use gpui::{Entity, Window, div, prelude::*, rgb};
+
+use crate::{library::Library, pane::Pane};
+
+/// Lists the notes that link to the note open in the pane, opening them when clicked.
+pub struct BacklinksPanel {
+    library: Entity<Library>,
+    pane: Entity<Pane>,
+}
+
+impl BacklinksPanel {
+    pub fn new(library: Entity<Library>, pane: Entity<Pane>, cx: &mut Context<Self>) -> Self {
+        cx.observe(&library, |_, _, cx| cx.notify()).detach();
+        cx.observe(&pane, |_, _, cx| cx.notify()).detach();
+
+        Self { library, pane }
+    }
+}
+
+impl Render for BacklinksPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let active_note = self.pane.read(cx).active_note();
+        let backlinks = library
+            .backlinks(active_note)
+            .into_iter()
+            .filter_map(|id| {
+                let title = library.note(id)?.title.clone();
+                Some((id, title))
+            })
+            .collect::<Vec<_>>();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_0p5()
+            .p_2()
+            .child(div().pb_1().text_color(rgb(0x8a8a8a)).child("Backlinks"))
+            .when(backlinks.is_empty(), |this| {
+                this.child(
+                    div()
+                        .px_1()
+                        .text_color(rgb(0x5a5a5a))
+                        .child("No notes link here"),
+                )
+            })
+            .children(backlinks.into_iter().enumerate().map(|(ix, (id, title))| {
+                let title = if title.is_empty() {
+                    "Untitled".to_string()
+                } else {
+                    title
+                };
+
+                div()
+                    .id(("backlink", ix))
+                    .px_1()
+                    .rounded_sm()
+                    .hover(|style| style.bg(rgb(0x2a2a2a)))
+                    .cursor_pointer()
+                    .child(title)
+                    .on_click(cx.listener(move |panel, _, _window, cx| {
+                        panel.pane.update(cx, |pane, cx| pane.open_note(id, cx));
+                    }))
+            }))
+    }
+}
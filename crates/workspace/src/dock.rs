@@ -1,6 +1,6 @@
 use gpui::{
-    App, FocusHandle, Focusable, MouseButton, MouseDownEvent, MouseUpEvent, Pixels, Window,
-    deferred, div, prelude::*, px, rgb,
+    AnyView, App, FocusHandle, Focusable, MouseButton, MouseDownEvent, MouseUpEvent, Pixels,
+    Window, deferred, div, prelude::*, px, rgb,
 };
 
 use crate::{DockPosition, DraggedDock};
@@ -12,15 +12,18 @@ pub struct Dock {
     size: Pixels,
     position: DockPosition,
     visible: bool,
+    /// Panels shown one below the other, such as the backlinks of the open note.
+    panels: Vec<AnyView>,
     focus_handle: FocusHandle,
 }
 
 impl Dock {
-    pub fn new(cx: &mut Context<Self>) -> Self {
+    pub fn new(panels: Vec<AnyView>, cx: &mut Context<Self>) -> Self {
         Self {
             size: DEFAULT_DOCK_SIZE,
             position: DockPosition::Left,
             visible: false,
+            panels,
             focus_handle: cx.focus_handle(),
         }
     }
@@ -105,7 +108,14 @@ impl Render for Dock {
                     .bg(rgb(0x141414))
                     .border_r_1()
                     .border_color(rgb(0x2a2a2a))
-                    .child(div().min_w(self.size).h_full())
+                    .child(
+                        div()
+                            .flex()
+                            .flex_col()
+                            .min_w(self.size)
+                            .h_full()
+                            .children(self.panels.iter().cloned()),
+                    )
                     .child(create_resize_handle())
             })
     }
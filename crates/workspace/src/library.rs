@@ -0,0 +1,876 @@
+use gpui::{App, Entity, Image, ImageFormat, prelude::*};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use attachments::{AttachmentId, AttachmentStore};
+use buffer::{Buffer, BufferEvent};
+
+use crate::{
+    note::{Note, NoteId, NoteMetadata},
+    note_index::NoteIndex,
+};
+
+/// Extension of the files notes are stored in.
+const NOTE_EXTENSION: &str = "md";
+
+/// How long a note has to go without changes before it's saved.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// The notes `[[wiki links]]` can point to.
+pub struct Library {
+    /// Directory the notes and their attachments are stored in.
+    directory: PathBuf,
+    attachments: AttachmentStore,
+    notes: Vec<Note>,
+}
+
+impl Library {
+    pub fn new(directory: PathBuf) -> Self {
+        Self {
+            attachments: AttachmentStore::new(&directory),
+            directory,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    pub fn note(&self, id: NoteId) -> Option<&Note> {
+        self.notes.iter().find(|note| note.id == id)
+    }
+
+    /// Returns the note a wiki link with this title points to, ignoring case.
+    pub fn find_by_title(&self, title: &str) -> Option<NoteId> {
+        let title = title.trim();
+        self.notes
+            .iter()
+            .find(|note| note.title.eq_ignore_ascii_case(title))
+            .map(|note| note.id)
+    }
+
+    /// Returns every tag in the library, with the number of notes that have it.
+    pub fn tags(&self) -> BTreeMap<String, usize> {
+        let mut tags = BTreeMap::new();
+        for tag in self.notes.iter().flat_map(|note| &note.tags) {
+            *tags.entry(tag.clone()).or_default() += 1;
+        }
+        tags
+    }
+
+    /// Adds a new note edited through `buffer`, keeping its title, tags and modification time
+    /// up to date as it's edited.
+    pub fn add_note(&mut self, buffer: Entity<Buffer>, cx: &mut Context<Self>) -> NoteId {
+        self.insert_note(buffer, NoteMetadata::new(SystemTime::now()), cx)
+    }
+
+    /// Adds a note read from the contents of its file, with the metadata in its front matter.
+    pub fn open_file(&mut self, path: PathBuf, contents: &str, cx: &mut Context<Self>) -> NoteId {
+        let (metadata, text) = NoteMetadata::parse(contents, SystemTime::now());
+        let buffer = cx.new(|_| Buffer::from_markdown(text));
+        let id = self.insert_note(buffer, metadata, cx);
+        if let Some(note) = self.notes.iter_mut().find(|note| note.id == id) {
+            note.path = Some(path);
+        }
+        id
+    }
+
+    /// Opens every note file in the directory, in order of their names. Files that can't be read
+    /// are skipped, returning the last error.
+    pub fn load(&mut self, cx: &mut Context<Self>) -> io::Result<()> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            // There's nothing to load until the first note is saved
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+
+        let mut result = Ok(());
+        let mut paths = Vec::new();
+        for entry in entries {
+            match entry {
+                Ok(entry) => paths.push(entry.path()),
+                Err(error) => result = Err(error),
+            }
+        }
+        paths.retain(|path| is_note_file(path));
+        paths.sort();
+
+        for path in paths {
+            match fs::read_to_string(&path) {
+                Ok(contents) => {
+                    self.open_file(path, &contents, cx);
+                }
+                Err(error) => result = Err(error),
+            }
+        }
+        result
+    }
+
+    /// Writes a note with unsaved changes to its file, with its metadata as front matter. A new
+    /// note is given a file named after its title.
+    ///
+    /// Renaming the note is committed first, pointing the wiki links to its old title to the
+    /// new one.
+    pub fn save_note(&mut self, id: NoteId, cx: &mut Context<Self>) -> io::Result<()> {
+        self.commit_rename(id, cx);
+        let Some(note) = self.note(id) else {
+            return Ok(());
+        };
+        if !note.has_unsaved_changes {
+            return Ok(());
+        }
+
+        let path = match &note.path {
+            Some(path) => path.clone(),
+            None => self.new_note_path(&note.title),
+        };
+        let contents = self.file_contents(id, cx).unwrap_or_default();
+
+        // Written under another name first, so that a partly written file never replaces the note
+        fs::create_dir_all(&self.directory)?;
+        let partial_path = path.with_extension(format!("{NOTE_EXTENSION}.partial"));
+        fs::write(&partial_path, contents)?;
+        fs::rename(&partial_path, &path)?;
+
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return Ok(());
+        };
+        note.path = Some(path);
+        note.has_unsaved_changes = false;
+        note.buffer.update(cx, |buffer, cx| buffer.did_save(cx));
+        Ok(())
+    }
+
+    /// Saves a note when nothing waits on the result, such as after it goes without edits,
+    /// reporting an error instead of returning it. A note that couldn't be saved keeps its
+    /// unsaved changes, so it's saved again after its next edit.
+    pub fn autosave_note(&mut self, id: NoteId, cx: &mut Context<Self>) {
+        if let Err(error) = self.save_note(id, cx) {
+            let title = self.note(id).map_or("", |note| note.title.as_str());
+            eprintln!("Couldn't save {title:?}: {error}");
+        }
+    }
+
+    /// Saves every note with unsaved changes, such as before quitting, returning the last error
+    /// if any note couldn't be saved.
+    pub fn save_all(&mut self, cx: &mut Context<Self>) -> io::Result<()> {
+        let ids = self.notes.iter().map(|note| note.id).collect::<Vec<_>>();
+        // Renames are committed first, as they edit the links in other notes
+        for &id in &ids {
+            self.commit_rename(id, cx);
+        }
+
+        let mut result = Ok(());
+        for id in ids {
+            if let Err(error) = self.save_note(id, cx) {
+                result = Err(error);
+            }
+        }
+        result
+    }
+
+    /// Returns a path for a new note's file, named after its title, that no file or other note
+    /// uses yet.
+    fn new_note_path(&self, title: &str) -> PathBuf {
+        let name = title
+            .chars()
+            .filter(|character| !matches!(character, '/' | '\\' | ':' | '\0'))
+            .collect::<String>();
+        let name = name.trim().trim_start_matches('.');
+        let name = if name.is_empty() { "Untitled" } else { name };
+
+        let is_used = |path: &PathBuf| {
+            path.exists()
+                || self
+                    .notes
+                    .iter()
+                    .any(|note| note.path.as_ref() == Some(path))
+        };
+        let mut path = self.directory.join(format!("{name}.{NOTE_EXTENSION}"));
+        let mut number = 1;
+        while is_used(&path) {
+            number += 1;
+            path = self
+                .directory
+                .join(format!("{name} {number}.{NOTE_EXTENSION}"));
+        }
+        path
+    }
+
+    /// Marks a note as changed, saving it once it goes without changes for [`SAVE_DELAY`].
+    fn note_changed(&mut self, id: NoteId, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+
+        note.has_unsaved_changes = true;
+        note._pending_save = Some(cx.spawn(async move |library, cx| {
+            cx.background_executor().timer(SAVE_DELAY).await;
+            library
+                .update(cx, |library, cx| library.autosave_note(id, cx))
+                .ok();
+        }));
+    }
+
+    /// Returns the contents of a note's file, with its metadata as front matter.
+    pub fn file_contents(&self, id: NoteId, cx: &App) -> Option<String> {
+        let note = self.note(id)?;
+        let buffer = note.buffer.read(cx);
+        Some(note.metadata.to_front_matter(buffer.line_ending()) + &buffer.text_for_save())
+    }
+
+    /// Replaces a note's explicit title and other properties with those in `key: value` lines,
+    /// as edited in the properties panel.
+    pub fn set_properties_text(&mut self, id: NoteId, text: &str, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+
+        note.metadata.set_properties_text(text);
+        note.metadata.modified = SystemTime::now();
+        self.note_changed(id, cx);
+        self.update_title(id, cx);
+        cx.notify();
+    }
+
+    /// Stores an image as an attachment next to the notes, returning its path relative to them
+    /// to be used as the image's source.
+    pub fn save_image(&mut self, image: &Image) -> io::Result<String> {
+        self.attachments
+            .insert(&image.bytes, image_extension(image.format))
+    }
+
+    /// Returns the number of notes referring to an attachment.
+    pub fn attachment_reference_count(&self, id: AttachmentId) -> usize {
+        self.attachments.reference_count(id)
+    }
+
+    /// Removes the attachments no note refers to anymore, such as images that were pasted and
+    /// then deleted, returning their IDs. Notes refer to attachments by their images and links.
+    ///
+    /// Every note file in the directory is read first, so that attachments only notes that
+    /// weren't opened refer to are kept.
+    pub fn collect_garbage(&mut self) -> io::Result<Vec<AttachmentId>> {
+        let mut references = BTreeSet::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if is_note_file(&path) {
+                let contents = fs::read_to_string(&path)?;
+                let (_, text) = NoteMetadata::parse(&contents, SystemTime::now());
+                references.extend(Buffer::from_markdown(text).attachments());
+            }
+        }
+
+        self.attachments.collect_garbage(&references)
+    }
+
+    fn insert_note(
+        &mut self,
+        buffer: Entity<Buffer>,
+        mut metadata: NoteMetadata,
+        cx: &mut Context<Self>,
+    ) -> NoteId {
+        // A copied note file has the ID of the note it was copied from, so the copy gets a new
+        // one, which is stored when it's next saved
+        if self.note(metadata.id).is_some() {
+            metadata.id = NoteId::new();
+        }
+        let id = metadata.id;
+
+        let subscription = cx.subscribe(&buffer, move |library, _, event, cx| match event {
+            BufferEvent::Edited { .. } | BufferEvent::Reloaded => library.note_edited(id, cx),
+            BufferEvent::FormatChanged { .. } => library.update_attachments(id, cx),
+            BufferEvent::LineEndingChanged => library.note_changed(id, cx),
+            BufferEvent::Saved => {}
+        });
+        let attachments = buffer.read(cx).attachments();
+        for attachment in &attachments {
+            self.attachments.add_reference(*attachment);
+        }
+        let mut index = NoteIndex::default();
+        let (tags, links) = index.sync(buffer.read(cx));
+        self.notes.push(Note {
+            id,
+            title: note_title(&metadata, buffer.read(cx)),
+            metadata,
+            tags,
+            links,
+            index,
+            attachments,
+            buffer,
+            path: None,
+            has_unsaved_changes: false,
+            renamed_from: None,
+            _pending_save: None,
+            _subscription: subscription,
+        });
+        cx.notify();
+
+        id
+    }
+
+    /// Creates an empty note with a title, such as for a wiki link to a note that doesn't exist
+    /// yet.
+    pub fn create_note(&mut self, title: &str, cx: &mut Context<Self>) -> NoteId {
+        let buffer = cx.new(|_| Buffer::from_text(title.trim()));
+        let id = self.add_note(buffer, cx);
+        self.note_changed(id, cx);
+        id
+    }
+
+    /// Returns the notes with a wiki link to a note, in library order.
+    pub fn backlinks(&self, id: NoteId) -> Vec<NoteId> {
+        let Some(target) = self.note(id) else {
+            return Vec::new();
+        };
+
+        let title = target.title.to_ascii_lowercase();
+        self.notes
+            .iter()
+            .filter(|note| note.id != id && note.links.contains(&title))
+            .map(|note| note.id)
+            .collect()
+    }
+
+    /// Returns the notes with a tag or a tag nested under it, or every note without a tag, in
+    /// library order.
+    pub fn notes_with_tag<'a>(&'a self, tag: Option<&'a str>) -> impl Iterator<Item = &'a Note> {
+        self.notes
+            .iter()
+            .filter(move |note| tag.is_none_or(|tag| note.has_tag(tag)))
+    }
+
+    /// Updates a note's title, tags and modification time after an edit.
+    fn note_edited(&mut self, id: NoteId, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+
+        (note.tags, note.links) = note.index.sync(note.buffer.read(cx));
+        note.metadata.modified = SystemTime::now();
+        self.note_changed(id, cx);
+        self.update_attachments(id, cx);
+        self.update_title(id, cx);
+        cx.notify();
+    }
+
+    /// Counts the attachments a note refers to after its text or links changed.
+    fn update_attachments(&mut self, id: NoteId, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+
+        let attachments = note.buffer.read(cx).attachments();
+        for attachment in attachments.difference(&note.attachments) {
+            self.attachments.add_reference(*attachment);
+        }
+        for attachment in note.attachments.difference(&attachments) {
+            self.attachments.remove_reference(*attachment);
+        }
+        note.attachments = attachments;
+    }
+
+    /// Updates a note's title after its text or metadata changed. The wiki links to its old
+    /// title are left alone until the rename is committed when the note is saved, so that they
+    /// don't follow every keystroke of a title being typed.
+    fn update_title(&mut self, id: NoteId, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+
+        // An emptied title keeps the old one, so links follow once it's retyped
+        let title = note_title(&note.metadata, note.buffer.read(cx));
+        if title.is_empty() || title == note.title {
+            return;
+        }
+        let old_title = std::mem::replace(&mut note.title, title);
+        note.renamed_from.get_or_insert(old_title);
+    }
+
+    /// Points the wiki links to a renamed note's old title to its new one. Links are left alone
+    /// while another note still has the old title, as they may have been meant for that note.
+    fn commit_rename(&mut self, id: NoteId, cx: &mut Context<Self>) {
+        let Some(note) = self.notes.iter_mut().find(|note| note.id == id) else {
+            return;
+        };
+        let Some(old_title) = note.renamed_from.take() else {
+            return;
+        };
+        let title = note.title.clone();
+        if old_title.is_empty()
+            || old_title.eq_ignore_ascii_case(&title)
+            || self.find_by_title(&old_title).is_some()
+        {
+            return;
+        }
+
+        let old_link = old_title.to_ascii_lowercase();
+        let mut edited_notes = Vec::new();
+        for note in self
+            .notes
+            .iter()
+            .filter(|note| note.links.contains(&old_link))
+        {
+            note.buffer.update(cx, |buffer, cx| {
+                let text = buffer.text();
+                let edits = buffer::wiki_links(&text)
+                    .into_iter()
+                    .filter(|link| {
+                        text[link.title.clone()]
+                            .trim()
+                            .eq_ignore_ascii_case(&old_title)
+                    })
+                    .map(|link| (link.title, title.clone()))
+                    .collect::<Vec<_>>();
+                if !edits.is_empty() {
+                    edited_notes.push(note.id);
+                    buffer.edit(edits, cx);
+                }
+            });
+        }
+
+        // Marked right away, as the buffers' edit events only arrive once the edits are flushed
+        for id in edited_notes {
+            self.note_changed(id, cx);
+        }
+    }
+}
+
+/// Returns whether a path in the notes directory is a note's file.
+fn is_note_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == NOTE_EXTENSION)
+}
+
+/// Returns the file extension of an image format, taken from its MIME type such as
+/// `image/svg+xml`.
+fn image_extension(format: ImageFormat) -> &'static str {
+    let subtype = format.mime_type().trim_start_matches("image/");
+    subtype.split('+').next().unwrap_or(subtype)
+}
+
+/// Returns a note's title, which is its first line unless its metadata sets one.
+fn note_title(metadata: &NoteMetadata, buffer: &Buffer) -> String {
+    match &metadata.title {
+        Some(title) => title.trim().to_string(),
+        None => buffer.line(0).unwrap_or_default().trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use buffer::{BlockStyle, LineEnding};
+    use editor::Editor;
+    use gpui::TestAppContext;
+
+    /// Returns an empty directory for a test's notes.
+    fn notes_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("library-{name}-{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        dir
+    }
+
+    /// Returns the text of a note's file without its front matter.
+    fn saved_text(path: &Path) -> String {
+        let contents = fs::read_to_string(path).unwrap();
+        NoteMetadata::parse(&contents, SystemTime::now())
+            .1
+            .to_string()
+    }
+
+    #[gpui::test]
+    fn test_save(cx: &mut TestAppContext) {
+        let dir = notes_dir("save");
+        let library = cx.new(|_| Library::new(dir.clone()));
+        let buffer = cx.new(|_| Buffer::new());
+        let id = library.update(cx, |library, cx| library.add_note(buffer.clone(), cx));
+
+        // Notes are saved once they go without edits for a moment
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(0..0, "Groceries".into())], cx)
+        });
+        cx.run_until_parked();
+        cx.executor().advance_clock(SAVE_DELAY / 2);
+        buffer.update(cx, |buffer, cx| buffer.edit([(9..9, "\nmilk".into())], cx));
+        cx.executor().advance_clock(SAVE_DELAY / 2);
+        cx.run_until_parked();
+        assert!(!dir.join("Groceries.md").exists());
+        cx.executor().advance_clock(SAVE_DELAY);
+        cx.run_until_parked();
+        let path = dir.join("Groceries.md");
+        assert_eq!(saved_text(&path), "Groceries\nmilk");
+        library.read_with(cx, |library, _| {
+            let note = library.note(id).unwrap();
+            assert_eq!(note.path.as_ref(), Some(&path));
+            assert!(!note.has_unsaved_changes);
+        });
+
+        // The file keeps its name when the title changes, and is saved with its line ending
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(0..0, "Weekly ".into())], cx);
+            buffer.set_line_ending(LineEnding::Windows, cx);
+        });
+        library
+            .update(cx, |library, cx| library.save_all(cx))
+            .unwrap();
+        assert_eq!(saved_text(&path), "Weekly Groceries\r\nmilk");
+        assert!(fs::read_to_string(&path).unwrap().starts_with("---\r\n"));
+
+        // A new note with the same title is given another file
+        let other_id = library.update(cx, |library, cx| library.create_note("Groceries", cx));
+        library
+            .update(cx, |library, cx| library.save_note(other_id, cx))
+            .unwrap();
+        library.read_with(cx, |library, _| {
+            let note = library.note(other_id).unwrap();
+            assert_eq!(note.path, Some(dir.join("Groceries 2.md")));
+        });
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        // A note that couldn't be saved keeps its unsaved changes
+        let library = cx.new(|_| Library::new(path.clone()));
+        let buffer = cx.new(|_| Buffer::new());
+        let id = library.update(cx, |library, cx| library.add_note(buffer.clone(), cx));
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..0, "Eggs".into())], cx));
+        cx.run_until_parked();
+        cx.executor().advance_clock(SAVE_DELAY);
+        cx.run_until_parked();
+        library.read_with(cx, |library, _| {
+            let note = library.note(id).unwrap();
+            assert!(note.path.is_none());
+            assert!(note.has_unsaved_changes);
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[gpui::test]
+    fn test_load(cx: &mut TestAppContext) {
+        let dir = notes_dir("load");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Groceries.md");
+        fs::write(
+            &path,
+            "---\ntitle: Shopping\ncreated: 2024-05-01T09:30:00Z\nstatus: draft\n---\nmilk\n",
+        )
+        .unwrap();
+        fs::write(dir.join("Divider.md"), "---\nBetween dividers\n---\n").unwrap();
+        fs::write(dir.join("notes.txt"), "Not a note").unwrap();
+
+        let library = cx.new(|_| Library::new(dir.clone()));
+        library.update(cx, |library, cx| library.load(cx)).unwrap();
+        let id = library.read_with(cx, |library, cx| {
+            let titles = library
+                .notes()
+                .iter()
+                .map(|note| note.title.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(titles, ["---", "Shopping"]);
+
+            let note = library
+                .note(library.find_by_title("shopping").unwrap())
+                .unwrap();
+            assert_eq!(note.path.as_ref(), Some(&path));
+            assert_eq!(note.metadata.property("status"), Some("draft"));
+            assert_eq!(note.buffer.read(cx).text(), "milk\n");
+            assert!(!note.has_unsaved_changes);
+            note.id
+        });
+
+        // Saving an edited note keeps its front matter
+        let buffer = library.read_with(cx, |library, _| library.note(id).unwrap().buffer.clone());
+        buffer.update(cx, |buffer, cx| buffer.edit([(4..4, ", eggs".into())], cx));
+        library
+            .update(cx, |library, cx| library.save_all(cx))
+            .unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let front_matter =
+            format!("---\nid: {id}\ntitle: Shopping\ncreated: 2024-05-01T09:30:00Z\n");
+        assert!(contents.starts_with(&front_matter));
+        assert!(contents.ends_with("status: draft\n---\nmilk, eggs\n"));
+        assert_eq!(
+            fs::read_to_string(dir.join("Divider.md")).unwrap(),
+            "---\nBetween dividers\n---\n"
+        );
+
+        // Notes keep their IDs across sessions, and a copied note gets a new one
+        fs::write(dir.join("Shopping copy.md"), &contents).unwrap();
+        let library = cx.new(|_| Library::new(dir.clone()));
+        library.update(cx, |library, cx| library.load(cx)).unwrap();
+        library.read_with(cx, |library, _| {
+            let note = library.note(id).unwrap();
+            assert_eq!(note.path.as_ref(), Some(&path));
+            assert_eq!(note.metadata.id, id);
+            let copy = library
+                .notes()
+                .iter()
+                .find(|note| note.path == Some(dir.join("Shopping copy.md")))
+                .unwrap();
+            assert_ne!(copy.id, id);
+            assert_eq!(copy.metadata.id, copy.id);
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[gpui::test]
+    fn test_save_formatting(cx: &mut TestAppContext) {
+        let dir = notes_dir("save-formatting");
+        let library = cx.new(|_| Library::new(dir.clone()));
+        let buffer = cx.new(|_| Buffer::new());
+        library.update(cx, |library, cx| library.add_note(buffer.clone(), cx));
+
+        // Markdown shortcuts are saved as the Markdown they were typed as
+        let window = cx.add_window(|_, cx| Editor::new(cx));
+        window
+            .update(cx, |editor, window, cx| {
+                editor.set_buffer(buffer, cx);
+                for char in "# Groceries\n**milk** and _eggs_".chars() {
+                    editor.handle_input(&char.to_string(), window, cx);
+                }
+            })
+            .unwrap();
+        library
+            .update(cx, |library, cx| library.save_all(cx))
+            .unwrap();
+        let path = dir.join("Groceries.md");
+        assert_eq!(saved_text(&path), "# Groceries\n**milk** and _eggs_");
+
+        // And read back into line styles and formatting
+        let library = cx.new(|_| Library::new(dir.clone()));
+        library.update(cx, |library, cx| library.load(cx)).unwrap();
+        library.read_with(cx, |library, cx| {
+            let note = &library.notes()[0];
+            assert_eq!(note.title, "Groceries");
+            let buffer = note.buffer.read(cx);
+            assert_eq!(buffer.text(), "Groceries\nmilk and eggs");
+            assert_eq!(buffer.block_style(0), Some(BlockStyle::Heading(1)));
+            assert_eq!(buffer.block_style(1), None);
+            let formatting = buffer
+                .format_spans()
+                .iter()
+                .map(|span| (span.range.clone(), span.bold, span.italic))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                formatting,
+                [(10..14, Some(true), None), (19..23, None, Some(true))]
+            );
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[gpui::test]
+    fn test_collect_garbage(cx: &mut TestAppContext) {
+        let dir = notes_dir("collect-garbage");
+        let mut store = AttachmentStore::new(&dir);
+        let linked = store.insert(b"report", "pdf").unwrap();
+        let unused = store.insert(b"draft", "pdf").unwrap();
+        fs::write(
+            dir.join("Report.md"),
+            format!("See [the report]({linked})\n"),
+        )
+        .unwrap();
+
+        // Attachments only linked to from notes that weren't opened are kept
+        let library = cx.new(|_| Library::new(dir.clone()));
+        let removed = library
+            .update(cx, |library, _| library.collect_garbage())
+            .unwrap();
+        assert_eq!(removed, [AttachmentId::from_source(&unused).unwrap()]);
+        assert!(dir.join(&linked).exists());
+        assert!(!dir.join(&unused).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Adds a note with some text to the library.
+    fn add_note(library: &Entity<Library>, text: &str, cx: &mut TestAppContext) -> NoteId {
+        let buffer = cx.new(|_| Buffer::from_text(text));
+        library.update(cx, |library, cx| library.add_note(buffer, cx))
+    }
+
+    fn note_text(library: &Entity<Library>, id: NoteId, cx: &mut TestAppContext) -> String {
+        library.read_with(cx, |library, cx| {
+            library.note(id).unwrap().buffer.read(cx).text()
+        })
+    }
+
+    #[gpui::test]
+    fn test_links(cx: &mut TestAppContext) {
+        let library = cx.new(|_| Library::new(notes_dir("links")));
+        let recipes = add_note(
+            &library,
+            "Recipes\nSee [[ groceries ]] and [[Meal plan]]",
+            cx,
+        );
+        let groceries = add_note(&library, "Groceries\nFor [[recipes]]", cx);
+        let plan = add_note(&library, "Meal plan\n[[Groceries]] list", cx);
+
+        library.read_with(cx, |library, _| {
+            // Titles are matched ignoring case and surrounding spaces
+            assert_eq!(library.find_by_title("GROCERIES"), Some(groceries));
+            assert_eq!(library.find_by_title(" meal plan "), Some(plan));
+            assert_eq!(library.find_by_title("Groceries list"), None);
+
+            assert_eq!(library.backlinks(groceries), [recipes, plan]);
+            assert_eq!(library.backlinks(recipes), [groceries]);
+            assert_eq!(library.backlinks(plan), [recipes]);
+        });
+
+        // Links to a note that doesn't exist yet create it
+        let pantry = library.update(cx, |library, cx| library.create_note(" Pantry ", cx));
+        library.read_with(cx, |library, _| {
+            assert_eq!(library.find_by_title("pantry"), Some(pantry));
+            assert!(library.backlinks(pantry).is_empty());
+        });
+    }
+
+    #[gpui::test]
+    fn test_rename(cx: &mut TestAppContext) {
+        let library = cx.new(|_| Library::new(notes_dir("rename")));
+        let groceries = add_note(&library, "Groceries\nmilk", cx);
+        let recipes = add_note(
+            &library,
+            "Recipes\nSee [[groceries]] and [[ Groceries ]]",
+            cx,
+        );
+
+        // Links follow the rename once it's committed, not while the title is being typed
+        let buffer = library.read_with(cx, |library, _| {
+            library.note(groceries).unwrap().buffer.clone()
+        });
+        buffer.update(cx, |buffer, cx| buffer.edit([(9..9, " l".into())], cx));
+        cx.run_until_parked();
+        buffer.update(cx, |buffer, cx| buffer.edit([(11..11, "ist".into())], cx));
+        cx.run_until_parked();
+        library.read_with(cx, |library, _| {
+            assert_eq!(library.find_by_title("groceries list"), Some(groceries));
+            assert_eq!(library.find_by_title("groceries"), None);
+        });
+        assert_eq!(
+            note_text(&library, recipes, cx),
+            "Recipes\nSee [[groceries]] and [[ Groceries ]]"
+        );
+        library.update(cx, |library, cx| library.commit_rename(groceries, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            note_text(&library, recipes, cx),
+            "Recipes\nSee [[Groceries list]] and [[Groceries list]]"
+        );
+        library.read_with(cx, |library, _| {
+            assert_eq!(library.backlinks(groceries), [recipes]);
+        });
+
+        // Links stay with another note that still has the old title
+        let other = add_note(&library, "Groceries list\neggs", cx);
+        buffer.update(cx, |buffer, cx| buffer.edit([(9..14, "".into())], cx));
+        library.update(cx, |library, cx| library.commit_rename(groceries, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            note_text(&library, recipes, cx),
+            "Recipes\nSee [[Groceries list]] and [[Groceries list]]"
+        );
+        library.read_with(cx, |library, _| {
+            assert_eq!(library.find_by_title("groceries list"), Some(other));
+            assert_eq!(library.backlinks(other), [recipes]);
+            assert!(library.backlinks(groceries).is_empty());
+        });
+
+        // Renaming a note back to its old title before committing leaves the links alone
+        let buffer =
+            library.read_with(cx, |library, _| library.note(other).unwrap().buffer.clone());
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(0..9, "Shopping".into())], cx)
+        });
+        cx.run_until_parked();
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(0..8, "Groceries".into())], cx)
+        });
+        cx.run_until_parked();
+        library.update(cx, |library, cx| library.commit_rename(other, cx));
+        cx.run_until_parked();
+        assert_eq!(
+            note_text(&library, recipes, cx),
+            "Recipes\nSee [[Groceries list]] and [[Groceries list]]"
+        );
+    }
+
+    fn note_tags(library: &Entity<Library>, id: NoteId, cx: &mut TestAppContext) -> Vec<String> {
+        library.read_with(cx, |library, _| {
+            library.note(id).unwrap().tags.iter().cloned().collect()
+        })
+    }
+
+    fn notes_with_tag(
+        library: &Entity<Library>,
+        tag: Option<&str>,
+        cx: &mut TestAppContext,
+    ) -> Vec<NoteId> {
+        library.read_with(cx, |library, _| {
+            library.notes_with_tag(tag).map(|note| note.id).collect()
+        })
+    }
+
+    #[gpui::test]
+    fn test_tags(cx: &mut TestAppContext) {
+        let library = cx.new(|_| Library::new(notes_dir("tags")));
+        let rust = add_note(&library, "Rust\n#Project/ideas and #rust", cx);
+        let code = add_note(
+            &library,
+            "Code\n#rust\n```python\n#comment\n```\nSee `#code` and `` #more ``",
+            cx,
+        );
+        let plain = add_note(&library, "Plain\n# Heading", cx);
+
+        // Tags are lowercased, and tags in code blocks and spans are ignored
+        assert_eq!(note_tags(&library, rust, cx), ["project/ideas", "rust"]);
+        assert_eq!(note_tags(&library, code, cx), ["rust"]);
+        assert!(note_tags(&library, plain, cx).is_empty());
+        library.read_with(cx, |library, _| {
+            let tags = library.tags();
+            assert_eq!(
+                tags.into_iter().collect::<Vec<_>>(),
+                [("project/ideas".to_string(), 1), ("rust".to_string(), 2)]
+            );
+        });
+
+        // Notes are filtered by a tag or the tags nested under it
+        assert_eq!(notes_with_tag(&library, None, cx), [rust, code, plain]);
+        assert_eq!(notes_with_tag(&library, Some("rust"), cx), [rust, code]);
+        assert_eq!(notes_with_tag(&library, Some("project"), cx), [rust]);
+        assert_eq!(notes_with_tag(&library, Some("project/ideas"), cx), [rust]);
+        assert!(notes_with_tag(&library, Some("proj"), cx).is_empty());
+
+        // The index follows edits, including ones that open or close code blocks
+        let buffer = library.read_with(cx, |library, _| library.note(code).unwrap().buffer.clone());
+        buffer.update(cx, |buffer, cx| buffer.edit([(11..21, "".into())], cx));
+        cx.run_until_parked();
+        assert_eq!(
+            buffer.read_with(cx, |buffer, _| buffer.text()),
+            "Code\n#rust\n#comment\n```\nSee `#code` and `` #more ``"
+        );
+        assert_eq!(note_tags(&library, code, cx), ["comment", "rust"]);
+        buffer.update(cx, |buffer, cx| {
+            buffer.edit([(0..0, "```\n".into())], cx);
+            buffer.edit([(9..14, "#Rusty #new".into())], cx);
+        });
+        cx.run_until_parked();
+        assert!(note_tags(&library, code, cx).is_empty());
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..4, "".into())], cx));
+        cx.run_until_parked();
+        assert_eq!(note_tags(&library, code, cx), ["comment", "new", "rusty"]);
+        assert_eq!(notes_with_tag(&library, Some("rust"), cx), [rust]);
+    }
+}
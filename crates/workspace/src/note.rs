@@ -0,0 +1,502 @@
+use gpui::{Entity, Subscription, Task};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    hash::{BuildHasher, Hasher, RandomState},
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use buffer::{AttachmentId, Buffer, LineEnding};
+
+use crate::note_index::NoteIndex;
+
+/// Identifies a note, staying the same when the note is renamed and across sessions. It's chosen
+/// at random when the note is created and stored in its front matter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NoteId(u128);
+
+impl NoteId {
+    /// Returns a new random ID.
+    pub fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let random_half = || {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            u128::from(hasher.finish())
+        };
+        Self((random_half() << 64) | random_half())
+    }
+}
+
+impl Default for NoteId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats the ID as the 32 hexadecimal digits it's stored as.
+impl fmt::Display for NoteId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:032x}", self.0)
+    }
+}
+
+impl FromStr for NoteId {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.len() != 32 || !text.chars().all(|char| char.is_ascii_hexdigit()) {
+            return Err(());
+        }
+        u128::from_str_radix(text, 16).map(Self).map_err(|_| ())
+    }
+}
+
+pub struct Note {
+    pub id: NoteId,
+    /// The title `[[wiki links]]` refer to the note by, which is its first line unless the
+    /// metadata sets one.
+    pub title: String,
+    pub metadata: NoteMetadata,
+    pub buffer: Entity<Buffer>,
+    /// Names of the `#tags` in the note's text outside code blocks, lowercased.
+    pub tags: BTreeSet<String>,
+    /// Titles of the notes the note's wiki links point to, trimmed and lowercased.
+    pub links: BTreeSet<String>,
+    pub(crate) index: NoteIndex,
+    /// The attachments the note's images and links refer to.
+    pub attachments: BTreeSet<AttachmentId>,
+    /// The file the note is stored in, chosen when a new note is first saved.
+    pub path: Option<PathBuf>,
+    /// Whether the note changed since it was last saved.
+    pub has_unsaved_changes: bool,
+    /// The title wiki links still refer to the note by, until its rename is committed.
+    pub(crate) renamed_from: Option<String>,
+    /// Saves the note once it hasn't been edited for a moment.
+    pub(crate) _pending_save: Option<Task<()>>,
+    pub(crate) _subscription: Subscription,
+}
+
+impl Note {
+    /// Returns whether the note has a tag, or a tag nested under it such as `#tag/nested`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|note_tag| {
+            note_tag
+                .strip_prefix(tag)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+/// Details about a note besides its text, stored as front matter at the top of its file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoteMetadata {
+    pub id: NoteId,
+    /// A title set explicitly, used instead of the note's first line.
+    pub title: Option<String>,
+    pub created: SystemTime,
+    pub modified: SystemTime,
+    /// Any other properties, in the order they're stored.
+    pub properties: Vec<(String, String)>,
+}
+
+/// The line that opens and closes front matter.
+const FRONT_MATTER_DELIMITER: &str = "---";
+
+impl NoteMetadata {
+    /// Returns the metadata of a note created at `now`, with a new ID.
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            id: NoteId::new(),
+            title: None,
+            created: now,
+            modified: now,
+            properties: Vec::new(),
+        }
+    }
+
+    /// Reads the front matter at the start of a note file, returning the metadata and the
+    /// note's text after it. Files without front matter get the metadata of a note created at
+    /// `now`, and files without a valid ID get a new one.
+    pub fn parse(contents: &str, now: SystemTime) -> (Self, &str) {
+        let mut metadata = Self::new(now);
+        let Some((front_matter, text)) = split_front_matter(contents) else {
+            return (metadata, contents);
+        };
+
+        for (key, value) in front_matter.lines().filter_map(front_matter_property) {
+            match key {
+                "id" => metadata.id = value.parse().unwrap_or(metadata.id),
+                "title" => metadata.title = (!value.is_empty()).then(|| value.to_string()),
+                "created" => metadata.created = parse_timestamp(value).unwrap_or(now),
+                "modified" => metadata.modified = parse_timestamp(value).unwrap_or(now),
+                _ => {
+                    metadata
+                        .properties
+                        .push((key.to_string(), value.to_string()));
+                }
+            }
+        }
+
+        (metadata, text)
+    }
+
+    /// Formats the metadata as front matter, to be written before the note's text.
+    pub fn to_front_matter(&self, line_ending: LineEnding) -> String {
+        let mut lines = vec![FRONT_MATTER_DELIMITER.to_string()];
+        lines.push(format!("id: {}", self.id));
+        lines.extend(self.title.as_ref().map(|title| format!("title: {title}")));
+        lines.push(format!("created: {}", format_timestamp(self.created)));
+        lines.push(format!("modified: {}", format_timestamp(self.modified)));
+        lines.extend(
+            self.properties
+                .iter()
+                .map(|(key, value)| format!("{key}: {value}")),
+        );
+        lines.push(FRONT_MATTER_DELIMITER.to_string());
+
+        let line_ending = line_ending.as_str();
+        lines.join(line_ending) + line_ending
+    }
+
+    /// Returns the value of a property.
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties
+            .iter()
+            .find(|(property, _)| property == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Formats the explicit title and the other properties as `key: value` lines, as edited in
+    /// the properties panel.
+    pub fn properties_text(&self) -> String {
+        let title = self.title.as_ref().map(|title| ("title", title.as_str()));
+        title
+            .into_iter()
+            .chain(
+                self.properties
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str())),
+            )
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Replaces the explicit title and the other properties with those in `key: value` lines.
+    /// Lines without a key, or whose key isn't a single word, are skipped, and the ID and
+    /// timestamps can't be changed this way.
+    pub fn set_properties_text(&mut self, text: &str) {
+        self.title = None;
+        self.properties.clear();
+
+        for line in text.lines() {
+            let (key, value) = line.split_once(':').unwrap_or((line, ""));
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                _ if !is_property_key(key) => {}
+                "id" | "created" | "modified" => {}
+                "title" => self.title = (!value.is_empty()).then(|| value.to_string()),
+                _ => self.properties.push((key.to_string(), value.to_string())),
+            }
+        }
+    }
+}
+
+/// Splits a note file into its front matter, without the delimiters, and the text after it.
+///
+/// Front matter only holds `key: value` lines, so that a note starting with a divider followed
+/// by a paragraph and another divider keeps that text.
+fn split_front_matter(contents: &str) -> Option<(&str, &str)> {
+    let rest = contents.strip_prefix(FRONT_MATTER_DELIMITER)?;
+    let rest = rest
+        .strip_prefix("\r\n")
+        .or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut line_start = 0;
+    for line in rest.split_inclusive('\n') {
+        let line_text = line.trim_end();
+        if line_text == FRONT_MATTER_DELIMITER {
+            let front_matter = &rest[..line_start];
+            if front_matter.trim().is_empty() {
+                return None;
+            }
+            return Some((front_matter, &rest[line_start + line.len()..]));
+        }
+        if !line_text.is_empty() && front_matter_property(line_text).is_none() {
+            return None;
+        }
+        line_start += line.len();
+    }
+
+    None
+}
+
+/// Returns the key and value of a front matter line such as `created: 2024-05-01T09:30:00Z`.
+/// Keys are single words, which may contain `-` and `_`.
+fn front_matter_property(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let has_separator = value.is_empty() || value.starts_with([' ', '\t']);
+    (is_property_key(key) && has_separator).then(|| (key, value.trim()))
+}
+
+/// Returns whether a property can be stored with this key in front matter.
+fn is_property_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|character| character.is_alphanumeric() || character == '-' || character == '_')
+}
+
+/// Formats a time as a UTC timestamp such as `2024-05-01T09:30:00Z`.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, seconds_of_day) = (seconds / 86_400, seconds % 86_400);
+    let (year, month, day) = civil_from_days(days as i64);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day / 60 % 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Parses a UTC timestamp written by [`format_timestamp`].
+pub fn parse_timestamp(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.trim().strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hours, minutes, seconds) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hours > 23
+        || minutes > 59
+        || seconds > 59
+    {
+        return None;
+    }
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    let seconds = days * 86_400 + hours * 3600 + minutes * 60 + seconds;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Converts days since 1970-01-01 to a year, month and day in the proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Converts a year, month and day in the proleptic Gregorian calendar to days since 1970-01-01.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let now = time(0);
+        let contents = concat!(
+            "---\n",
+            "id: 0123456789abcdef0123456789abcdef\n",
+            "title: Groceries\n",
+            "created: 2024-05-01T09:30:00Z\n",
+            "modified: 2024-05-02T18:00:05Z\n",
+            "status: draft\n",
+            "---\n",
+            "milk\n",
+        );
+        let (metadata, text) = NoteMetadata::parse(contents, now);
+        assert_eq!(text, "milk\n");
+        assert_eq!(metadata.id.to_string(), "0123456789abcdef0123456789abcdef");
+        assert_eq!(metadata.title.as_deref(), Some("Groceries"));
+        assert_eq!(format_timestamp(metadata.created), "2024-05-01T09:30:00Z");
+        assert_eq!(format_timestamp(metadata.modified), "2024-05-02T18:00:05Z");
+        assert_eq!(metadata.property("status"), Some("draft"));
+        assert_eq!(
+            metadata.to_front_matter(LineEnding::Unix) + text,
+            contents,
+            "formatting the parsed metadata gives back the file"
+        );
+        assert_eq!(
+            metadata.to_front_matter(LineEnding::Windows),
+            contents
+                .split_inclusive('\n')
+                .take(7)
+                .collect::<String>()
+                .replace('\n', "\r\n")
+        );
+
+        // Windows line endings and blank lines are allowed, and bad IDs and timestamps are
+        // replaced
+        let (metadata, text) = NoteMetadata::parse(
+            "---\r\nid: 12\r\ncreated: yesterday\r\n\r\nurl: https://example.com\r\n---\r\nText",
+            now,
+        );
+        assert_eq!(text, "Text");
+        assert_eq!(metadata.id.to_string().len(), 32);
+        assert_eq!(metadata.created, now);
+        assert_eq!(metadata.title, None);
+        assert_eq!(metadata.property("url"), Some("https://example.com"));
+
+        // Notes that start with a divider keep their text
+        for contents in [
+            "---\nA paragraph between dividers\n---\nText",
+            "---\nShopping list: milk, eggs\n---\nText",
+            "---\n---\nText",
+            "---\nstatus: draft",
+            "--- \nstatus: draft\n---\n",
+            "----\nstatus: draft\n---\n",
+        ] {
+            let (metadata, text) = NoteMetadata::parse(contents, now);
+            assert_eq!(text, contents);
+            assert_eq!(
+                metadata,
+                NoteMetadata {
+                    id: metadata.id,
+                    ..NoteMetadata::new(now)
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_note_ids() {
+        let id = NoteId::new();
+        assert_ne!(id, NoteId::new());
+        assert_eq!(id.to_string().parse(), Ok(id));
+        for text in [
+            "",
+            "12",
+            "0123456789abcdef0123456789abcdeg",
+            "+123456789abcdef0123456789abcdef",
+        ] {
+            assert_eq!(text.parse::<NoteId>(), Err(()), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_properties_text() {
+        let mut metadata = NoteMetadata::new(time(0));
+        let id = metadata.id;
+        metadata.set_properties_text(
+            "title: Groceries \nstatus: draft\n\ncreated: 2024-05-01T09:30:00Z\n\
+             due date: today\nid: 0123456789abcdef0123456789abcdef\narchived",
+        );
+        assert_eq!(metadata.title.as_deref(), Some("Groceries"));
+        assert_eq!(metadata.id, id);
+        assert_eq!(metadata.created, time(0));
+        assert_eq!(
+            metadata.properties,
+            [
+                ("status".to_string(), "draft".to_string()),
+                ("archived".to_string(), String::new())
+            ]
+        );
+        assert_eq!(
+            metadata.properties_text(),
+            "title: Groceries\nstatus: draft\narchived: "
+        );
+
+        // Every property that can be set survives being saved
+        let contents = metadata.to_front_matter(LineEnding::Unix);
+        assert_eq!(NoteMetadata::parse(&contents, time(1)), (metadata, ""));
+    }
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(format_timestamp(time(0)), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(time(951_782_400)), "2000-02-29T00:00:00Z");
+        assert_eq!(
+            format_timestamp(time(1_709_251_199)),
+            "2024-02-29T23:59:59Z"
+        );
+        assert_eq!(
+            format_timestamp(time(4_107_542_400)),
+            "2100-03-01T00:00:00Z"
+        );
+        assert_eq!(
+            format_timestamp(SystemTime::UNIX_EPOCH - Duration::from_secs(1)),
+            "1970-01-01T00:00:00Z"
+        );
+
+        for seconds in [0, 59, 86_399, 951_782_400, 1_709_251_199, 4_107_542_400] {
+            assert_eq!(
+                parse_timestamp(&format_timestamp(time(seconds))),
+                Some(time(seconds))
+            );
+        }
+        assert_eq!(
+            parse_timestamp(" 2024-05-01T09:30:00Z "),
+            Some(time(1_714_555_800))
+        );
+        for text in [
+            "2024-05-01T09:30:00",
+            "2024-05-01 09:30:00Z",
+            "2024-13-01T09:30:00Z",
+            "2024-05-00T09:30:00Z",
+            "2024-05-01T24:00:00Z",
+            "2024-05-01T09:60:00Z",
+            "2024-05-01T09:30:60Z",
+            "1969-12-31T23:59:59Z",
+            "2024-05-01",
+        ] {
+            assert_eq!(parse_timestamp(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn test_civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(47_540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+
+        // Every day round-trips, across leap years and century years
+        for days in -800_000..800_000 {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(days_from_civil(1600, 1, 1), -135_140);
+    }
+}
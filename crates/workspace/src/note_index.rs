@@ -0,0 +1,84 @@
+use std::{collections::BTreeSet, iter};
+
+use buffer::Buffer;
+
+/// The `#tags` and `[[wiki links]]` in each row of a note, kept up to date by only reading the
+/// rows edits touched again.
+#[derive(Default)]
+pub(crate) struct NoteIndex {
+    /// What each buffer row refers to, or `None` for rows that need to be read again.
+    rows: Vec<Option<RowReferences>>,
+    /// Number of the buffer's row edits the rows are up to date with.
+    row_edit_count: Option<usize>,
+}
+
+#[derive(Default)]
+struct RowReferences {
+    /// Lowercased names of the row's tags.
+    tags: Vec<String>,
+    /// Titles of the notes the row links to, trimmed and lowercased.
+    links: Vec<String>,
+}
+
+impl NoteIndex {
+    /// Catches up with the buffer's edits, returning the tags outside code blocks and the
+    /// titles of the notes linked to.
+    pub fn sync(&mut self, buffer: &Buffer) -> (BTreeSet<String>, BTreeSet<String>) {
+        let row_edits = self
+            .row_edit_count
+            .and_then(|count| buffer.row_edits_since(count));
+        let mut caught_up = false;
+        if let Some(row_edits) = row_edits {
+            caught_up = true;
+            for row_edit in row_edits {
+                if row_edit.old_rows.end > self.rows.len() {
+                    caught_up = false;
+                    break;
+                }
+                self.rows.splice(
+                    row_edit.old_rows.clone(),
+                    iter::repeat_with(|| None).take(row_edit.new_row_count),
+                );
+            }
+        }
+        if !caught_up || self.rows.len() != buffer.line_count() {
+            self.rows.clear();
+            self.rows.resize_with(buffer.line_count(), || None);
+        }
+        self.row_edit_count = Some(buffer.row_edit_count());
+
+        for (row, references) in self.rows.iter_mut().enumerate() {
+            if references.is_none() {
+                *references = Some(row_references(&buffer.line(row).unwrap_or_default()));
+            }
+        }
+
+        let code_blocks = buffer.code_blocks();
+        let mut tags = BTreeSet::new();
+        let mut links = BTreeSet::new();
+        for (row, references) in self.rows.iter().enumerate() {
+            let Some(references) = references else {
+                continue;
+            };
+            if !code_blocks.iter().any(|block| block.contains_row(row)) {
+                tags.extend(references.tags.iter().cloned());
+            }
+            links.extend(references.links.iter().cloned());
+        }
+
+        (tags, links)
+    }
+}
+
+fn row_references(line: &str) -> RowReferences {
+    RowReferences {
+        tags: buffer::tags(line)
+            .into_iter()
+            .map(|tag| line[tag.name].to_lowercase())
+            .collect(),
+        links: buffer::wiki_links(line)
+            .into_iter()
+            .map(|link| line[link.title].trim().to_ascii_lowercase())
+            .collect(),
+    }
+}
@@ -0,0 +1,138 @@
+use gpui::{Entity, MouseButton, MouseUpEvent, Window, div, prelude::*, rgb};
+
+use crate::{library::Library, pane::Pane};
+
+/// Browses the library's tags and lists its notes, only those with the picked tag when one is
+/// picked. Clicking a note opens it in the pane, and dropping text dragged out of the editor on
+/// a note adds the text to it.
+pub struct NotesPanel {
+    library: Entity<Library>,
+    pane: Entity<Pane>,
+    /// The tag the note list is filtered by.
+    selected_tag: Option<String>,
+}
+
+impl NotesPanel {
+    pub fn new(library: Entity<Library>, pane: Entity<Pane>, cx: &mut Context<Self>) -> Self {
+        cx.observe(&library, |panel, library, cx| {
+            // Stop filtering by a tag no note has anymore
+            if let Some(tag) = &panel.selected_tag
+                && !library.read(cx).tags().contains_key(tag)
+            {
+                panel.selected_tag = None;
+            }
+            cx.notify();
+        })
+        .detach();
+        cx.observe(&pane, |_, _, cx| cx.notify()).detach();
+
+        Self {
+            library,
+            pane,
+            selected_tag: None,
+        }
+    }
+
+    /// Filters the note list by a tag, or stops filtering if it already was.
+    pub fn toggle_tag(&mut self, tag: String, cx: &mut Context<Self>) {
+        if self.selected_tag.as_ref() == Some(&tag) {
+            self.selected_tag = None;
+        } else {
+            self.selected_tag = Some(tag);
+        }
+        cx.notify();
+    }
+
+    /// Creates an empty note and opens it.
+    fn new_note(&mut self, cx: &mut Context<Self>) {
+        let id = self
+            .library
+            .update(cx, |library, cx| library.create_note("", cx));
+        self.pane.update(cx, |pane, cx| pane.open_note(id, cx));
+    }
+}
+
+impl Render for NotesPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let library = self.library.read(cx);
+        let active_note = self.pane.read(cx).active_note();
+        let tags = library.tags();
+        let notes = library
+            .notes_with_tag(self.selected_tag.as_deref())
+            .map(|note| (note.id, note.title.clone()))
+            .collect::<Vec<_>>();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_0p5()
+            .p_2()
+            .when(!tags.is_empty(), |this| {
+                this.child(div().pb_1().text_color(rgb(0x8a8a8a)).child("Tags"))
+                    .child(div().flex().flex_row().flex_wrap().gap_1().pb_2().children(
+                        tags.into_iter().enumerate().map(|(ix, (tag, count))| {
+                            let selected = self.selected_tag.as_ref() == Some(&tag);
+                            div()
+                                .id(("tag", ix))
+                                .px_1()
+                                .rounded_sm()
+                                .text_color(rgb(0xd7a6ff))
+                                .bg(rgb(if selected { 0x3d2f4a } else { 0x222222 }))
+                                .hover(|style| style.bg(rgb(0x2f2f2f)))
+                                .cursor_pointer()
+                                .child(format!("#{tag} {count}"))
+                                .on_click(cx.listener(move |panel, _, _window, cx| {
+                                    panel.toggle_tag(tag.clone(), cx);
+                                }))
+                        }),
+                    ))
+            })
+            .child(
+                div()
+                    .flex()
+                    .flex_row()
+                    .pb_1()
+                    .text_color(rgb(0x8a8a8a))
+                    .child(div().flex_1().child("Notes"))
+                    .child(
+                        div()
+                            .id("new-note")
+                            .px_1()
+                            .rounded_sm()
+                            .hover(|style| style.bg(rgb(0x2a2a2a)))
+                            .cursor_pointer()
+                            .child("New")
+                            .on_click(cx.listener(|panel, _, _window, cx| panel.new_note(cx))),
+                    ),
+            )
+            .children(notes.into_iter().enumerate().map(|(ix, (id, title))| {
+                let title = if title.is_empty() {
+                    "Untitled".to_string()
+                } else {
+                    title
+                };
+
+                div()
+                    .id(("note", ix))
+                    .px_1()
+                    .rounded_sm()
+                    .when(id == active_note, |this| this.bg(rgb(0x2a2a2a)))
+                    .hover(|style| style.bg(rgb(0x2a2a2a)))
+                    .cursor_pointer()
+                    .child(title)
+                    .on_click(cx.listener(move |panel, _, _window, cx| {
+                        panel.pane.update(cx, |pane, cx| pane.open_note(id, cx));
+                    }))
+                    // Text dragged out of the editor is added to the note it's dropped on
+                    .on_mouse_up(
+                        MouseButton::Left,
+                        cx.listener(move |panel, event: &MouseUpEvent, _window, cx| {
+                            let copy = event.modifiers.alt;
+                            panel
+                                .pane
+                                .update(cx, |pane, cx| pane.drop_dragged_text(id, copy, cx));
+                        }),
+                    )
+            }))
+    }
+}
@@ -1,24 +1,174 @@
-use gpui::{App, Entity, FocusHandle, Focusable, Window, div, prelude::*, rgb};
+use gpui::{App, Entity, FocusHandle, Focusable, Image, Window, div, prelude::*, rgb};
 
-use editor::Editor;
+use buffer::{Buffer, FormatSpan, ImageBlock, ImageSize};
+use editor::{Editor, EditorEvent};
+
+use crate::{library::Library, note::NoteId};
 
 pub struct Pane {
     editor: Entity<Editor>,
+    library: Entity<Library>,
+    /// The note open in the editor.
+    active_note: NoteId,
     focus_handle: FocusHandle,
 }
 
 impl Pane {
-    pub fn new(cx: &mut Context<Self>) -> Self {
-        Self {
-            editor: cx.new(Editor::new),
+    pub fn new(library: Entity<Library>, cx: &mut Context<Self>) -> Self {
+        let editor = cx.new(Editor::new);
+        // The most recently modified note is opened, or a new one if there are none yet
+        let last_modified_note = library
+            .read(cx)
+            .notes()
+            .iter()
+            .max_by_key(|note| note.metadata.modified)
+            .map(|note| (note.id, note.buffer.clone()));
+        let active_note = match last_modified_note {
+            Some((id, buffer)) => {
+                editor.update(cx, |editor, cx| editor.set_buffer(buffer, cx));
+                id
+            }
+            None => {
+                let buffer = editor.read(cx).buffer().clone();
+                library.update(cx, |library, cx| library.add_note(buffer, cx))
+            }
+        };
+        let base_dir = library.read(cx).directory().to_path_buf();
+        editor.update(cx, |editor, cx| editor.set_base_dir(Some(base_dir), cx));
+
+        cx.subscribe(&editor, |pane, _, event, cx| match event {
+            EditorEvent::OpenNote { title } => pane.open_note_titled(title, cx),
+            EditorEvent::InsertImage { image } => pane.insert_image(image, cx),
+        })
+        .detach();
+        cx.observe(&library, |pane, _, cx| pane.update_note_titles(cx))
+            .detach();
+
+        let mut pane = Self {
+            editor,
+            library,
+            active_note,
             focus_handle: cx.focus_handle(),
-        }
+        };
+        pane.update_note_titles(cx);
+        pane
+    }
+
+    pub fn editor(&self) -> &Entity<Editor> {
+        &self.editor
+    }
+
+    pub fn active_note(&self) -> NoteId {
+        self.active_note
+    }
+
+    /// Returns the buffer of the note open in the editor.
+    pub fn active_buffer(&self, cx: &App) -> Entity<Buffer> {
+        self.editor.read(cx).buffer().clone()
     }
 
     pub fn focus_editor(&self, window: &mut Window, cx: &App) {
         let focus_handle = self.editor.read(cx).focus_handle(cx);
         window.focus(&focus_handle);
     }
+
+    /// Opens a note from the library in the editor.
+    pub fn open_note(&mut self, id: NoteId, cx: &mut Context<Self>) {
+        let Some(note) = self.library.read(cx).note(id) else {
+            return;
+        };
+        if id == self.active_note {
+            return;
+        }
+
+        let buffer = note.buffer.clone();
+        let previous_note = self.active_note;
+        self.library.update(cx, |library, cx| {
+            library.autosave_note(previous_note, cx);
+        });
+        self.editor
+            .update(cx, |editor, cx| editor.set_buffer(buffer, cx));
+        self.active_note = id;
+        self.update_note_titles(cx);
+        cx.notify();
+    }
+
+    /// Drops the text being dragged in the editor at the end of another note, moving it there
+    /// or copying it when `copy` is set. The text starts a new line, and keeps its formatting.
+    pub fn drop_dragged_text(&mut self, id: NoteId, copy: bool, cx: &mut Context<Self>) {
+        if id == self.active_note {
+            return;
+        }
+        let Some(note) = self.library.read(cx).note(id) else {
+            return;
+        };
+        let buffer = note.buffer.clone();
+        let Some((text, format_spans)) = self
+            .editor
+            .update(cx, |editor, cx| editor.take_dragged_text(copy, cx))
+        else {
+            return;
+        };
+
+        buffer.update(cx, |buffer, cx| {
+            let end = buffer.len();
+            let separator = if end == 0 || buffer.slice(end - 1..end) == "\n" {
+                ""
+            } else {
+                "\n"
+            };
+            let format_spans = format_spans
+                .into_iter()
+                .map(|span| FormatSpan {
+                    range: span.range.start + separator.len()..span.range.end + separator.len(),
+                    ..span
+                })
+                .collect::<Vec<_>>();
+            buffer.insert_formatted(end, &format!("{separator}{text}"), &format_spans, cx);
+        });
+    }
+
+    /// Opens the note a wiki link points to, creating it when no note has that title yet.
+    fn open_note_titled(&mut self, title: &str, cx: &mut Context<Self>) {
+        let id = match self.library.read(cx).find_by_title(title) {
+            Some(id) => id,
+            None => self
+                .library
+                .update(cx, |library, cx| library.create_note(title, cx)),
+        };
+        self.open_note(id, cx);
+    }
+
+    /// Stores a pasted or dropped image as an attachment and inserts it into the note.
+    fn insert_image(&mut self, image: &Image, cx: &mut Context<Self>) {
+        let Ok(source) = self
+            .library
+            .update(cx, |library, _| library.save_image(image))
+        else {
+            return;
+        };
+
+        let image = ImageBlock {
+            alt: String::new(),
+            source,
+            size: ImageSize::from_bytes(&image.bytes),
+        };
+        self.editor
+            .update(cx, |editor, cx| editor.insert_image(&image, cx));
+    }
+
+    /// Offers the titles of the other notes while typing wiki links.
+    fn update_note_titles(&mut self, cx: &mut Context<Self>) {
+        let library = self.library.read(cx);
+        let titles = library
+            .notes()
+            .iter()
+            .filter(|note| note.id != self.active_note && !note.title.is_empty())
+            .map(|note| note.title.clone())
+            .collect();
+        self.editor
+            .update(cx, |editor, _| editor.set_note_titles(titles));
+    }
 }
 
 impl Focusable for Pane {
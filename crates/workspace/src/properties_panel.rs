@@ -0,0 +1,138 @@
+use gpui::{Entity, Task, Window, div, prelude::*, px, rgb};
+use std::time::Duration;
+
+use buffer::BufferEvent;
+use editor::Editor;
+
+use crate::{
+    library::Library,
+    note::{NoteId, format_timestamp},
+    pane::Pane,
+};
+
+/// How long the properties have to go without edits before they're applied to the note.
+const APPLY_DELAY: Duration = Duration::from_millis(500);
+
+/// Shows when the note open in the pane was created and modified, and edits its title and other
+/// properties as `key: value` lines.
+pub struct PropertiesPanel {
+    library: Entity<Library>,
+    pane: Entity<Pane>,
+    editor: Entity<Editor>,
+    /// The note whose properties the editor holds.
+    note: Option<NoteId>,
+    /// Applies the edited properties once they haven't been edited for a moment.
+    pending_apply: Option<Task<()>>,
+}
+
+impl PropertiesPanel {
+    pub fn new(library: Entity<Library>, pane: Entity<Pane>, cx: &mut Context<Self>) -> Self {
+        let editor = cx.new(Editor::new);
+        editor.update(cx, |editor, _| editor.set_markdown_shortcuts(false));
+
+        let buffer = editor.read(cx).buffer().clone();
+        cx.subscribe(&buffer, |panel, _, event, cx| {
+            if let BufferEvent::Edited { .. } = event {
+                panel.schedule_apply(cx);
+            }
+        })
+        .detach();
+        cx.observe(&library, |_, _, cx| cx.notify()).detach();
+        cx.observe(&pane, |panel, _, cx| panel.load_properties(cx))
+            .detach();
+
+        let mut panel = Self {
+            library,
+            pane,
+            editor,
+            note: None,
+            pending_apply: None,
+        };
+        panel.load_properties(cx);
+        panel
+    }
+
+    /// Fills the editor with the properties of the note open in the pane, if it changed.
+    fn load_properties(&mut self, cx: &mut Context<Self>) {
+        let id = self.pane.read(cx).active_note();
+        if self.note == Some(id) {
+            return;
+        }
+        if self.pending_apply.is_some() {
+            self.apply_properties(cx);
+        }
+        let Some(note) = self.library.read(cx).note(id) else {
+            return;
+        };
+
+        self.note = Some(id);
+        let text = note.metadata.properties_text();
+        let buffer = self.editor.read(cx).buffer().clone();
+        buffer.update(cx, |buffer, cx| buffer.reload(&text, Vec::new(), cx));
+        cx.notify();
+    }
+
+    /// Applies the edited properties once they go without edits for [`APPLY_DELAY`], so that a
+    /// title being typed doesn't rename the note after every keystroke.
+    fn schedule_apply(&mut self, cx: &mut Context<Self>) {
+        self.pending_apply = Some(cx.spawn(async move |panel, cx| {
+            cx.background_executor().timer(APPLY_DELAY).await;
+            panel
+                .update(cx, |panel, cx| panel.apply_properties(cx))
+                .ok();
+        }));
+    }
+
+    /// Stores the edited properties in the note's metadata.
+    fn apply_properties(&mut self, cx: &mut Context<Self>) {
+        self.pending_apply = None;
+        let Some(id) = self.note else {
+            return;
+        };
+
+        let text = self.editor.read(cx).buffer().read(cx).text();
+        self.library
+            .update(cx, |library, cx| library.set_properties_text(id, &text, cx));
+    }
+}
+
+impl Render for PropertiesPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let metadata = self
+            .note
+            .and_then(|id| self.library.read(cx).note(id))
+            .map(|note| note.metadata.clone());
+        let timestamp_row = |label: &'static str, value: String| {
+            div()
+                .flex()
+                .flex_row()
+                .px_1()
+                .child(div().w(px(64.)).text_color(rgb(0x8a8a8a)).child(label))
+                .child(value)
+        };
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_0p5()
+            .p_2()
+            .child(div().pb_1().text_color(rgb(0x8a8a8a)).child("Properties"))
+            .when_some(metadata, |this, metadata| {
+                this.child(timestamp_row("Created", format_timestamp(metadata.created)))
+                    .child(timestamp_row(
+                        "Modified",
+                        format_timestamp(metadata.modified),
+                    ))
+            })
+            .child(
+                div()
+                    .h(px(96.))
+                    .mt_1()
+                    .rounded_sm()
+                    .border_1()
+                    .border_color(rgb(0x2a2a2a))
+                    .bg(rgb(0x1a1a1a))
+                    .child(self.editor.clone()),
+            )
+    }
+}
@@ -1,21 +1,49 @@
-use gpui::{Entity, FocusHandle, Focusable, Window, div, prelude::*, px, rgb};
+use gpui::{Entity, FocusHandle, Focusable, Subscription, Window, div, prelude::*, px, rgb};
 
+use buffer::LineEnding;
 use ui::{ButtonCommon, ButtonShape, ButtonSize, Clickable, IconButton, IconName};
 
-use crate::Workspace;
+use crate::{Workspace, pane::Pane};
 
 pub struct StatusBar {
     workspace: Entity<Workspace>,
+    /// Pane with the open note, whose line ending is shown.
+    pane: Entity<Pane>,
+    _buffer_subscription: Subscription,
     focus_handle: FocusHandle,
 }
 
 impl StatusBar {
-    pub fn new(workspace: Entity<Workspace>, cx: &mut Context<Self>) -> Self {
+    pub fn new(workspace: Entity<Workspace>, pane: Entity<Pane>, cx: &mut Context<Self>) -> Self {
+        // Follow the open note's buffer as the pane switches between notes
+        cx.observe(&pane, |status_bar, pane, cx| {
+            let buffer = pane.read(cx).active_buffer(cx);
+            status_bar._buffer_subscription = cx.observe(&buffer, |_, _, cx| cx.notify());
+            cx.notify();
+        })
+        .detach();
+        let buffer = pane.read(cx).active_buffer(cx);
+        let buffer_subscription = cx.observe(&buffer, |_, _, cx| cx.notify());
+
         Self {
             workspace,
+            pane,
+            _buffer_subscription: buffer_subscription,
             focus_handle: cx.focus_handle(),
         }
     }
+
+    /// Switches the open note between LF and CRLF line endings.
+    fn toggle_line_ending(&mut self, cx: &mut Context<Self>) {
+        let buffer = self.pane.read(cx).active_buffer(cx);
+        buffer.update(cx, |buffer, cx| {
+            let line_ending = match buffer.line_ending() {
+                LineEnding::Unix => LineEnding::Windows,
+                LineEnding::Windows => LineEnding::Unix,
+            };
+            buffer.set_line_ending(line_ending, cx);
+        });
+    }
 }
 
 impl Focusable for StatusBar {
@@ -27,6 +55,7 @@ impl Focusable for StatusBar {
 impl Render for StatusBar {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let workspace = self.workspace.clone();
+        let line_ending = self.pane.read(cx).active_buffer(cx).read(cx).line_ending();
 
         div()
             .track_focus(&self.focus_handle(cx))
@@ -48,5 +77,19 @@ impl Render for StatusBar {
                         workspace.update(cx, |w, cx| w.toggle_dock(window, cx))
                     }),
             )
+            .child(div().flex_1())
+            .child(
+                div()
+                    .id("line-ending")
+                    .px_1()
+                    .rounded_sm()
+                    .text_color(rgb(0x8a8a8a))
+                    .hover(|style| style.bg(rgb(0x2a2a2a)))
+                    .cursor_pointer()
+                    .child(line_ending.label())
+                    .on_click(cx.listener(|status_bar, _, _window, cx| {
+                        status_bar.toggle_line_ending(cx);
+                    })),
+            )
     }
 }
@@ -2,11 +2,21 @@ use gpui::{
     App, Bounds, DragMoveEvent, Entity, Focusable, Pixels, Point, Window, canvas, div, prelude::*,
     px, rgb,
 };
+use std::{io, path::PathBuf};
 
-use crate::{dock::Dock, pane::Pane, status_bar::StatusBar};
+use crate::{
+    backlinks_panel::BacklinksPanel, dock::Dock, library::Library, notes_panel::NotesPanel,
+    pane::Pane, properties_panel::PropertiesPanel, status_bar::StatusBar,
+};
 
+pub mod backlinks_panel;
 pub mod dock;
+pub mod library;
+pub mod note;
+mod note_index;
+pub mod notes_panel;
 pub mod pane;
+pub mod properties_panel;
 pub mod status_bar;
 
 const MIN_DOCK_WIDTH: Pixels = px(110.0);
@@ -19,6 +29,7 @@ pub enum DockPosition {
 }
 
 pub struct Workspace {
+    library: Entity<Library>,
     dock: Entity<Dock>,
     pane: Entity<Pane>,
     status_bar: Entity<StatusBar>,
@@ -36,23 +47,63 @@ impl Render for DraggedDock {
 }
 
 impl Workspace {
-    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+    /// Creates a workspace for the notes stored in a directory.
+    pub fn new(notes_dir: PathBuf, window: &mut Window, cx: &mut Context<Self>) -> Self {
         let workspace = cx.entity();
-        let pane = cx.new(Pane::new);
+        let library = cx.new(|cx| {
+            cx.on_app_quit(|library: &mut Library, cx| {
+                // Saved first, so that attachments the last edits refer to are kept. If a note
+                // couldn't be saved, its file may not refer to them yet, so they're all kept
+                match library.save_all(cx) {
+                    Ok(()) => {
+                        if let Err(error) = library.collect_garbage() {
+                            eprintln!("Couldn't remove unused attachments: {error}");
+                        }
+                    }
+                    Err(error) => eprintln!("Couldn't save every note: {error}"),
+                }
+                async {}
+            })
+            .detach();
+            let mut library = Library::new(notes_dir);
+            if let Err(error) = library.load(cx) {
+                eprintln!("Couldn't load every note: {error}");
+            }
+            library
+        });
+        let pane = cx.new(|cx| Pane::new(library.clone(), cx));
 
         pane.update(cx, |pane, cx| {
             pane.focus_editor(window, cx);
         });
 
+        let notes_panel = cx.new(|cx| NotesPanel::new(library.clone(), pane.clone(), cx));
+        let backlinks_panel = cx.new(|cx| BacklinksPanel::new(library.clone(), pane.clone(), cx));
+        let properties_panel = cx.new(|cx| PropertiesPanel::new(library.clone(), pane.clone(), cx));
+
         Self {
-            dock: cx.new(Dock::new),
+            library,
+            dock: cx.new(|cx| {
+                Dock::new(
+                    vec![
+                        notes_panel.into(),
+                        backlinks_panel.into(),
+                        properties_panel.into(),
+                    ],
+                    cx,
+                )
+            }),
+            status_bar: cx.new(|cx| StatusBar::new(workspace, pane.clone(), cx)),
             pane,
-            status_bar: cx.new(|cx| StatusBar::new(workspace, cx)),
             bounds: Bounds::default(),
             previous_dock_drag_coordinates: None,
         }
     }
 
+    pub fn library(&self) -> &Entity<Library> {
+        &self.library
+    }
+
     fn resize_dock(&mut self, size: Pixels, window: &mut Window, cx: &mut App) {
         let size = size
             .min(self.bounds.size.width - MIN_PANE_WIDTH)
@@ -146,3 +197,10 @@ impl Render for Workspace {
             .child(self.status_bar.clone())
     }
 }
+
+/// Returns the directory notes and their attachments are stored in, which is `~/Notes`.
+pub fn notes_dir() -> io::Result<PathBuf> {
+    let home_dir = std::env::var_os("HOME").filter(|home_dir| !home_dir.is_empty());
+    let home_dir = home_dir.ok_or_else(|| io::Error::other("HOME isn't set"))?;
+    Ok(PathBuf::from(home_dir).join("Notes"))
+}